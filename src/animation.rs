@@ -0,0 +1,362 @@
+//! Animating key images.
+//!
+//! The Stream Deck software shows only the first frame of an animated image set with
+//! [SetImage](../enum.MessageOut.html#variant.SetImage), so animations are played by sending each
+//! frame as its own image. Frames are given as images that have already been decoded and encoded
+//! as data URIs, for example with [`RgbaImage`](../image/struct.RgbaImage.html) when the `png`
+//! feature is enabled. With the `animated-image` feature, animated GIF and PNG files can be decoded into frames.
+
+#[cfg(feature = "png")]
+use crate::image::RgbaImage;
+use crate::{Context, ImagePayload, Message, MessageOut};
+use futures::channel::mpsc;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// Frames shown for this long or less are shown for `SHORT_DELAY_REPLACEMENT` instead, as web
+/// browsers do, because animated images often give no delay when they mean the default one.
+const SHORT_DELAY: Duration = Duration::from_millis(10);
+/// How long frames with a `SHORT_DELAY` are shown.
+const SHORT_DELAY_REPLACEMENT: Duration = Duration::from_millis(100);
+
+/// One image of an animation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// The image in the form of a data URI.
+    pub image: String,
+    /// How long the image is shown before the next frame.
+    pub delay: Duration,
+}
+
+/// A sequence of frames.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Animation {
+    frames: Vec<Frame>,
+    once: bool,
+}
+
+impl Animation {
+    /// Creates an animation with no frames which repeats forever.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a frame from a data URI.
+    pub fn frame<T: Into<String>>(mut self, image: T, delay: Duration) -> Self {
+        self.frames.push(Frame {
+            image: image.into(),
+            delay,
+        });
+        self
+    }
+
+    /// Adds a frame from a raw image.
+    #[cfg(feature = "png")]
+    pub fn image_frame(self, image: &RgbaImage, delay: Duration) -> Self {
+        self.frame(image.to_data_uri(), delay)
+    }
+
+    /// Plays the animation only once, leaving the last frame on the key.
+    pub fn once(mut self) -> Self {
+        self.once = true;
+        self
+    }
+
+    /// The frames of the animation.
+    pub fn frames(&self) -> &[Frame] {
+        &self.frames
+    }
+}
+
+#[cfg(feature = "animated-image")]
+impl Animation {
+    /// Decodes an animated GIF or PNG file, which repeats forever.
+    ///
+    /// A PNG file that is not animated becomes a single frame.
+    pub fn decode(bytes: &[u8]) -> Result<Self, crate::image::ImageError> {
+        decode(bytes, None)
+    }
+
+    /// Decodes an animated GIF or PNG file, scaling each frame to a new size such as the key image
+    /// size of a device.
+    pub fn decode_resized(
+        bytes: &[u8],
+        width: u32,
+        height: u32,
+    ) -> Result<Self, crate::image::ImageError> {
+        decode(bytes, Some((width, height)))
+    }
+}
+
+#[cfg(feature = "animated-image")]
+fn decode(bytes: &[u8], size: Option<(u32, u32)>) -> Result<Animation, crate::image::ImageError> {
+    use crate::image::ImageError;
+    use ::image::codecs::{gif::GifDecoder, png::PngDecoder};
+    use ::image::{AnimationDecoder, DynamicImage};
+    use std::convert::TryFrom;
+    use std::io::Cursor;
+
+    let frames = if bytes.starts_with(b"GIF8") {
+        GifDecoder::new(Cursor::new(bytes))
+            .and_then(|decoder| decoder.into_frames().collect_frames())
+    } else {
+        PngDecoder::new(Cursor::new(bytes)).and_then(|decoder| {
+            if decoder.is_apng()? {
+                decoder.apng()?.into_frames().collect_frames()
+            } else {
+                let image = DynamicImage::from_decoder(decoder)?.into_rgba8();
+                Ok(vec![::image::Frame::new(image)])
+            }
+        })
+    }
+    .map_err(ImageError::BadImage)?;
+
+    let mut animation = Animation::new();
+    for frame in frames {
+        let delay = Duration::from(frame.delay());
+        let image = match size {
+            Some((width, height)) => RgbaImage::from_image_resized(
+                &DynamicImage::ImageRgba8(frame.into_buffer()),
+                width,
+                height,
+            )?,
+            None => RgbaImage::try_from(frame.into_buffer())?,
+        };
+        animation = animation.image_frame(&image, delay);
+    }
+    Ok(animation)
+}
+
+/// Plays animations on keys by sending each frame through a channel.
+///
+/// Each animation runs as a tokio task, so the animator must be used within a tokio runtime. Only
+/// one animation plays for an instance of an action at a time, and it is stopped when the
+/// instance disappears if the animator [observes](#method.observe) the incoming messages. All
+/// animations are stopped when the animator is dropped.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use futures::channel::mpsc;
+/// # use serde_json::Value;
+/// # use std::time::Duration;
+/// # use streamdeck_rs::animation::{Animation, Animator};
+/// # use streamdeck_rs::{Message, MessageOut};
+/// # fn example(sender: mpsc::UnboundedSender<MessageOut<Value, Value, Value>>, message: &Message<Value, Value, Value>) {
+/// let mut animator = Animator::new(sender);
+/// animator.observe(message);
+/// if let Message::KeyDown { context, .. } = message {
+///     let animation = Animation::new()
+///         .frame("data:image/svg+xml,...", Duration::from_millis(100))
+///         .frame("data:image/svg+xml,...", Duration::from_millis(100));
+///     animator.play(context, animation);
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Animator<G, S, M> {
+    sender: mpsc::UnboundedSender<MessageOut<G, S, M>>,
+    running: HashMap<Context, JoinHandle<()>>,
+}
+
+impl<G, S, M> Animator<G, S, M>
+where
+    G: Send + 'static,
+    S: Send + 'static,
+    M: Send + 'static,
+{
+    /// Creates an animator that sends messages through a channel.
+    pub fn new(sender: mpsc::UnboundedSender<MessageOut<G, S, M>>) -> Self {
+        Animator {
+            sender,
+            running: HashMap::new(),
+        }
+    }
+
+    /// Starts playing an animation for an instance of an action, replacing any animation already
+    /// playing for it.
+    ///
+    /// Frames with a delay of 10 milliseconds or less are shown for 100 milliseconds, as in web
+    /// browsers, so that they do not flood the Stream Deck software with images.
+    pub fn play(&mut self, context: &str, animation: Animation) {
+        self.stop(context);
+        if animation.frames.is_empty() {
+            return;
+        }
+        let sender = self.sender.clone();
+        let owned_context = Context::from(context);
+        let frames = animation.frames;
+        let once = animation.once;
+        let task = tokio::spawn(async move {
+            loop {
+                for frame in frames.iter() {
+                    let sent = sender.unbounded_send(MessageOut::SetImage {
+                        context: owned_context.clone(),
+                        payload: ImagePayload::from_data_uri(frame.image.clone()),
+                    });
+                    if sent.is_err() {
+                        return;
+                    }
+                    let delay = if frame.delay <= SHORT_DELAY {
+                        SHORT_DELAY_REPLACEMENT
+                    } else {
+                        frame.delay
+                    };
+                    tokio::time::sleep(delay).await;
+                }
+                if once {
+                    return;
+                }
+            }
+        });
+        self.running.insert(Context::from(context), task);
+    }
+
+    /// Stops the animation for an instance of an action, leaving the current frame on the key.
+    pub fn stop(&mut self, context: &str) {
+        if let Some(task) = self.running.remove(context) {
+            task.abort();
+        }
+    }
+
+    /// Whether an animation is playing for an instance of an action.
+    pub fn is_playing(&self, context: &str) -> bool {
+        self.running
+            .get(context)
+            .is_some_and(|task| !task.is_finished())
+    }
+
+    /// Updates the animator with a message received from the Stream Deck software, stopping
+    /// animations for instances that have disappeared.
+    pub fn observe<MG, MS, MM>(&mut self, message: &Message<MG, MS, MM>) {
+        if let Message::WillDisappear { context, .. } = message {
+            self.stop(context);
+        }
+    }
+}
+
+impl<G, S, M> Drop for Animator<G, S, M> {
+    fn drop(&mut self) {
+        for task in self.running.values() {
+            task.abort();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Animation, Animator};
+    use crate::{Location, Message, MessageOut, VisibilityPayload};
+    use futures::channel::mpsc;
+    use futures::prelude::*;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    #[test]
+    fn animator() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (sender, mut receiver) = mpsc::unbounded::<MessageOut<Value, Value, Value>>();
+            let mut animator = Animator::new(sender);
+            let delay = Duration::from_millis(100);
+            let start = tokio::time::Instant::now();
+            animator.play("abc", Animation::new().frame("a", delay).frame("b", delay));
+
+            let mut images = Vec::new();
+            while images.len() < 3 {
+                match receiver.next().await {
+                    Some(MessageOut::SetImage { context, payload }) => {
+                        assert_eq!("abc", context);
+                        images.push(payload.image.unwrap());
+                    }
+                    _ => unreachable!(),
+                }
+            }
+            assert_eq!(vec!["a", "b", "a"], images);
+            // the clock only moves forward while the animation waits between frames
+            assert_eq!(delay * 2, start.elapsed());
+
+            let message: Message<Value, Value, Value> = Message::WillDisappear {
+                action: "com.example.action".parse().unwrap(),
+                context: "abc".into(),
+                device: None,
+                payload: VisibilityPayload {
+                    settings: Value::Null,
+                    location: Location::MultiAction,
+                    state: None,
+                    controller: None,
+                    extra: Default::default(),
+                },
+            };
+            animator.observe(&message);
+            assert!(!animator.is_playing("abc"));
+        });
+    }
+
+    #[test]
+    fn short_delay() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (sender, mut receiver) = mpsc::unbounded::<MessageOut<Value, Value, Value>>();
+            let mut animator = Animator::new(sender);
+            let start = tokio::time::Instant::now();
+            animator.play(
+                "abc",
+                Animation::new()
+                    .frame("a", Duration::ZERO)
+                    .frame("b", Duration::from_millis(10))
+                    .frame("c", Duration::from_millis(11)),
+            );
+            for _ in 0..4 {
+                receiver.next().await.unwrap();
+            }
+            assert_eq!(Duration::from_millis(211), start.elapsed());
+        });
+    }
+
+    #[cfg(feature = "animated-image")]
+    #[test]
+    fn decode() {
+        use crate::image::RgbaImage;
+        use ::image::codecs::gif::GifEncoder;
+        use ::image::{Delay, Frame, Rgba};
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut gif);
+            for (color, ms) in [([255, 0, 0, 255], 100), ([0, 0, 255, 255], 250)] {
+                let image = ::image::RgbaImage::from_pixel(4, 4, Rgba(color));
+                let delay = Delay::from_numer_denom_ms(ms, 1);
+                encoder
+                    .encode_frame(Frame::from_parts(image, 0, 0, delay))
+                    .unwrap();
+            }
+        }
+        let animation = Animation::decode(&gif).unwrap();
+        let delays: Vec<_> = animation.frames().iter().map(|frame| frame.delay).collect();
+        assert_eq!(
+            vec![Duration::from_millis(100), Duration::from_millis(250)],
+            delays
+        );
+        let resized = Animation::decode_resized(&gif, 72, 72).unwrap();
+        assert_eq!(
+            RgbaImage::new(72, 72, [0, 0, 255, 255].repeat(72 * 72))
+                .unwrap()
+                .to_data_uri(),
+            resized.frames()[1].image
+        );
+
+        let png = RgbaImage::new(1, 1, vec![0, 255, 0, 255]).unwrap().to_png();
+        assert_eq!(1, Animation::decode(&png).unwrap().frames().len());
+        assert!(Animation::decode(b"not an image").is_err());
+    }
+}
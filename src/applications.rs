@@ -0,0 +1,159 @@
+//! Matching the applications monitored by a plugin.
+//!
+//! The `ApplicationsToMonitor` section of the manifest lists applications separately for each
+//! platform: bundle identifiers on macOS and executable names on Windows. The
+//! [ApplicationDidLaunch](../enum.Message.html#variant.ApplicationDidLaunch) and
+//! [ApplicationDidTerminate](../enum.Message.html#variant.ApplicationDidTerminate) messages
+//! report whichever of those names applies to the current platform.
+
+use crate::Message;
+use serde_derive::{Deserialize, Serialize};
+
+/// Maps the platform-specific names of monitored applications to identifiers chosen by the plugin.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::applications::{ApplicationEvent, ApplicationMatcher};
+/// # use streamdeck_rs::{ApplicationPayload, Message};
+/// # use serde_json::Value;
+/// #[derive(Debug, PartialEq)]
+/// enum App {
+///     Discord,
+/// }
+///
+/// let matcher = ApplicationMatcher::new()
+///     .mac("com.hnc.Discord", App::Discord)
+///     .windows("Discord.exe", App::Discord);
+///
+/// let message: Message<Value, Value, Value> = Message::ApplicationDidLaunch {
+///     payload: ApplicationPayload {
+///         application: "discord.exe".to_string(),
+///         extra: Default::default(),
+///     },
+/// };
+/// assert_eq!(Some(ApplicationEvent::Launched(&App::Discord)), matcher.event(&message));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ApplicationMatcher<T> {
+    mac: Vec<(String, T)>,
+    windows: Vec<(String, T)>,
+}
+
+/// A monitored application launching or terminating.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApplicationEvent<'a, T> {
+    /// The application has launched.
+    Launched(&'a T),
+    /// The application has terminated.
+    Terminated(&'a T),
+}
+
+/// The `ApplicationsToMonitor` section of the manifest.
+///
+/// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/manifest/#applicationstomonitor)
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ApplicationsToMonitor {
+    /// The bundle identifiers of the applications to monitor on macOS.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub mac: Vec<String>,
+    /// The executable names of the applications to monitor on Windows.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub windows: Vec<String>,
+}
+
+impl<T> ApplicationMatcher<T> {
+    /// Creates a matcher with no applications.
+    pub fn new() -> Self {
+        ApplicationMatcher {
+            mac: Vec::new(),
+            windows: Vec::new(),
+        }
+    }
+
+    /// Adds a macOS application by its bundle identifier.
+    pub fn mac<N: Into<String>>(mut self, bundle_identifier: N, id: T) -> Self {
+        self.mac.push((bundle_identifier.into(), id));
+        self
+    }
+
+    /// Adds a Windows application by its executable name.
+    ///
+    /// Executable names are matched without regard to case.
+    pub fn windows<N: Into<String>>(mut self, executable: N, id: T) -> Self {
+        self.windows.push((executable.into(), id));
+        self
+    }
+
+    /// Finds the identifier for an application name reported by the Stream Deck software.
+    pub fn find(&self, application: &str) -> Option<&T> {
+        self.mac
+            .iter()
+            .find(|(name, _)| name == application)
+            .or_else(|| {
+                self.windows
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(application))
+            })
+            .map(|(_, id)| id)
+    }
+
+    /// Identifies the application in an `ApplicationDidLaunch` or `ApplicationDidTerminate` message.
+    ///
+    /// Returns `None` for other messages and for applications that were not added.
+    pub fn event<G, S, M>(&self, message: &Message<G, S, M>) -> Option<ApplicationEvent<'_, T>> {
+        match message {
+            Message::ApplicationDidLaunch { payload } => self
+                .find(&payload.application)
+                .map(ApplicationEvent::Launched),
+            Message::ApplicationDidTerminate { payload } => self
+                .find(&payload.application)
+                .map(ApplicationEvent::Terminated),
+            _ => None,
+        }
+    }
+
+    /// Lists the applications in the form used by the manifest.
+    pub fn applications_to_monitor(&self) -> ApplicationsToMonitor {
+        fn names<T>(entries: &[(String, T)]) -> Vec<String> {
+            let mut names: Vec<String> = Vec::with_capacity(entries.len());
+            for (name, _) in entries {
+                if !names.contains(name) {
+                    names.push(name.clone());
+                }
+            }
+            names
+        }
+
+        ApplicationsToMonitor {
+            mac: names(&self.mac),
+            windows: names(&self.windows),
+        }
+    }
+}
+
+impl<T> Default for ApplicationMatcher<T> {
+    fn default() -> Self {
+        ApplicationMatcher::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ApplicationMatcher;
+    use serde_json::json;
+
+    #[test]
+    fn manifest() {
+        let matcher = ApplicationMatcher::new()
+            .mac("com.apple.Music", 1)
+            .mac("com.spotify.client", 2)
+            .windows("Spotify.exe", 2);
+        assert_eq!(Some(&2), matcher.find("SPOTIFY.EXE"));
+        assert_eq!(None, matcher.find("com.apple.music"));
+        assert_eq!(
+            json!({ "mac": ["com.apple.Music", "com.spotify.client"], "windows": ["Spotify.exe"] }),
+            serde_json::to_value(matcher.applications_to_monitor()).unwrap()
+        );
+    }
+}
@@ -0,0 +1,451 @@
+//! Shorthand for constructing messages to send to the Stream Deck software.
+//!
+//! Messages without options are constructed directly by functions on
+//! [`MessageOut`](../enum.MessageOut.html), and messages with options return a builder which is
+//! finished with `build`.
+//!
+//! # Examples
+//!
+//! ```
+//! # use serde_json::Value;
+//! # use streamdeck_rs::MessageOut;
+//! let message: MessageOut<Value, Value, Value> = MessageOut::set_title("context")
+//!     .text("Hi")
+//!     .hardware_only()
+//!     .state(1)
+//!     .build();
+//! ```
+
+use crate::feedback::{FeedbackPayload, FeedbackValue, Layout};
+use crate::{
+    Context, DeviceId, ImagePayload, LogMessagePayload, MessageOut, ProfilePayload,
+    SetFeedbackLayoutPayload, SetTriggerDescriptionPayload, StatePayload, Target, TitlePayload,
+    TriggerDescription, UrlPayload,
+};
+use std::marker::PhantomData;
+
+/// Marks a builder as producing messages of a type without owning any values of that type.
+type Builds<G, S, M> = PhantomData<fn() -> MessageOut<G, S, M>>;
+
+impl<G, S, M> MessageOut<G, S, M> {
+    /// Starts a [SetTitle](enum.MessageOut.html#variant.SetTitle) message, which reverts to the
+    /// title chosen by the user unless [`text`](builder/struct.SetTitle.html#method.text) is set.
+    pub fn set_title<C: Into<Context>>(context: C) -> SetTitle<G, S, M> {
+        SetTitle {
+            _message: PhantomData,
+            context: context.into(),
+            payload: TitlePayload::reset(),
+        }
+    }
+
+    /// Starts a [SetImage](enum.MessageOut.html#variant.SetImage) message, which reverts to the
+    /// image chosen by the user unless an image is set.
+    pub fn set_image<C: Into<Context>>(context: C) -> SetImage<G, S, M> {
+        SetImage {
+            _message: PhantomData,
+            context: context.into(),
+            payload: ImagePayload::reset(),
+        }
+    }
+
+    /// Creates a [ShowAlert](enum.MessageOut.html#variant.ShowAlert) message.
+    pub fn show_alert<C: Into<Context>>(context: C) -> Self {
+        MessageOut::ShowAlert {
+            context: context.into(),
+        }
+    }
+
+    /// Creates a [ShowOk](enum.MessageOut.html#variant.ShowOk) message.
+    pub fn show_ok<C: Into<Context>>(context: C) -> Self {
+        MessageOut::ShowOk {
+            context: context.into(),
+        }
+    }
+
+    /// Creates a [GetSettings](enum.MessageOut.html#variant.GetSettings) message.
+    pub fn get_settings<C: Into<Context>>(context: C) -> Self {
+        MessageOut::GetSettings {
+            context: context.into(),
+        }
+    }
+
+    /// Creates a [SetSettings](enum.MessageOut.html#variant.SetSettings) message.
+    pub fn set_settings<C: Into<Context>>(context: C, settings: S) -> Self {
+        MessageOut::SetSettings {
+            context: context.into(),
+            payload: settings,
+        }
+    }
+
+    /// Creates a [SetState](enum.MessageOut.html#variant.SetState) message.
+    pub fn set_state<C: Into<Context>>(context: C, state: u8) -> Self {
+        MessageOut::SetState {
+            context: context.into(),
+            payload: StatePayload::new(state),
+        }
+    }
+
+    /// Creates a [SendToPropertyInspector](enum.MessageOut.html#variant.SendToPropertyInspector)
+    /// message.
+    pub fn send_to_property_inspector<C: Into<Context>>(context: C, message: M) -> Self {
+        MessageOut::SendToPropertyInspector {
+            action: None,
+            context: context.into(),
+            payload: message,
+        }
+    }
+
+    /// Starts a [SwitchToProfile](enum.MessageOut.html#variant.SwitchToProfile) message.
+    ///
+    /// An empty profile name returns to the previously active profile.
+    pub fn switch_to_profile<C, D, P>(context: C, device: D, profile: P) -> SwitchToProfile<G, S, M>
+    where
+        C: Into<Context>,
+        D: Into<DeviceId>,
+        P: Into<String>,
+    {
+        SwitchToProfile {
+            _message: PhantomData,
+            context: context.into(),
+            device: device.into(),
+            payload: ProfilePayload::new(profile),
+        }
+    }
+
+    /// Creates an [OpenUrl](enum.MessageOut.html#variant.OpenUrl) message.
+    pub fn open_url<U: Into<String>>(url: U) -> Self {
+        MessageOut::OpenUrl {
+            payload: UrlPayload::new(url),
+        }
+    }
+
+    /// Creates a [GetGlobalSettings](enum.MessageOut.html#variant.GetGlobalSettings) message.
+    pub fn get_global_settings<C: Into<Context>>(context: C) -> Self {
+        MessageOut::GetGlobalSettings {
+            context: context.into(),
+        }
+    }
+
+    /// Creates a [SetGlobalSettings](enum.MessageOut.html#variant.SetGlobalSettings) message.
+    pub fn set_global_settings<C: Into<Context>>(context: C, settings: G) -> Self {
+        MessageOut::SetGlobalSettings {
+            context: context.into(),
+            payload: settings,
+        }
+    }
+
+    /// Creates a [LogMessage](enum.MessageOut.html#variant.LogMessage) message.
+    pub fn log_message<T: Into<String>>(message: T) -> Self {
+        MessageOut::LogMessage {
+            payload: LogMessagePayload::new(message),
+        }
+    }
+
+    /// Starts a [SetFeedback](enum.MessageOut.html#variant.SetFeedback) message.
+    pub fn set_feedback<C: Into<Context>>(context: C) -> SetFeedback<G, S, M> {
+        SetFeedback {
+            _message: PhantomData,
+            context: context.into(),
+            payload: FeedbackPayload::new(),
+        }
+    }
+
+    /// Creates a [SetFeedbackLayout](enum.MessageOut.html#variant.SetFeedbackLayout) message.
+    pub fn set_feedback_layout<C: Into<Context>>(context: C, layout: Layout) -> Self {
+        MessageOut::SetFeedbackLayout {
+            context: context.into(),
+            payload: SetFeedbackLayoutPayload::new(layout),
+        }
+    }
+
+    /// Starts a [SetTriggerDescription](enum.MessageOut.html#variant.SetTriggerDescription)
+    /// message, which reverts to the descriptions in the manifest unless they are set.
+    pub fn set_trigger_description<C: Into<Context>>(context: C) -> SetTriggerDescription<G, S, M> {
+        SetTriggerDescription {
+            _message: PhantomData,
+            context: context.into(),
+            payload: SetTriggerDescriptionPayload::new(),
+        }
+    }
+}
+
+/// Builds a [SetTitle](../enum.MessageOut.html#variant.SetTitle) message.
+#[derive(Debug)]
+#[must_use]
+pub struct SetTitle<G, S, M> {
+    _message: Builds<G, S, M>,
+    context: Context,
+    payload: TitlePayload,
+}
+
+impl<G, S, M> SetTitle<G, S, M> {
+    /// Sets the title.
+    pub fn text<T: Into<String>>(mut self, title: T) -> Self {
+        self.payload.title = Some(title.into());
+        self
+    }
+
+    /// Sets the title for only one display.
+    pub fn target(mut self, target: Target) -> Self {
+        self.payload.target = target;
+        self
+    }
+
+    /// Sets the title only on the device.
+    pub fn hardware_only(self) -> Self {
+        self.target(Target::Hardware)
+    }
+
+    /// Sets the title only on the display within the Stream Deck software.
+    pub fn software_only(self) -> Self {
+        self.target(Target::Software)
+    }
+
+    /// Sets the title for only one state.
+    pub fn state(mut self, state: u8) -> Self {
+        self.payload.state = Some(state);
+        self
+    }
+
+    /// Finishes the message.
+    pub fn build(self) -> MessageOut<G, S, M> {
+        MessageOut::SetTitle {
+            context: self.context,
+            payload: self.payload,
+        }
+    }
+}
+
+/// Builds a [SetImage](../enum.MessageOut.html#variant.SetImage) message.
+#[derive(Debug)]
+#[must_use]
+pub struct SetImage<G, S, M> {
+    _message: Builds<G, S, M>,
+    context: Context,
+    payload: ImagePayload,
+}
+
+impl<G, S, M> SetImage<G, S, M> {
+    /// Sets the image from a data URI.
+    pub fn image<T: Into<String>>(mut self, data_uri: T) -> Self {
+        self.payload.image = Some(data_uri.into());
+        self
+    }
+
+    /// Sets the image from PNG data.
+    pub fn png(mut self, bytes: &[u8]) -> Self {
+        self.payload.image = ImagePayload::from_png_bytes(bytes).image;
+        self
+    }
+
+    /// Sets the image from the source of an SVG image.
+    pub fn svg(mut self, svg: &str) -> Self {
+        self.payload.image = ImagePayload::from_svg(svg).image;
+        self
+    }
+
+    /// Sets the image for only one display.
+    pub fn target(mut self, target: Target) -> Self {
+        self.payload.target = target;
+        self
+    }
+
+    /// Sets the image only on the device.
+    pub fn hardware_only(self) -> Self {
+        self.target(Target::Hardware)
+    }
+
+    /// Sets the image only on the display within the Stream Deck software.
+    pub fn software_only(self) -> Self {
+        self.target(Target::Software)
+    }
+
+    /// Sets the image for only one state.
+    pub fn state(mut self, state: u8) -> Self {
+        self.payload.state = Some(state);
+        self
+    }
+
+    /// Finishes the message.
+    pub fn build(self) -> MessageOut<G, S, M> {
+        MessageOut::SetImage {
+            context: self.context,
+            payload: self.payload,
+        }
+    }
+}
+
+/// Builds a [SwitchToProfile](../enum.MessageOut.html#variant.SwitchToProfile) message.
+#[derive(Debug)]
+#[must_use]
+pub struct SwitchToProfile<G, S, M> {
+    _message: Builds<G, S, M>,
+    context: Context,
+    device: DeviceId,
+    payload: ProfilePayload,
+}
+
+impl<G, S, M> SwitchToProfile<G, S, M> {
+    /// Shows a page of the profile other than the first.
+    pub fn page(mut self, page: u8) -> Self {
+        self.payload.page = Some(page);
+        self
+    }
+
+    /// Finishes the message.
+    pub fn build(self) -> MessageOut<G, S, M> {
+        MessageOut::SwitchToProfile {
+            context: self.context,
+            device: self.device,
+            payload: self.payload,
+        }
+    }
+}
+
+/// Builds a [SetFeedback](../enum.MessageOut.html#variant.SetFeedback) message.
+#[derive(Debug)]
+#[must_use]
+pub struct SetFeedback<G, S, M> {
+    _message: Builds<G, S, M>,
+    context: Context,
+    payload: FeedbackPayload,
+}
+
+impl<G, S, M> SetFeedback<G, S, M> {
+    /// Sets the value of an item in the layout.
+    pub fn item<K: Into<String>, V: Into<FeedbackValue>>(mut self, key: K, value: V) -> Self {
+        self.payload.insert(key, value);
+        self
+    }
+
+    /// Finishes the message.
+    pub fn build(self) -> MessageOut<G, S, M> {
+        MessageOut::SetFeedback {
+            context: self.context,
+            payload: self.payload,
+        }
+    }
+}
+
+/// Builds a [SetTriggerDescription](../enum.MessageOut.html#variant.SetTriggerDescription)
+/// message.
+#[derive(Debug)]
+#[must_use]
+pub struct SetTriggerDescription<G, S, M> {
+    _message: Builds<G, S, M>,
+    context: Context,
+    payload: SetTriggerDescriptionPayload,
+}
+
+impl<G, S, M> SetTriggerDescription<G, S, M> {
+    /// Describes the long-touch interaction with the touch display.
+    pub fn long_touch<T: Into<String>>(mut self, description: T) -> Self {
+        self.payload = self.payload.long_touch(description);
+        self
+    }
+
+    /// Describes the push interaction with the dial.
+    pub fn push<T: Into<String>>(mut self, description: T) -> Self {
+        self.payload = self.payload.push(description);
+        self
+    }
+
+    /// Describes the rotate interaction with the dial.
+    pub fn rotate<T: Into<String>>(mut self, description: T) -> Self {
+        self.payload = self.payload.rotate(description);
+        self
+    }
+
+    /// Describes the touch interaction with the touch display.
+    pub fn touch<T: Into<String>>(mut self, description: T) -> Self {
+        self.payload = self.payload.touch(description);
+        self
+    }
+
+    /// Replaces all of the descriptions.
+    pub fn description(mut self, description: TriggerDescription) -> Self {
+        self.payload = description;
+        self
+    }
+
+    /// Reverts the description of the long-touch interaction to the one in the manifest.
+    pub fn clear_long_touch(mut self) -> Self {
+        self.payload = self.payload.clear_long_touch();
+        self
+    }
+
+    /// Reverts the description of the push interaction to the one in the manifest.
+    pub fn clear_push(mut self) -> Self {
+        self.payload = self.payload.clear_push();
+        self
+    }
+
+    /// Reverts the description of the rotate interaction to the one in the manifest.
+    pub fn clear_rotate(mut self) -> Self {
+        self.payload = self.payload.clear_rotate();
+        self
+    }
+
+    /// Reverts the description of the touch interaction to the one in the manifest.
+    pub fn clear_touch(mut self) -> Self {
+        self.payload = self.payload.clear_touch();
+        self
+    }
+
+    /// Reverts all of the descriptions to those in the manifest.
+    pub fn clear_all(mut self) -> Self {
+        self.payload = self.payload.clear_all();
+        self
+    }
+
+    /// Finishes the message.
+    pub fn build(self) -> MessageOut<G, S, M> {
+        MessageOut::SetTriggerDescription {
+            context: self.context,
+            payload: self.payload,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::MessageOut;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn builders() {
+        let message: MessageOut<Value, Value, Value> = MessageOut::set_title("abc")
+            .text("Hi")
+            .hardware_only()
+            .state(1)
+            .build();
+        assert_eq!(
+            json!({
+                "event": "setTitle",
+                "context": "abc",
+                "payload": { "title": "Hi", "target": 1, "state": 1 },
+            }),
+            serde_json::to_value(&message).unwrap()
+        );
+
+        let message: MessageOut<Value, Value, Value> = MessageOut::set_feedback("abc")
+            .item("title", "Volume")
+            .item("indicator", 50.0)
+            .build();
+        assert_eq!(
+            json!({ "title": "Volume", "indicator": 50.0 }),
+            serde_json::to_value(&message).unwrap()["payload"]
+        );
+
+        let message: MessageOut<Value, Value, Value> = MessageOut::set_trigger_description("abc")
+            .rotate("Volume")
+            .push("Mute")
+            .touch("Skip")
+            .clear_touch()
+            .build();
+        assert_eq!(
+            json!({ "longTouch": null, "push": "Mute", "rotate": "Volume", "touch": null }),
+            serde_json::to_value(&message).unwrap()["payload"]
+        );
+    }
+}
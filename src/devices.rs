@@ -0,0 +1,214 @@
+//! Keeping track of the devices connected to the Stream Deck software.
+
+use crate::registration::RegistrationInfo;
+use crate::{Context, Device, DeviceId, DeviceType, Message, MessageOut, ProfilePayload};
+use std::collections::HashMap;
+
+/// The devices currently connected to the Stream Deck software.
+///
+/// The registry starts with the devices from the registration info and is kept up to date by
+/// passing it each message received from the Stream Deck software.
+#[derive(Debug, Default)]
+pub struct DeviceRegistry {
+    devices: HashMap<DeviceId, Device>,
+}
+
+impl DeviceRegistry {
+    /// Creates a registry with no devices.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a registry with the devices from the registration info.
+    pub fn from_info(info: &RegistrationInfo) -> Self {
+        let mut registry = Self::new();
+        for device in &info.devices {
+            if let Some(id) = &device.id {
+                registry.devices.insert(id.clone(), device.clone());
+            }
+        }
+        registry
+    }
+
+    /// Updates the registry with a message received from the Stream Deck software.
+    pub fn observe<G, S, M>(&mut self, message: &Message<G, S, M>) {
+        match message {
+            Message::DeviceDidConnect {
+                device,
+                device_info,
+            } => {
+                self.devices
+                    .insert(device.clone(), device_info.clone().with_id(device.clone()));
+            }
+            Message::DeviceDidDisconnect { device } => {
+                self.devices.remove(device);
+            }
+            _ => {}
+        }
+    }
+
+    /// Gets a connected device by ID.
+    pub fn get(&self, id: &str) -> Option<&Device> {
+        self.devices.get(id)
+    }
+
+    /// Iterates over the connected devices.
+    pub fn iter(&self) -> impl Iterator<Item = &Device> {
+        self.devices.values()
+    }
+
+    /// Iterates over the connected devices of a type.
+    pub fn of_type(&self, device_type: DeviceType) -> impl Iterator<Item = &Device> {
+        self.iter()
+            .filter(move |device| device._type == Some(device_type))
+    }
+
+    /// The number of connected devices.
+    pub fn len(&self) -> usize {
+        self.devices.len()
+    }
+
+    /// Checks whether there are no connected devices.
+    pub fn is_empty(&self) -> bool {
+        self.devices.is_empty()
+    }
+
+    /// Switches every connected device, or every connected device of a type, to a profile.
+    ///
+    /// `context` must be an instance of one of the plugin's actions. The profile must be one of
+    /// the profiles listed in the plugin's manifest.
+    ///
+    /// Returns the messages to send, along with a [`ProfileTakeover`](struct.ProfileTakeover.html)
+    /// which can later return the same devices to their previous profiles.
+    pub fn switch_to_profile<G, S, M>(
+        &self,
+        context: &str,
+        profile: &str,
+        device_type: Option<DeviceType>,
+    ) -> (ProfileTakeover, Vec<MessageOut<G, S, M>>) {
+        let devices: Vec<DeviceId> = self
+            .iter()
+            .filter(|device| device_type.is_none() || device._type == device_type)
+            .filter_map(|device| device.id.clone())
+            .collect();
+        let takeover = ProfileTakeover {
+            context: Context::from(context),
+            devices,
+        };
+        let messages = takeover.messages(profile);
+        (takeover, messages)
+    }
+}
+
+/// A group of devices that have been switched to a profile.
+///
+/// Created by [`DeviceRegistry::switch_to_profile`](struct.DeviceRegistry.html#method.switch_to_profile).
+#[derive(Clone, Debug)]
+pub struct ProfileTakeover {
+    context: Context,
+    devices: Vec<DeviceId>,
+}
+
+impl ProfileTakeover {
+    /// The IDs of the devices that were switched.
+    pub fn devices(&self) -> &[DeviceId] {
+        &self.devices
+    }
+
+    /// Creates the messages that return the devices to their previous profiles.
+    pub fn restore<G, S, M>(self) -> Vec<MessageOut<G, S, M>> {
+        self.messages("")
+    }
+
+    fn messages<G, S, M>(&self, profile: &str) -> Vec<MessageOut<G, S, M>> {
+        self.devices
+            .iter()
+            .map(|device| MessageOut::SwitchToProfile {
+                context: self.context.clone(),
+                device: device.clone(),
+                payload: ProfilePayload {
+                    profile: profile.to_string(),
+                    page: None,
+                },
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeviceRegistry;
+    use crate::{DeviceType, Message, MessageOut};
+    use serde_json::{json, Value};
+
+    fn connect(device: &str, device_type: u8) -> Message<Value, Value, Value> {
+        serde_json::from_value(json!({
+            "event": "deviceDidConnect",
+            "device": device,
+            "deviceInfo": {
+                "name": device,
+                "size": {"columns": 5, "rows": 3},
+                "type": device_type,
+            },
+        }))
+        .unwrap()
+    }
+
+    fn switches(messages: &[MessageOut<Value, Value, Value>]) -> Vec<(String, String)> {
+        let mut switches: Vec<_> = messages
+            .iter()
+            .map(|message| match message {
+                MessageOut::SwitchToProfile {
+                    context,
+                    device,
+                    payload,
+                } => {
+                    assert_eq!("a", &**context);
+                    (device.to_string(), payload.profile.clone())
+                }
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        switches.sort();
+        switches
+    }
+
+    #[test]
+    fn profile_takeover() {
+        let mut registry = DeviceRegistry::new();
+        registry.observe(&connect("one", 0));
+        registry.observe(&connect("two", 0));
+        registry.observe(&connect("mini", 1));
+        assert_eq!(3, registry.len());
+        assert_eq!(2, registry.of_type(DeviceType::StreamDeck).count());
+
+        let (takeover, messages) =
+            registry.switch_to_profile("a", "Game", Some(DeviceType::StreamDeck));
+        assert_eq!(
+            vec![
+                ("one".to_string(), "Game".to_string()),
+                ("two".to_string(), "Game".to_string()),
+            ],
+            switches(&messages)
+        );
+        assert_eq!(
+            vec![
+                ("one".to_string(), String::new()),
+                ("two".to_string(), String::new()),
+            ],
+            switches(&takeover.restore())
+        );
+
+        let (takeover, _) = registry.switch_to_profile::<Value, Value, Value>("a", "Game", None);
+        assert_eq!(3, takeover.devices().len());
+
+        registry.observe(
+            &serde_json::from_value::<Message<Value, Value, Value>>(
+                json!({"event": "deviceDidDisconnect", "device": "mini"}),
+            )
+            .unwrap(),
+        );
+        assert!(registry.get("mini").is_none());
+        assert_eq!(2, registry.len());
+    }
+}
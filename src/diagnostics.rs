@@ -0,0 +1,249 @@
+//! Collecting the state of a plugin for bug reports.
+
+use crate::devices::DeviceRegistry;
+use crate::metrics::Metrics;
+use crate::registration::RegistrationInfo;
+use crate::{ActionUuid, Context, Coordinates, DeviceId, Message};
+use serde::ser;
+use serde_json::{json, Value};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The number of errors kept by default.
+const DEFAULT_ERROR_LIMIT: usize = 10;
+
+/// Keeps track of the state of a plugin so that it can be exported for diagnostics.
+///
+/// The collector is kept up to date by passing it each message received from the Stream Deck
+/// software and each error encountered by the plugin. Settings are not included in the snapshot,
+/// as they may contain private information, but a hash of them is included so that changes can
+/// be noticed.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::diagnostics::Diagnostics;
+/// let mut diagnostics = Diagnostics::new();
+/// diagnostics.record_error(Some("context"), "could not connect");
+/// let snapshot = diagnostics.snapshot(None);
+/// assert_eq!("could not connect", snapshot["errors"][0]["message"]);
+/// ```
+#[derive(Debug)]
+pub struct Diagnostics {
+    devices: DeviceRegistry,
+    contexts: BTreeMap<Context, VisibleContext>,
+    errors: VecDeque<RecordedError>,
+    error_limit: usize,
+}
+
+#[derive(Debug)]
+struct VisibleContext {
+    action: ActionUuid,
+    device: Option<DeviceId>,
+    coordinates: Option<Coordinates>,
+    state: Option<u8>,
+    settings_hash: Option<String>,
+}
+
+#[derive(Debug)]
+struct RecordedError {
+    time: u64,
+    context: Option<String>,
+    message: String,
+}
+
+impl Diagnostics {
+    /// Creates a collector with no devices.
+    pub fn new() -> Self {
+        Diagnostics {
+            devices: DeviceRegistry::new(),
+            contexts: BTreeMap::new(),
+            errors: VecDeque::new(),
+            error_limit: DEFAULT_ERROR_LIMIT,
+        }
+    }
+
+    /// Creates a collector with the devices from the registration info.
+    pub fn from_info(info: &RegistrationInfo) -> Self {
+        Diagnostics {
+            devices: DeviceRegistry::from_info(info),
+            ..Self::new()
+        }
+    }
+
+    /// Sets the number of most recent errors to keep.
+    pub fn with_error_limit(mut self, limit: usize) -> Self {
+        self.error_limit = limit;
+        while self.errors.len() > limit {
+            self.errors.pop_front();
+        }
+        self
+    }
+
+    /// Updates the collector with a message received from the Stream Deck software.
+    pub fn observe<G, S: ser::Serialize, M>(&mut self, message: &Message<G, S, M>) {
+        self.devices.observe(message);
+        match message {
+            Message::WillAppear {
+                action,
+                context,
+                device,
+                payload,
+            } => {
+                self.contexts.insert(
+                    context.clone(),
+                    VisibleContext {
+                        action: action.clone(),
+                        device: device.clone(),
+                        coordinates: payload.location.coordinates(),
+                        state: payload.state,
+                        settings_hash: settings_hash(&payload.settings),
+                    },
+                );
+            }
+            Message::WillDisappear { context, .. } => {
+                self.contexts.remove(context);
+            }
+            Message::KeyDown {
+                context, payload, ..
+            }
+            | Message::KeyUp {
+                context, payload, ..
+            }
+            | Message::DidReceiveSettings {
+                context, payload, ..
+            } => {
+                if let Some(visible) = self.contexts.get_mut(context) {
+                    visible.state = payload.state;
+                    visible.settings_hash = settings_hash(&payload.settings);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Records an error encountered by the plugin, optionally for an instance of an action.
+    pub fn record_error<E: fmt::Display>(&mut self, context: Option<&str>, error: E) {
+        if self.error_limit == 0 {
+            return;
+        }
+        while self.errors.len() >= self.error_limit {
+            self.errors.pop_front();
+        }
+        self.errors.push_back(RecordedError {
+            time: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |time| time.as_secs()),
+            context: context.map(str::to_string),
+            message: error.to_string(),
+        });
+    }
+
+    /// Exports the collected state as JSON, along with the socket metrics if they are available.
+    pub fn snapshot(&self, metrics: Option<&Metrics>) -> Value {
+        let mut devices: Vec<_> = self.devices.iter().collect();
+        devices.sort_by(|a, b| a.id.cmp(&b.id));
+
+        let contexts: Vec<_> = self
+            .contexts
+            .iter()
+            .map(|(context, visible)| {
+                json!({
+                    "context": context,
+                    "action": visible.action,
+                    "device": visible.device,
+                    "coordinates": visible.coordinates,
+                    "state": visible.state,
+                    "settingsHash": visible.settings_hash,
+                })
+            })
+            .collect();
+
+        let errors: Vec<_> = self
+            .errors
+            .iter()
+            .map(|error| {
+                json!({
+                    "time": error.time,
+                    "context": error.context,
+                    "message": error.message,
+                })
+            })
+            .collect();
+
+        let metrics = metrics.map(|metrics| {
+            json!({
+                "pingRttMs": metrics.ping_rtt().map(|rtt| rtt.as_secs_f64() * 1000.0),
+                "pingsSent": metrics.pings_sent(),
+                "pongsReceived": metrics.pongs_received(),
+                "eventsReceived": metrics.iter_events_received().collect::<BTreeMap<_, _>>(),
+                "messagesSent": metrics.messages_sent(),
+                "decodeFailures": metrics.decode_failures(),
+                "encodeFailures": metrics.encode_failures(),
+                "bytesReceived": metrics.bytes_received(),
+                "bytesSent": metrics.bytes_sent(),
+                "queueDepth": metrics.queue_depth(),
+            })
+        });
+
+        json!({
+            "crateVersion": env!("CARGO_PKG_VERSION"),
+            "devices": devices,
+            "contexts": contexts,
+            "errors": errors,
+            "metrics": metrics,
+        })
+    }
+}
+
+impl Default for Diagnostics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn settings_hash<S: ser::Serialize>(settings: &S) -> Option<String> {
+    let json = serde_json::to_string(settings).ok()?;
+    let mut hasher = DefaultHasher::new();
+    json.hash(&mut hasher);
+    Some(format!("{:016x}", hasher.finish()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::Diagnostics;
+    use crate::{Coordinates, Location, Message, VisibilityPayload};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn snapshot() {
+        let mut diagnostics = Diagnostics::new().with_error_limit(1);
+        let message: Message<Value, Value, Value> = Message::WillAppear {
+            action: "com.example.action".parse().unwrap(),
+            context: "abc".into(),
+            device: Some("device".into()),
+            payload: VisibilityPayload {
+                settings: json!({ "secret": "hunter2" }),
+                location: Location::Coordinates(Coordinates::new(1, 2)),
+                state: Some(0),
+                controller: None,
+                extra: Default::default(),
+            },
+        };
+        diagnostics.observe(&message);
+        diagnostics.record_error(None, "first");
+        diagnostics.record_error(Some("abc"), "second");
+
+        let snapshot = diagnostics.snapshot(None);
+        assert_eq!(
+            json!({ "column": 1, "row": 2 }),
+            snapshot["contexts"][0]["coordinates"]
+        );
+        assert!(!snapshot.to_string().contains("hunter2"));
+        assert_eq!(1, snapshot["errors"].as_array().unwrap().len());
+        assert_eq!("second", snapshot["errors"][0]["message"]);
+    }
+}
@@ -0,0 +1,372 @@
+//! Running the plugin's handlers for messages received from the Stream Deck software.
+
+use crate::{Context, LogMessagePayload, Message, MessageOut};
+use failure::Fail;
+use futures::channel::mpsc;
+use futures::prelude::*;
+use futures::stream::FuturesUnordered;
+use std::any::Any;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use std::task::Poll;
+use tokio::sync::Semaphore;
+
+/// A handler panicked while handling a message.
+#[derive(Clone, Debug)]
+pub struct HandlerPanic {
+    /// The name of the event being handled.
+    pub event: String,
+    /// The instance of the action the message was for, if any.
+    pub context: Option<Context>,
+    /// The panic message.
+    pub message: String,
+}
+
+impl fmt::Display for HandlerPanic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "handler for {} panicked", self.event)?;
+        if let Some(context) = &self.context {
+            write!(f, " (context {})", context)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// Runs a handler for a message, containing any panic so that the rest of the plugin keeps
+/// running.
+///
+/// If the handler panics, the panic is written to the Stream Deck log and, if the message was for
+/// an instance of an action, an alert is shown on that instance. The messages are sent through
+/// `sender`, which is usually the same channel used by the plugin to send its own messages.
+///
+/// # Examples
+///
+/// ```
+/// # use futures::channel::mpsc;
+/// # use serde_json::Value;
+/// # use streamdeck_rs::{Message, MessageOut};
+/// # use streamdeck_rs::dispatch::run_isolated;
+/// # async fn example(message: Message<Value, Value, Value>, sender: mpsc::UnboundedSender<MessageOut<Value, Value, Value>>) {
+/// let result = run_isolated(message, &sender, |message| async move {
+///     if let Message::KeyDown { .. } = message {
+///         panic!("not implemented");
+///     }
+/// })
+/// .await;
+/// # }
+/// ```
+pub async fn run_isolated<G, S, M, GO, SO, MO, F, Fut>(
+    message: Message<G, S, M>,
+    sender: &mpsc::UnboundedSender<MessageOut<GO, SO, MO>>,
+    handler: F,
+) -> Result<Fut::Output, HandlerPanic>
+where
+    F: FnOnce(Message<G, S, M>) -> Fut,
+    Fut: Future,
+{
+    let event = message.event().to_string();
+    let context = message.context().cloned();
+
+    match AssertUnwindSafe(async move { handler(message).await })
+        .catch_unwind()
+        .await
+    {
+        Ok(output) => Ok(output),
+        Err(panic) => {
+            let panic = HandlerPanic {
+                event,
+                context,
+                message: panic_message(&*panic),
+            };
+            report(&panic, sender);
+            Err(panic)
+        }
+    }
+}
+
+fn report<GO, SO, MO>(
+    panic: &HandlerPanic,
+    sender: &mpsc::UnboundedSender<MessageOut<GO, SO, MO>>,
+) {
+    let _ = sender.unbounded_send(MessageOut::LogMessage {
+        payload: LogMessagePayload {
+            message: panic.to_string(),
+        },
+    });
+    if let Some(context) = &panic.context {
+        let _ = sender.unbounded_send(MessageOut::ShowAlert {
+            context: context.clone(),
+        });
+    }
+}
+
+/// Runs a handler for each message, keeping the messages for each context in order while
+/// allowing messages for different contexts to be handled concurrently.
+///
+/// A handler for one context that takes a long time to complete, such as one waiting on a network
+/// request, does not delay the handlers for other contexts. A message is not handled until the
+/// handler for the previous message with the same context has completed, so handlers do not race
+/// within a context. Messages that are not for a context, such as
+/// [DeviceDidConnect](../enum.Message.html#variant.DeviceDidConnect), are kept in order with each
+/// other.
+///
+/// At most `limit` handlers run at once. While the limit is reached, no more messages are taken
+/// from the stream.
+///
+/// The handlers run concurrently within the returned future rather than being spawned, so this
+/// works with any executor. Use [`run_isolated`](fn.run_isolated.html) within the handler to keep
+/// a panic from ending the dispatch.
+///
+/// The returned future completes when the stream ends and all handlers have completed.
+pub async fn for_each_by_context<St, G, S, M, F, Fut>(messages: St, limit: usize, mut handler: F)
+where
+    St: Stream<Item = Message<G, S, M>>,
+    F: FnMut(Message<G, S, M>) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    enum Event<T> {
+        Received(Option<T>),
+        Completed(Option<Context>),
+    }
+
+    let limit = limit.max(1);
+    let messages = messages.fuse();
+    futures::pin_mut!(messages);
+    let mut running = FuturesUnordered::new();
+    let mut queued: HashMap<Option<Context>, VecDeque<Message<G, S, M>>> = HashMap::new();
+    let mut input_done = false;
+
+    loop {
+        if input_done && running.is_empty() {
+            break;
+        }
+
+        let event = future::poll_fn(|cx| {
+            if let Poll::Ready(Some(key)) = running.poll_next_unpin(cx) {
+                return Poll::Ready(Event::Completed(key));
+            }
+            if !input_done && running.len() < limit {
+                if let Poll::Ready(message) = messages.as_mut().poll_next(cx) {
+                    return Poll::Ready(Event::Received(message));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+
+        match event {
+            Event::Received(Some(message)) => {
+                let key = message.context().cloned();
+                match queued.get_mut(&key) {
+                    // a handler is already running for this context
+                    Some(queue) => queue.push_back(message),
+                    None => {
+                        queued.insert(key.clone(), VecDeque::new());
+                        running.push(in_lane(handler(message), key));
+                    }
+                }
+            }
+            Event::Received(None) => input_done = true,
+            Event::Completed(key) => match queued.get_mut(&key).and_then(VecDeque::pop_front) {
+                Some(message) => running.push(in_lane(handler(message), key)),
+                None => {
+                    queued.remove(&key);
+                }
+            },
+        }
+    }
+}
+
+/// Runs a handler, then identifies the context it was for.
+async fn in_lane<Fut: Future<Output = ()>>(handler: Fut, key: Option<Context>) -> Option<Context> {
+    handler.await;
+    key
+}
+
+/// Runs blocking work, such as rendering images or reading files, on a bounded number of threads.
+///
+/// Handlers run on the same task as the socket, so work that blocks a handler also delays
+/// receiving and sending messages. The pool runs the work on Tokio's blocking threads and allows
+/// at most `limit` pieces of work to run at once, so a burst of work cannot take every blocking
+/// thread. Work waiting for a turn does not block anything.
+///
+/// The pool can be cloned cheaply, and clones share the same limit.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use streamdeck_rs::dispatch::BlockingPool;
+/// # async fn example() {
+/// let pool = BlockingPool::new(2);
+/// let image = pool.run(|| std::fs::read("key.png")).await;
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct BlockingPool {
+    permits: Arc<Semaphore>,
+}
+
+/// Work run by a [`BlockingPool`](struct.BlockingPool.html) panicked or was cancelled.
+#[derive(Debug, Fail)]
+#[fail(display = "blocking work failed: {}", _0)]
+pub struct BlockingError(pub String);
+
+impl BlockingPool {
+    /// Creates a pool that runs at most `limit` pieces of work at once.
+    pub fn new(limit: usize) -> Self {
+        BlockingPool {
+            permits: Arc::new(Semaphore::new(limit.max(1))),
+        }
+    }
+
+    /// Runs work on a blocking thread once the pool has room for it, returning the result.
+    ///
+    /// This must be called within a Tokio runtime.
+    pub async fn run<F, T>(&self, work: F) -> Result<T, BlockingError>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let permit = self
+            .permits
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("semaphore is never closed");
+        let result = tokio::task::spawn_blocking(move || {
+            let _permit = permit;
+            work()
+        })
+        .await;
+        match result {
+            Ok(output) => Ok(output),
+            Err(error) if error.is_panic() => {
+                Err(BlockingError(panic_message(&*error.into_panic())))
+            }
+            Err(error) => Err(BlockingError(error.to_string())),
+        }
+    }
+}
+
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{for_each_by_context, run_isolated, BlockingPool};
+    use crate::{Message, MessageOut};
+    use futures::channel::{mpsc, oneshot};
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use serde_json::Value;
+    use std::cell::RefCell;
+
+    #[test]
+    fn isolated_panic() {
+        let (sender, mut receiver) = mpsc::unbounded::<MessageOut<Value, Value, Value>>();
+        let message: Message<Value, Value, Value> = Message::PropertyInspectorDidAppear {
+            action: "com.example.plugin.action".parse().unwrap(),
+            context: "context".into(),
+            device: "device".into(),
+        };
+
+        let result = block_on(run_isolated(message, &sender, |_| async {
+            panic!("oops");
+        }));
+        let panic = result.expect_err("panic");
+        assert_eq!("propertyInspectorDidAppear", panic.event);
+        assert_eq!(Some("context"), panic.context.as_deref());
+        assert_eq!("oops", panic.message);
+
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(MessageOut::LogMessage { .. })
+        ));
+        assert!(matches!(
+            receiver.try_recv(),
+            Ok(MessageOut::ShowAlert { .. })
+        ));
+    }
+
+    #[test]
+    fn ordered_by_context() {
+        let message = |context: &str, action: &str| -> Message<Value, Value, Value> {
+            Message::PropertyInspectorDidAppear {
+                action: action.parse().unwrap(),
+                context: context.into(),
+                device: "device".into(),
+            }
+        };
+        let messages = vec![
+            message("a", "com.example.1"),
+            message("a", "com.example.2"),
+            message("b", "com.example.3"),
+            message("a", "com.example.4"),
+        ];
+
+        // a's first handler can only complete after b's handler runs.
+        let (sender, receiver) = oneshot::channel::<()>();
+        let sender = RefCell::new(Some(sender));
+        let receiver = RefCell::new(Some(receiver));
+        let handled = RefCell::new(Vec::new());
+
+        block_on(for_each_by_context(stream::iter(messages), 2, |message| {
+            let action = match &message {
+                Message::PropertyInspectorDidAppear { action, .. } => action.clone(),
+                _ => unreachable!(),
+            };
+            let receiver = if action == "com.example.1" {
+                receiver.borrow_mut().take()
+            } else {
+                None
+            };
+            let sender = if action == "com.example.3" {
+                sender.borrow_mut().take()
+            } else {
+                None
+            };
+            let handled = &handled;
+            async move {
+                if let Some(receiver) = receiver {
+                    receiver.await.unwrap();
+                }
+                handled.borrow_mut().push(action);
+                if let Some(sender) = sender {
+                    sender.send(()).unwrap();
+                }
+            }
+        }));
+
+        assert_eq!(
+            vec![
+                "com.example.3",
+                "com.example.1",
+                "com.example.2",
+                "com.example.4"
+            ],
+            handled.into_inner()
+        );
+    }
+
+    #[test]
+    fn blocking_pool() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        let pool = BlockingPool::new(1);
+        runtime.block_on(async {
+            let (a, b) = futures::join!(pool.run(|| 1 + 1), pool.run(|| panic!("boom")));
+            assert_eq!(2, a.unwrap());
+            assert_eq!("blocking work failed: boom", b.unwrap_err().to_string());
+        });
+    }
+}
@@ -0,0 +1,887 @@
+//! Types for the feedback displayed on the touch display of the Stream Deck +.
+//!
+//! [Official Documentation](https://docs.elgato.com/sdk/plugins/layouts-sd)
+
+use super::layout::{BarSubtype, Font, Range, TextAlignment, TextOverflow};
+use super::{Color, ParseColorError};
+use failure::Fail;
+use serde::{de, ser};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
+/// A layout for the touch display, sent with
+/// [SetFeedbackLayout](../enum.MessageOut.html#variant.SetFeedbackLayout).
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/layouts-sd#built-in-layouts)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Layout {
+    /// `$X1`: a title and a centered icon.
+    X1,
+    /// `$A0`: a title and an image filling the canvas.
+    A0,
+    /// `$A1`: a title, an icon, and a value.
+    A1,
+    /// `$B1`: a title, an icon, a value, and a bar.
+    B1,
+    /// `$B2`: a title, an icon, a value, and a gradient bar.
+    B2,
+    /// `$C1`: a title and two icons, each with a bar.
+    C1,
+    /// The path of a custom layout file, relative to the plugin.
+    Custom(String),
+}
+
+impl fmt::Display for Layout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Layout::X1 => "$X1",
+            Layout::A0 => "$A0",
+            Layout::A1 => "$A1",
+            Layout::B1 => "$B1",
+            Layout::B2 => "$B2",
+            Layout::C1 => "$C1",
+            Layout::Custom(path) => path,
+        })
+    }
+}
+
+impl FromStr for Layout {
+    type Err = Infallible;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "$X1" => Layout::X1,
+            "$A0" => Layout::A0,
+            "$A1" => Layout::A1,
+            "$B1" => Layout::B1,
+            "$B2" => Layout::B2,
+            "$C1" => Layout::C1,
+            path => Layout::Custom(path.to_string()),
+        })
+    }
+}
+
+/// Feedback for the items of a built-in layout.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::feedback::{B1Feedback, FeedbackPayload, Layout, StockFeedback};
+/// let feedback = B1Feedback {
+///     value: Some("75%".to_string()),
+///     indicator: Some(75.0),
+///     ..Default::default()
+/// };
+/// assert_eq!(Layout::B1, B1Feedback::LAYOUT);
+/// let payload = FeedbackPayload::from(feedback);
+/// assert_eq!(2, payload.iter().count());
+/// ```
+pub trait StockFeedback: Into<FeedbackPayload> {
+    /// The layout the items belong to.
+    const LAYOUT: Layout;
+}
+
+macro_rules! stock_feedback_type {
+    (text) => {
+        String
+    };
+    (image) => {
+        String
+    };
+    (bar) => {
+        f64
+    };
+}
+
+macro_rules! stock_feedback_value {
+    (text, $value:expr) => {
+        FeedbackValue::Text($value)
+    };
+    (image, $value:expr) => {
+        FeedbackValue::Image($value)
+    };
+    (bar, $value:expr) => {
+        FeedbackValue::Bar { value: $value }
+    };
+}
+
+macro_rules! stock_feedback {
+    ($(#[$meta:meta])* $name:ident, $layout:expr, {
+        $($(#[$field_meta:meta])* $field:ident: $key:literal => $kind:ident,)*
+    }) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug, Default, PartialEq)]
+        pub struct $name {
+            $($(#[$field_meta])* pub $field: Option<stock_feedback_type!($kind)>,)*
+        }
+
+        impl StockFeedback for $name {
+            const LAYOUT: Layout = $layout;
+        }
+
+        impl From<$name> for FeedbackPayload {
+            fn from(feedback: $name) -> Self {
+                let mut payload = FeedbackPayload::new();
+                $(
+                    if let Some(value) = feedback.$field {
+                        payload.insert($key, stock_feedback_value!($kind, value));
+                    }
+                )*
+                payload
+            }
+        }
+    };
+}
+
+stock_feedback!(
+    /// Feedback for the `$X1` layout.
+    X1Feedback, Layout::X1, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image in the center.
+        icon: "icon" => image,
+    }
+);
+
+stock_feedback!(
+    /// Feedback for the `$A0` layout.
+    A0Feedback, Layout::A0, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image filling the canvas.
+        full_canvas: "full-canvas" => image,
+    }
+);
+
+stock_feedback!(
+    /// Feedback for the `$A1` layout.
+    A1Feedback, Layout::A1, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image on the left.
+        icon: "icon" => image,
+        /// The text on the right.
+        value: "value" => text,
+    }
+);
+
+stock_feedback!(
+    /// Feedback for the `$B1` layout.
+    B1Feedback, Layout::B1, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image on the left.
+        icon: "icon" => image,
+        /// The text on the right.
+        value: "value" => text,
+        /// The position of the bar at the bottom, from 0 to 100.
+        indicator: "indicator" => bar,
+    }
+);
+
+stock_feedback!(
+    /// Feedback for the `$B2` layout.
+    B2Feedback, Layout::B2, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image on the left.
+        icon: "icon" => image,
+        /// The text on the right.
+        value: "value" => text,
+        /// The position of the gradient bar at the bottom, from 0 to 100.
+        indicator: "indicator" => bar,
+    }
+);
+
+stock_feedback!(
+    /// Feedback for the `$C1` layout.
+    C1Feedback, Layout::C1, {
+        /// The text at the top.
+        title: "title" => text,
+        /// The image beside the upper bar.
+        icon1: "icon1" => image,
+        /// The image beside the lower bar.
+        icon2: "icon2" => image,
+        /// The position of the upper bar, from 0 to 100.
+        indicator1: "indicator1" => bar,
+        /// The position of the lower bar, from 0 to 100.
+        indicator2: "indicator2" => bar,
+    }
+);
+
+/// The values to update for the items of a layout, sent with
+/// [SetFeedback](../enum.MessageOut.html#variant.SetFeedback).
+///
+/// Items that are not included keep their current values.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::feedback::{FeedbackPayload, FeedbackValue};
+/// let payload = FeedbackPayload::new()
+///     .with("title", "Volume")
+///     .with("value", "75%")
+///     .with("indicator", FeedbackValue::Bar { value: 75.0 });
+/// assert_eq!(
+///     r#"{"indicator":{"value":75.0},"title":"Volume","value":"75%"}"#,
+///     serde_json::to_string(&payload).unwrap()
+/// );
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct FeedbackPayload {
+    items: BTreeMap<String, FeedbackValue>,
+}
+
+impl FeedbackPayload {
+    /// Creates a payload that updates no items.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a value for the item with a key.
+    pub fn with<K: Into<String>, V: Into<FeedbackValue>>(mut self, key: K, value: V) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Sets the value for the item with a key, returning the previous value.
+    pub fn insert<K: Into<String>, V: Into<FeedbackValue>>(
+        &mut self,
+        key: K,
+        value: V,
+    ) -> Option<FeedbackValue> {
+        self.items.insert(key.into(), value.into())
+    }
+
+    /// Gets the value for the item with a key.
+    pub fn get(&self, key: &str) -> Option<&FeedbackValue> {
+        self.items.get(key)
+    }
+
+    /// Iterates over the keys and values of the items, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FeedbackValue)> {
+        self.items.iter().map(|(key, value)| (key.as_str(), value))
+    }
+
+    /// Checks whether the payload updates no items.
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Adds the updates from a later payload, as though both had been sent.
+    ///
+    /// Values in the later payload replace values for the same item, except that the properties
+    /// are combined when either value is given as an object. A value that is not an object sets
+    /// the `value` property of the item.
+    pub fn merge(&mut self, later: FeedbackPayload) {
+        for (key, value) in later.items {
+            let value = match (self.items.remove(&key), value) {
+                (Some(FeedbackValue::Object(mut properties)), later) => {
+                    properties.extend(later.into_properties());
+                    FeedbackValue::Object(properties)
+                }
+                (Some(earlier), FeedbackValue::Object(later)) => {
+                    let mut properties = earlier.into_properties();
+                    properties.extend(later);
+                    FeedbackValue::Object(properties)
+                }
+                (_, value) => value,
+            };
+            self.items.insert(key, value);
+        }
+    }
+}
+
+/// The value of an item in a [`FeedbackPayload`](struct.FeedbackPayload.html).
+#[derive(Clone, Debug, PartialEq)]
+pub enum FeedbackValue {
+    /// The text of a text item.
+    Text(String),
+    /// A number, which is displayed by a text item or sets the value of a bar item.
+    Number(f64),
+    /// The image of a pixmap item, as a data URI or a path relative to the plugin.
+    ///
+    /// Only data URIs are recognized as images when deserializing.
+    Image(String),
+    /// The value of a bar or gbar item.
+    Bar {
+        /// The position of the bar within its range.
+        value: f64,
+    },
+    /// Any of the properties of an item.
+    ///
+    /// The item structs such as [`Text`](struct.Text.html) and [`Bar`](struct.Bar.html) convert
+    /// into this.
+    Object(Map<String, Value>),
+}
+
+impl FeedbackValue {
+    /// The properties of the item that this sets.
+    fn into_properties(self) -> Map<String, Value> {
+        let value = match self {
+            FeedbackValue::Object(properties) => return properties,
+            FeedbackValue::Text(value) | FeedbackValue::Image(value) => Value::from(value),
+            FeedbackValue::Number(value) | FeedbackValue::Bar { value } => Value::from(value),
+        };
+        let mut properties = Map::new();
+        properties.insert("value".to_string(), value);
+        properties
+    }
+}
+
+impl From<&str> for FeedbackValue {
+    fn from(value: &str) -> Self {
+        FeedbackValue::Text(value.to_string())
+    }
+}
+
+impl From<String> for FeedbackValue {
+    fn from(value: String) -> Self {
+        FeedbackValue::Text(value)
+    }
+}
+
+impl From<f64> for FeedbackValue {
+    fn from(value: f64) -> Self {
+        FeedbackValue::Number(value)
+    }
+}
+
+impl From<i32> for FeedbackValue {
+    fn from(value: i32) -> Self {
+        FeedbackValue::Number(value.into())
+    }
+}
+
+macro_rules! item_feedback_value {
+    ($type:ty) => {
+        impl From<$type> for FeedbackValue {
+            fn from(item: $type) -> Self {
+                match serde_json::to_value(item) {
+                    Ok(Value::Object(properties)) => FeedbackValue::Object(properties),
+                    _ => unreachable!("items serialize to objects"),
+                }
+            }
+        }
+    };
+}
+
+item_feedback_value!(Text);
+item_feedback_value!(Pixmap);
+item_feedback_value!(Bar);
+item_feedback_value!(GBar);
+
+/// The properties of a text item.
+///
+/// This is used both for the item in a custom [layout](../layout/index.html) and, as a
+/// [`FeedbackValue`](enum.FeedbackValue.html), to update the item. Properties that are `None` are
+/// left unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Text {
+    /// The text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    /// The font of the text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub font: Option<Font>,
+    /// The color of the text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub color: Option<Color>,
+    /// The horizontal alignment of the text.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alignment: Option<TextAlignment>,
+    /// How text that does not fit is shown.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub text_overflow: Option<TextOverflow>,
+}
+
+/// The properties of a pixmap item.
+///
+/// This is used both for the item in a custom [layout](../layout/index.html) and, as a
+/// [`FeedbackValue`](enum.FeedbackValue.html), to update the item.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Pixmap {
+    /// The image, as a data URI or a path relative to the plugin.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+}
+
+/// The properties of a bar item, which shows a value within a range.
+///
+/// This is used both for the item in a custom [layout](../layout/index.html) and, as a
+/// [`FeedbackValue`](enum.FeedbackValue.html), to update the item. Properties that are `None` are
+/// left unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct Bar {
+    /// The value shown by the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// The range of values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    /// The shape of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<BarSubtype>,
+    /// The fill of the part of the bar representing the value.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_fill_c: Option<BarFill>,
+    /// The fill of the rest of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_bg_c: Option<BarFill>,
+    /// The color of the border of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_border_c: Option<Color>,
+    /// The width of the border of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_w: Option<u16>,
+    /// The height of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_h: Option<u16>,
+}
+
+/// The properties of a gbar item, which shows a value within a range with an indicator over a
+/// gradient.
+///
+/// This is used both for the item in a custom [layout](../layout/index.html) and, as a
+/// [`FeedbackValue`](enum.FeedbackValue.html), to update the item. Properties that are `None` are
+/// left unchanged.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct GBar {
+    /// The value shown by the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// The range of values.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<Range>,
+    /// The shape of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<BarSubtype>,
+    /// The gradient along the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_fill_c: Option<Gradient>,
+    /// The fill behind the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_bg_c: Option<BarFill>,
+    /// The color of the border of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_border_c: Option<Color>,
+    /// The width of the border of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub border_w: Option<u16>,
+    /// The height of the bar.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bar_h: Option<u16>,
+}
+
+impl From<Map<String, Value>> for FeedbackValue {
+    fn from(value: Map<String, Value>) -> Self {
+        FeedbackValue::Object(value)
+    }
+}
+
+impl ser::Serialize for FeedbackValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        match self {
+            FeedbackValue::Text(value) | FeedbackValue::Image(value) => {
+                serializer.serialize_str(value)
+            }
+            FeedbackValue::Number(value) => serializer.serialize_f64(*value),
+            FeedbackValue::Bar { value } => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry("value", value)?;
+                map.end()
+            }
+            FeedbackValue::Object(value) => value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de> de::Deserialize<'de> for FeedbackValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::String(value) if value.starts_with("data:") => Ok(FeedbackValue::Image(value)),
+            Value::String(value) => Ok(FeedbackValue::Text(value)),
+            Value::Number(value) => value
+                .as_f64()
+                .map(FeedbackValue::Number)
+                .ok_or_else(|| de::Error::custom("number out of range")),
+            Value::Object(value) => match (value.len(), value.get("value").and_then(Value::as_f64))
+            {
+                (1, Some(bar)) => Ok(FeedbackValue::Bar { value: bar }),
+                _ => Ok(FeedbackValue::Object(value)),
+            },
+            _ => Err(de::Error::custom("expected a string, number, or object")),
+        }
+    }
+}
+
+/// The fill of a bar, such as the `bar_fill_c` or `bar_bg_c` properties of a bar or gbar item.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BarFill {
+    /// The bar is filled with a single color.
+    Solid(Color),
+    /// The bar is filled with a gradient.
+    Gradient(Gradient),
+}
+
+impl From<Color> for BarFill {
+    fn from(value: Color) -> Self {
+        BarFill::Solid(value)
+    }
+}
+
+impl From<Gradient> for BarFill {
+    fn from(value: Gradient) -> Self {
+        BarFill::Gradient(value)
+    }
+}
+
+impl fmt::Display for BarFill {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BarFill::Solid(color) => color.fmt(f),
+            BarFill::Gradient(gradient) => gradient.fmt(f),
+        }
+    }
+}
+
+impl FromStr for BarFill {
+    type Err = GradientError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if value.contains(':') {
+            value.parse().map(BarFill::Gradient)
+        } else {
+            value
+                .parse()
+                .map(BarFill::Solid)
+                .map_err(GradientError::BadColor)
+        }
+    }
+}
+
+/// A color at a position along a [`Gradient`](struct.Gradient.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct GradientStop {
+    /// The position of the stop, from 0 at the start of the bar to 1 at the end.
+    pub position: f32,
+    /// The color at the position.
+    pub color: Color,
+}
+
+/// A gradient, written by the Stream Deck software as color stops such as `0:#ff0000,1:#00ff00`.
+///
+/// Gradients can only be created through [`Gradient::builder`](#method.builder) or by parsing,
+/// both of which check that the stops are valid.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::Color;
+/// # use streamdeck_rs::feedback::Gradient;
+/// let gradient = Gradient::builder()
+///     .stop(0.0, Color::Rgb { r: 0xff, g: 0, b: 0 })
+///     .stop(1.0, Color::Rgb { r: 0, g: 0xff, b: 0 })
+///     .build()
+///     .unwrap();
+/// assert_eq!("0:#ff0000,1:#00ff00", gradient.to_string());
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Gradient {
+    stops: Vec<GradientStop>,
+}
+
+impl Gradient {
+    /// Begins building a gradient.
+    pub fn builder() -> GradientBuilder {
+        GradientBuilder { stops: Vec::new() }
+    }
+
+    /// Creates a gradient with evenly spaced stops.
+    pub fn evenly_spaced<I: IntoIterator<Item = Color>>(colors: I) -> Result<Self, GradientError> {
+        let colors: Vec<Color> = colors.into_iter().collect();
+        let last = colors.len().saturating_sub(1).max(1) as f32;
+        colors
+            .into_iter()
+            .enumerate()
+            .fold(Gradient::builder(), |builder, (i, color)| {
+                builder.stop(i as f32 / last, color)
+            })
+            .build()
+    }
+
+    /// The stops of the gradient, in order.
+    pub fn stops(&self) -> &[GradientStop] {
+        &self.stops
+    }
+
+    fn validate(stops: Vec<GradientStop>) -> Result<Self, GradientError> {
+        if stops.len() < 2 {
+            return Err(GradientError::TooFewStops);
+        }
+        let mut previous = 0.0;
+        for stop in &stops {
+            if !(0.0..=1.0).contains(&stop.position) {
+                return Err(GradientError::PositionOutOfRange(stop.position));
+            }
+            if stop.position < previous {
+                return Err(GradientError::Unordered);
+            }
+            previous = stop.position;
+        }
+        Ok(Gradient { stops })
+    }
+}
+
+impl fmt::Display for Gradient {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, stop) in self.stops.iter().enumerate() {
+            if i != 0 {
+                f.write_str(",")?;
+            }
+            write!(f, "{}:{}", stop.position, stop.color)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Gradient {
+    type Err = GradientError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let stops = value
+            .split(',')
+            .map(|stop| {
+                let mut parts = stop.splitn(2, ':');
+                let position = parts.next().unwrap_or_default().trim();
+                let color = parts
+                    .next()
+                    .ok_or_else(|| GradientError::BadStop(stop.to_string()))?;
+                Ok(GradientStop {
+                    position: position
+                        .parse()
+                        .map_err(|_| GradientError::BadStop(stop.to_string()))?,
+                    color: color.trim().parse().map_err(GradientError::BadColor)?,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Gradient::validate(stops)
+    }
+}
+
+/// Builds a [`Gradient`](struct.Gradient.html).
+#[derive(Clone, Debug, Default)]
+pub struct GradientBuilder {
+    stops: Vec<GradientStop>,
+}
+
+impl GradientBuilder {
+    /// Adds a color stop at a position from 0 to 1.
+    ///
+    /// Stops must be added in order.
+    pub fn stop(mut self, position: f32, color: Color) -> Self {
+        self.stops.push(GradientStop { position, color });
+        self
+    }
+
+    /// Checks the stops and creates the gradient.
+    pub fn build(self) -> Result<Gradient, GradientError> {
+        Gradient::validate(self.stops)
+    }
+}
+
+/// An error in the definition of a [`Gradient`](struct.Gradient.html).
+#[derive(Debug, Fail)]
+pub enum GradientError {
+    /// A gradient must have at least two stops.
+    #[fail(display = "a gradient needs at least two stops")]
+    TooFewStops,
+    /// A stop was positioned outside of the bar.
+    #[fail(display = "gradient stop position {} is not between 0 and 1", _0)]
+    PositionOutOfRange(f32),
+    /// The stops were not in order.
+    #[fail(display = "gradient stops are not in order")]
+    Unordered,
+    /// A stop could not be parsed.
+    #[fail(display = "invalid gradient stop {:?}", _0)]
+    BadStop(String),
+    /// A color could not be parsed.
+    #[fail(display = "invalid color")]
+    BadColor(#[fail(cause)] ParseColorError),
+}
+
+macro_rules! string_serde {
+    ($type:ty, $expecting:expr) => {
+        impl ser::Serialize for $type {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: ser::Serializer,
+            {
+                serializer.collect_str(self)
+            }
+        }
+
+        impl<'de> de::Deserialize<'de> for $type {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: de::Deserializer<'de>,
+            {
+                struct Visitor;
+
+                impl<'de> de::Visitor<'de> for Visitor {
+                    type Value = $type;
+
+                    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                        formatter.write_str($expecting)
+                    }
+
+                    fn visit_str<E>(self, value: &str) -> Result<$type, E>
+                    where
+                        E: de::Error,
+                    {
+                        value.parse().map_err(E::custom)
+                    }
+                }
+
+                deserializer.deserialize_str(Visitor)
+            }
+        }
+    };
+}
+
+string_serde!(BarFill, "a hex color or gradient");
+string_serde!(Gradient, "a gradient");
+string_serde!(Layout, "a layout");
+
+#[cfg(test)]
+mod test {
+    use super::{Bar, BarFill, FeedbackPayload, FeedbackValue, Gradient, GradientError};
+    use crate::Color;
+
+    #[test]
+    fn feedback_payload() {
+        let payload: FeedbackPayload = serde_json::from_str(
+            r##"{"title":"CPU","icon":"data:image/png;base64,AAAA","indicator":{"value":20},"value":{"value":"20%","color":"#ff0000"},"count":3}"##,
+        )
+        .unwrap();
+        assert_eq!(
+            Some(&FeedbackValue::Text("CPU".to_string())),
+            payload.get("title")
+        );
+        assert!(matches!(payload.get("icon"), Some(FeedbackValue::Image(_))));
+        assert_eq!(
+            Some(&FeedbackValue::Bar { value: 20.0 }),
+            payload.get("indicator")
+        );
+        assert!(matches!(
+            payload.get("value"),
+            Some(FeedbackValue::Object(_))
+        ));
+        assert_eq!(Some(&FeedbackValue::Number(3.0)), payload.get("count"));
+
+        let bar = Bar {
+            value: Some(10.0),
+            bar_fill_c: Some(BarFill::Solid("#00ff00".parse().unwrap())),
+            ..Default::default()
+        };
+        assert_eq!(
+            r##"{"indicator":{"bar_fill_c":"#00ff00","value":10.0}}"##,
+            serde_json::to_string(&FeedbackPayload::new().with("indicator", bar)).unwrap()
+        );
+    }
+
+    #[test]
+    fn merge() {
+        let mut payload: FeedbackPayload =
+            serde_json::from_str(r##"{"indicator":{"value":10,"bar_fill_c":"#ff0000"}}"##).unwrap();
+        payload.merge(serde_json::from_str(r#"{"indicator":{"value":20}}"#).unwrap());
+        assert_eq!(
+            r##"{"indicator":{"bar_fill_c":"#ff0000","value":20.0}}"##,
+            serde_json::to_string(&payload).unwrap()
+        );
+
+        let mut payload: FeedbackPayload =
+            serde_json::from_str(r##"{"title":{"value":"x","color":"#ff0000"}}"##).unwrap();
+        payload.merge(serde_json::from_str(r#"{"title":"y"}"#).unwrap());
+        assert_eq!(
+            r##"{"title":{"color":"#ff0000","value":"y"}}"##,
+            serde_json::to_string(&payload).unwrap()
+        );
+
+        let mut payload = FeedbackPayload::new().with("title", "x");
+        payload.merge(serde_json::from_str(r##"{"title":{"color":"#ff0000"}}"##).unwrap());
+        assert_eq!(
+            r##"{"title":{"color":"#ff0000","value":"x"}}"##,
+            serde_json::to_string(&payload).unwrap()
+        );
+
+        let mut payload = FeedbackPayload::new().with("title", "x");
+        payload.merge(FeedbackPayload::new().with("title", "y"));
+        assert_eq!(
+            Some(&FeedbackValue::Text("y".to_string())),
+            payload.get("title")
+        );
+    }
+
+    #[test]
+    fn gradient() {
+        let fill: BarFill =
+            serde_json::from_str(r##""0:#ff0000,0.5:#ffff00,1:#00ff00""##).expect("gradient");
+        match &fill {
+            BarFill::Gradient(gradient) => assert_eq!(3, gradient.stops().len()),
+            _ => panic!("expected a gradient"),
+        }
+        assert_eq!(
+            r##""0:#ff0000,0.5:#ffff00,1:#00ff00""##,
+            serde_json::to_string(&fill).unwrap()
+        );
+
+        let fill: BarFill = serde_json::from_str(r##""#123456""##).expect("color");
+        assert_eq!(
+            BarFill::Solid(Color::Rgb {
+                r: 0x12,
+                g: 0x34,
+                b: 0x56
+            }),
+            fill
+        );
+
+        let red = Color::Rgb {
+            r: 0xff,
+            g: 0,
+            b: 0,
+        };
+        assert!(matches!(
+            Gradient::builder().stop(0.0, red.clone()).build(),
+            Err(GradientError::TooFewStops)
+        ));
+        assert!(matches!(
+            Gradient::builder()
+                .stop(0.5, red.clone())
+                .stop(0.25, red.clone())
+                .build(),
+            Err(GradientError::Unordered)
+        ));
+        assert!(matches!(
+            Gradient::builder()
+                .stop(0.0, red.clone())
+                .stop(1.5, red)
+                .build(),
+            Err(GradientError::PositionOutOfRange(_))
+        ));
+    }
+}
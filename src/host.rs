@@ -0,0 +1,243 @@
+//! Locating the Stream Deck software installed on this computer.
+//!
+//! These functions look in the default install locations used by the Stream Deck software. They
+//! are intended for installers and diagnostics tools. Plugins launched by the Stream Deck software
+//! should prefer the information in [`RegistrationInfo`](../registration/struct.RegistrationInfo.html),
+//! which also has methods for finding the directories of the Stream Deck software.
+
+use crate::registration::{Platform, RegistrationInfo};
+use std::path::{Path, PathBuf};
+
+/// Finds the installed Stream Deck application.
+///
+/// On Mac OS X, this is the application bundle. On Windows, this is the executable.
+///
+/// Returns `None` if the application is not installed in the default location or if the current
+/// platform is not supported by the Stream Deck software.
+pub fn application_path() -> Option<PathBuf> {
+    let path = default_application_path()?;
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Reads the version of the installed Stream Deck application.
+///
+/// On Mac OS X, the version is read from the application bundle. On Windows, it is the product
+/// version in the version resource of the executable.
+pub fn application_version() -> Option<String> {
+    let path = application_path()?;
+    read_version(&path)
+}
+
+/// Gets the directory where the Stream Deck software installs plugins.
+///
+/// The directory is not guaranteed to exist.
+pub fn plugins_directory() -> Option<PathBuf> {
+    plugins_directory_for(&current_platform()?)
+}
+
+/// Gets the directory where the Stream Deck software writes its logs.
+///
+/// The directory is not guaranteed to exist.
+pub fn logs_directory() -> Option<PathBuf> {
+    logs_directory_for(&current_platform()?)
+}
+
+/// Gets the directory where the Stream Deck software installs plugins on a platform, such as the
+/// [`platform`](../registration/struct.RegistrationInfoApplication.html#structfield.platform)
+/// from the registration info.
+///
+/// The directory is not guaranteed to exist.
+pub fn plugins_directory_for(platform: &Platform) -> Option<PathBuf> {
+    data_directory(platform).map(|path| path.join("Plugins"))
+}
+
+/// Gets the directory where the Stream Deck software writes its logs on a platform.
+///
+/// The directory is not guaranteed to exist.
+pub fn logs_directory_for(platform: &Platform) -> Option<PathBuf> {
+    match platform {
+        Platform::Mac => std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Logs")
+                .join("ElgatoStreamDeck")
+        }),
+        _ => data_directory(platform).map(|path| path.join("logs")),
+    }
+}
+
+impl RegistrationInfo {
+    /// Gets the directory where the Stream Deck software installs plugins.
+    pub fn plugins_directory(&self) -> Option<PathBuf> {
+        plugins_directory_for(&self.application.platform)
+    }
+
+    /// Gets the directory where the Stream Deck software writes its logs.
+    pub fn logs_directory(&self) -> Option<PathBuf> {
+        logs_directory_for(&self.application.platform)
+    }
+
+    /// Gets the directory the plugin is installed in, which contains its manifest and bundled
+    /// resources.
+    ///
+    /// Older versions of the Stream Deck software do not send the uuid of the plugin, so the
+    /// directory is not known.
+    pub fn plugin_directory(&self) -> Option<PathBuf> {
+        let uuid = self.plugin.uuid.as_ref()?;
+        self.plugins_directory()
+            .map(|path| path.join(format!("{}.sdPlugin", uuid)))
+    }
+}
+
+fn current_platform() -> Option<Platform> {
+    if cfg!(target_os = "macos") {
+        Some(Platform::Mac)
+    } else if cfg!(windows) {
+        Some(Platform::Windows)
+    } else {
+        None
+    }
+}
+
+fn data_directory(platform: &Platform) -> Option<PathBuf> {
+    match platform {
+        Platform::Mac => std::env::var_os("HOME").map(|home| {
+            PathBuf::from(home)
+                .join("Library")
+                .join("Application Support")
+                .join("com.elgato.StreamDeck")
+        }),
+        Platform::Windows => std::env::var_os("APPDATA")
+            .map(|appdata| PathBuf::from(appdata).join("Elgato").join("StreamDeck")),
+        Platform::Unknown(_) => None,
+    }
+}
+
+fn default_application_path() -> Option<PathBuf> {
+    #[cfg(target_os = "macos")]
+    {
+        Some(PathBuf::from("/Applications/Elgato Stream Deck.app"))
+    }
+    #[cfg(windows)]
+    {
+        let program_files =
+            std::env::var_os("ProgramW6432").or_else(|| std::env::var_os("ProgramFiles"))?;
+        Some(PathBuf::from(program_files).join("Elgato\\StreamDeck\\StreamDeck.exe"))
+    }
+    #[cfg(not(any(target_os = "macos", windows)))]
+    {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_version(application: &Path) -> Option<String> {
+    let plist = std::fs::read_to_string(application.join("Contents/Info.plist")).ok()?;
+    plist_string(&plist, "CFBundleShortVersionString")
+}
+
+#[cfg(windows)]
+fn read_version(application: &Path) -> Option<String> {
+    let executable = std::fs::read(application).ok()?;
+    product_version(&executable)
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn read_version(_application: &Path) -> Option<String> {
+    None
+}
+
+/// The signature of the `VS_FIXEDFILEINFO` structure in the version resource of an executable.
+const FIXED_FILE_INFO_SIGNATURE: u32 = 0xfeef_04bd;
+
+/// Finds the product version in the version resource of a Windows executable.
+#[cfg_attr(not(windows), allow(dead_code))]
+fn product_version(executable: &[u8]) -> Option<String> {
+    let dword = |offset: usize| {
+        let bytes = executable.get(offset..offset + 4)?;
+        Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    // the structure is aligned to four bytes, and is followed by its own version, which is 1.0
+    let offset = (0..executable.len()).step_by(4).find(|&offset| {
+        dword(offset) == Some(FIXED_FILE_INFO_SIGNATURE) && dword(offset + 4) == Some(0x1_0000)
+    })?;
+    let most = dword(offset + 16)?;
+    let least = dword(offset + 20)?;
+    Some(format!(
+        "{}.{}.{}.{}",
+        most >> 16,
+        most & 0xffff,
+        least >> 16,
+        least & 0xffff
+    ))
+}
+
+/// Finds the string value for a key in an XML property list.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn plist_string(plist: &str, key: &str) -> Option<String> {
+    let key = format!("<key>{}</key>", key);
+    let rest = plist[plist.find(&key)? + key.len()..].trim_start();
+    let rest = rest.strip_prefix("<string>")?;
+    let end = rest.find("</string>")?;
+    Some(rest[..end].trim().to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{plist_string, plugins_directory_for, product_version};
+    use crate::registration::Platform;
+
+    #[test]
+    fn plist() {
+        let plist = r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0">
+<dict>
+	<key>CFBundleName</key>
+	<string>Stream Deck</string>
+	<key>CFBundleShortVersionString</key>
+	<string>6.4.0</string>
+</dict>
+</plist>"#;
+        assert_eq!(
+            Some("6.4.0".to_string()),
+            plist_string(plist, "CFBundleShortVersionString")
+        );
+        assert_eq!(None, plist_string(plist, "CFBundleVersion"));
+    }
+
+    #[test]
+    fn directories() {
+        if let Some(path) = plugins_directory_for(&Platform::Mac) {
+            assert!(path.ends_with("Library/Application Support/com.elgato.StreamDeck/Plugins"));
+        }
+        assert_eq!(
+            None,
+            plugins_directory_for(&Platform::Unknown("linux".to_string()))
+        );
+    }
+
+    #[test]
+    fn windows_version() {
+        let mut executable = b"MZ\x90\0padding".to_vec();
+        executable.resize(64, 0);
+        for dword in [
+            0xfeef_04bd_u32,
+            0x1_0000,
+            0x6_0004,
+            0x1_0000,
+            0x6_0004,
+            0x4c2c,
+        ] {
+            executable.extend_from_slice(&dword.to_le_bytes());
+        }
+        assert_eq!(
+            Some("6.4.0.19500".to_string()),
+            product_version(&executable)
+        );
+        assert_eq!(None, product_version(b"MZ not a version resource"));
+    }
+}
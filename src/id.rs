@@ -0,0 +1,473 @@
+use failure::Fail;
+use serde::{de, ser};
+use serde_derive::{Deserialize, Serialize};
+use std::borrow::Borrow;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::str::FromStr;
+use std::sync::Arc;
+
+/// The most identifiers remembered by each thread so that repeated identifiers share memory.
+///
+/// A plugin sees one context for each instance of its actions and one device id for each device,
+/// so this is only reached if identifiers keep changing, in which case the table is emptied.
+const INTERNED_LIMIT: usize = 4096;
+
+thread_local! {
+    static INTERNED: RefCell<HashSet<Arc<str>>> = RefCell::new(HashSet::new());
+}
+
+/// Gets a shared copy of a string, allocating only if the string has not been seen recently.
+fn intern(value: &str) -> Arc<str> {
+    INTERNED.with(|interned| {
+        let mut interned = interned.borrow_mut();
+        if let Some(shared) = interned.get(value) {
+            return shared.clone();
+        }
+        if interned.len() >= INTERNED_LIMIT {
+            interned.clear();
+        }
+        let shared: Arc<str> = Arc::from(value);
+        interned.insert(shared.clone());
+        shared
+    })
+}
+
+/// An immutable string which is cheap to clone.
+///
+/// Strings received from the Stream Deck software are interned as they are read from the message
+/// text, so the identifiers in each message do not need their own allocations.
+#[derive(Clone)]
+enum SharedStr {
+    Static(&'static str),
+    Shared(Arc<str>),
+}
+
+impl SharedStr {
+    fn as_str(&self) -> &str {
+        match self {
+            SharedStr::Static(value) => value,
+            SharedStr::Shared(value) => value,
+        }
+    }
+}
+
+impl Default for SharedStr {
+    fn default() -> Self {
+        SharedStr::Static("")
+    }
+}
+
+impl From<String> for SharedStr {
+    fn from(value: String) -> Self {
+        SharedStr::Shared(Arc::from(value))
+    }
+}
+
+impl From<&str> for SharedStr {
+    fn from(value: &str) -> Self {
+        SharedStr::Shared(Arc::from(value))
+    }
+}
+
+impl Deref for SharedStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq for SharedStr {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for SharedStr {}
+
+impl PartialOrd for SharedStr {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SharedStr {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for SharedStr {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Debug for SharedStr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl ser::Serialize for SharedStr {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> de::Deserialize<'de> for SharedStr {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = SharedStr;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<SharedStr, E> {
+                Ok(SharedStr::Shared(intern(value)))
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
+}
+
+/// The uuid of an action, as declared in the plugin manifest.
+///
+/// Action uuids are written in reverse-DNS form, e.g. `com.example.plugin.action`.
+///
+/// Use the [`action_uuid!`](../macro.action_uuid.html) macro to create one from a literal that is
+/// checked at compile time.
+///
+/// Action uuids received from the Stream Deck software are not checked, so plugins whose manifests
+/// use other uuids still receive their messages.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct ActionUuid(SharedStr);
+
+impl ActionUuid {
+    /// Creates an action uuid from a static string.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `uuid` is not a well-formed action uuid. When used in a const context, such as
+    /// by [`action_uuid!`](../macro.action_uuid.html), this is a compile time error instead.
+    pub const fn from_static(uuid: &'static str) -> Self {
+        if !is_valid_action_uuid(uuid) {
+            panic!("action uuids must be lowercase reverse-DNS with at least three segments");
+        }
+        ActionUuid(SharedStr::Static(uuid))
+    }
+
+    /// Returns the uuid as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Checks whether a string is a well-formed action uuid.
+///
+/// A well-formed action uuid has at least three non-empty segments separated by `.` and contains
+/// only lowercase letters, digits, and `-`.
+pub const fn is_valid_action_uuid(uuid: &str) -> bool {
+    let bytes = uuid.as_bytes();
+    let mut segments = 1;
+    let mut segment_len = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'.' => {
+                if segment_len == 0 {
+                    return false;
+                }
+                segments += 1;
+                segment_len = 0;
+            }
+            b'a'..=b'z' | b'0'..=b'9' | b'-' => segment_len += 1,
+            _ => return false,
+        }
+        i += 1;
+    }
+    segment_len != 0 && segments >= 3
+}
+
+/// An error indicating a string is not a well-formed action uuid.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid action uuid: {}", _0)]
+pub struct InvalidActionUuid(pub String);
+
+impl TryFrom<String> for ActionUuid {
+    type Error = InvalidActionUuid;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if is_valid_action_uuid(&value) {
+            Ok(ActionUuid(SharedStr::from(value)))
+        } else {
+            Err(InvalidActionUuid(value))
+        }
+    }
+}
+
+impl FromStr for ActionUuid {
+    type Err = InvalidActionUuid;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ActionUuid::try_from(s.to_string())
+    }
+}
+
+impl Deref for ActionUuid {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for ActionUuid {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for ActionUuid {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<str> for ActionUuid {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for ActionUuid {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for ActionUuid {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Display for ActionUuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+macro_rules! string_id {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(Clone, Debug, Default, Eq, Hash, PartialEq, Ord, PartialOrd, Deserialize, Serialize)]
+        #[serde(transparent)]
+        pub struct $name(SharedStr);
+
+        impl $name {
+            /// Returns the identifier as a string slice.
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+
+            /// Converts the identifier into its string.
+            pub fn into_string(self) -> String {
+                self.0.to_string()
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(value: String) -> Self {
+                $name(SharedStr::from(value))
+            }
+        }
+
+        impl From<&str> for $name {
+            fn from(value: &str) -> Self {
+                $name(SharedStr::from(value))
+            }
+        }
+
+        impl From<&$name> for $name {
+            fn from(value: &$name) -> Self {
+                value.clone()
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.into_string()
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Borrow<str> for $name {
+            fn borrow(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.as_str() == *other
+            }
+        }
+
+        impl PartialEq<String> for $name {
+            fn eq(&self, other: &String) -> bool {
+                self.as_str() == other
+            }
+        }
+
+        impl PartialEq<$name> for &str {
+            fn eq(&self, other: &$name) -> bool {
+                *self == other.as_str()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+    };
+}
+
+string_id! {
+    /// The opaque identifier of an instance of an action.
+    ///
+    /// Each key or dial an action is placed on has its own context, which is used to address
+    /// messages to that instance. Contexts are cheap to clone, and contexts received from the
+    /// Stream Deck software share memory with earlier copies of the same context.
+    Context
+}
+
+string_id! {
+    /// The opaque identifier of a device.
+    DeviceId
+}
+
+impl PartialEq<ActionUuid> for &str {
+    fn eq(&self, other: &ActionUuid) -> bool {
+        *self == other.as_str()
+    }
+}
+
+/// Creates an [`ActionUuid`](id/struct.ActionUuid.html) from a string literal, checking at compile
+/// time that it is well-formed.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::{action_uuid, ActionUuid};
+/// const COUNTER: ActionUuid = action_uuid!("com.example.counter.increment");
+/// assert_eq!(COUNTER, "com.example.counter.increment");
+/// ```
+///
+/// Malformed uuids are rejected:
+///
+/// ```compile_fail
+/// # use streamdeck_rs::{action_uuid, ActionUuid};
+/// const COUNTER: ActionUuid = action_uuid!("Com.Example.Counter");
+/// ```
+#[macro_export]
+macro_rules! action_uuid {
+    ($uuid:literal) => {{
+        const UUID: $crate::ActionUuid = $crate::ActionUuid::from_static($uuid);
+        UUID
+    }};
+}
+
+#[cfg(test)]
+mod test {
+    use super::{is_valid_action_uuid, ActionUuid, Context, SharedStr};
+    use crate::Message;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    fn is_shared(a: &SharedStr, b: &SharedStr) -> bool {
+        match (a, b) {
+            (SharedStr::Shared(a), SharedStr::Shared(b)) => Arc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn action_uuid_validation() {
+        assert!(is_valid_action_uuid("com.example.plugin"));
+        assert!(is_valid_action_uuid("com.example-2.plugin.action"));
+        assert!(!is_valid_action_uuid("com.example"));
+        assert!(!is_valid_action_uuid("com.Example.plugin"));
+        assert!(!is_valid_action_uuid("com.example .plugin"));
+        assert!(!is_valid_action_uuid("com..example.plugin"));
+        assert!(!is_valid_action_uuid("com.example.plugin."));
+        assert!(!is_valid_action_uuid(""));
+
+        let uuid: ActionUuid = serde_json::from_str(r#""com.example.plugin""#).unwrap();
+        assert_eq!("com.example.plugin", uuid);
+        let uuid: ActionUuid = serde_json::from_str(r#""com.Example.my_plugin""#).unwrap();
+        assert_eq!("com.Example.my_plugin", uuid);
+        assert!("com.Example.my_plugin".parse::<ActionUuid>().is_err());
+    }
+
+    #[test]
+    fn context() {
+        let context: Context = serde_json::from_str(r#""abc""#).unwrap();
+        assert_eq!("abc", context);
+        assert_eq!(3, context.len());
+        assert_eq!(r#""abc""#, serde_json::to_string(&context).unwrap());
+
+        let again: Context = serde_json::from_str(r#""abc""#).unwrap();
+        assert!(is_shared(&context.0, &again.0));
+    }
+
+    #[test]
+    fn interned_messages() {
+        let json = r#"{"event":"dialRotate","action":"com.example.plugin.dial","context":"abc","device":"def","payload":{"settings":{},"coordinates":{"column":0,"row":0},"ticks":1,"pressed":false}}"#;
+        let identifiers = |message: Message<Value, Value, Value>| match message {
+            Message::DialRotate {
+                action,
+                context,
+                device,
+                ..
+            } => (action, context, device),
+            _ => unreachable!(),
+        };
+        let (action_a, context_a, device_a) = identifiers(Message::from_json(json).unwrap());
+        let (action_b, context_b, device_b) = identifiers(Message::from_json(json).unwrap());
+        assert!(is_shared(&action_a.0, &action_b.0));
+        assert!(is_shared(&context_a.0, &context_b.0));
+        assert!(is_shared(&device_a.0, &device_b.0));
+    }
+}
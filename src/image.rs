@@ -0,0 +1,408 @@
+//! Raw images which can be sent as key images or encoder feedback.
+//!
+//! With the `png` feature, raw images are encoded as PNG files, which every version of the Stream
+//! Deck software can display. With the `image` feature, images from the [image](https://docs.rs/image) crate can be converted
+//! to an [`RgbaImage`](struct.RgbaImage.html), or sent directly. With the `resvg` feature, SVG
+//! images can be rasterized at the size of a key for devices that display vector images poorly.
+
+#[cfg(feature = "png")]
+use super::feedback::FeedbackValue;
+#[cfg(feature = "png")]
+use super::ImagePayload;
+use failure::Fail;
+#[cfg(feature = "image")]
+use std::convert::TryFrom;
+
+#[cfg(feature = "png")]
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+/// The deflate compression level, which favors speed since images may be sent many times a second.
+#[cfg(feature = "png")]
+const COMPRESSION_LEVEL: u8 = 3;
+
+/// An error creating an image.
+#[derive(Debug, Fail)]
+pub enum ImageError {
+    /// The number of bytes does not match the size of the image.
+    #[fail(
+        display = "expected {} bytes for the image but got {}",
+        expected, actual
+    )]
+    SizeMismatch {
+        /// The number of bytes needed for the size of the image.
+        expected: usize,
+        /// The number of bytes given.
+        actual: usize,
+    },
+    /// The image has no pixels.
+    #[fail(display = "the image is empty")]
+    Empty,
+    /// The image file could not be decoded.
+    #[cfg(feature = "image")]
+    #[fail(display = "the image could not be decoded")]
+    BadImage(#[fail(cause)] ::image::ImageError),
+    /// The SVG image could not be parsed.
+    #[cfg(feature = "resvg")]
+    #[fail(display = "the SVG image could not be parsed")]
+    BadSvg(#[fail(cause)] resvg::usvg::Error),
+}
+
+/// An image made of 8-bit red, green, blue, and alpha channels, stored row by row from the top
+/// left corner.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::image::RgbaImage;
+/// # use streamdeck_rs::ImagePayload;
+/// let red = RgbaImage::new(2, 2, [255, 0, 0, 255].repeat(4)).unwrap();
+/// let payload = ImagePayload::from(red.resize(144, 144));
+/// assert!(payload.image.unwrap().starts_with("data:image/png;base64,"));
+/// ```
+#[cfg(feature = "png")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RgbaImage {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+#[cfg(feature = "png")]
+impl RgbaImage {
+    /// Creates an image from its pixels.
+    pub fn new(width: u32, height: u32, pixels: Vec<u8>) -> Result<Self, ImageError> {
+        if width == 0 || height == 0 {
+            return Err(ImageError::Empty);
+        }
+        let expected = width as usize * height as usize * 4;
+        if pixels.len() != expected {
+            return Err(ImageError::SizeMismatch {
+                expected,
+                actual: pixels.len(),
+            });
+        }
+        Ok(RgbaImage {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    /// The width of the image in pixels.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The height of the image in pixels.
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The pixels of the image.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// Scales the image to a new size using the nearest pixel, so that the Stream Deck software
+    /// does not need to rescale it.
+    ///
+    /// A size of zero is treated as one.
+    pub fn resize(&self, width: u32, height: u32) -> RgbaImage {
+        let (width, height) = (width.max(1), height.max(1));
+        if width == self.width && height == self.height {
+            return self.clone();
+        }
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height {
+            let source_y = (y as u64 * self.height as u64 / height as u64) as usize;
+            for x in 0..width {
+                let source_x = (x as u64 * self.width as u64 / width as u64) as usize;
+                let offset = (source_y * self.width as usize + source_x) * 4;
+                pixels.extend_from_slice(&self.pixels[offset..offset + 4]);
+            }
+        }
+        RgbaImage {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// Encodes the image as a PNG file.
+    pub fn to_png(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // 8 bits per channel, RGBA, deflate, standard filters, not interlaced.
+        header.extend_from_slice(&[8, 6, 0, 0, 0]);
+
+        let stride = self.width as usize * 4;
+        let mut scanlines = Vec::with_capacity((stride + 1) * self.height as usize);
+        for row in self.pixels.chunks(stride) {
+            // Each row starts with its filter type, which is always none.
+            scanlines.push(0);
+            scanlines.extend_from_slice(row);
+        }
+
+        let mut png = PNG_SIGNATURE.to_vec();
+        write_chunk(&mut png, b"IHDR", &header);
+        let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&scanlines, COMPRESSION_LEVEL);
+        write_chunk(&mut png, b"IDAT", &compressed);
+        write_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Encodes the image as a PNG data URI.
+    pub fn to_data_uri(&self) -> String {
+        format!("data:image/png;base64,{}", base64::encode(self.to_png()))
+    }
+}
+
+#[cfg(feature = "png")]
+impl From<RgbaImage> for ImagePayload {
+    fn from(image: RgbaImage) -> Self {
+        ImagePayload::from_data_uri(image.to_data_uri())
+    }
+}
+
+#[cfg(feature = "png")]
+impl From<RgbaImage> for FeedbackValue {
+    fn from(image: RgbaImage) -> Self {
+        FeedbackValue::Image(image.to_data_uri())
+    }
+}
+
+#[cfg(feature = "resvg")]
+impl RgbaImage {
+    /// Rasterizes an SVG image, stretching it to the given size.
+    ///
+    /// Text is drawn with the fonts installed on the system, which are loaded the first time an
+    /// image is rasterized.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::image::RgbaImage;
+    /// # use streamdeck_rs::ImagePayload;
+    /// let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 10 10">
+    ///     <rect width="10" height="10" fill="red"/>
+    /// </svg>"#;
+    /// let image = RgbaImage::from_svg(svg, 144, 144).unwrap();
+    /// assert_eq!(&[255, 0, 0, 255], &image.pixels()[..4]);
+    /// let payload = ImagePayload::from(image);
+    /// ```
+    pub fn from_svg(svg: &str, width: u32, height: u32) -> Result<Self, ImageError> {
+        use resvg::{tiny_skia, usvg};
+
+        let options = usvg::Options {
+            fontdb: system_fonts(),
+            ..usvg::Options::default()
+        };
+        let tree = usvg::Tree::from_str(svg, &options).map_err(ImageError::BadSvg)?;
+        let (width, height) = (width.max(1), height.max(1));
+        let mut pixmap = tiny_skia::Pixmap::new(width, height).ok_or(ImageError::Empty)?;
+        let size = tree.size();
+        let transform = tiny_skia::Transform::from_scale(
+            width as f32 / size.width(),
+            height as f32 / size.height(),
+        );
+        resvg::render(&tree, transform, &mut pixmap.as_mut());
+
+        // tiny-skia premultiplies the color channels by the alpha channel, and PNG does not
+        let mut pixels = Vec::with_capacity(width as usize * height as usize * 4);
+        for pixel in pixmap.pixels() {
+            let pixel = pixel.demultiply();
+            pixels.extend_from_slice(&[pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()]);
+        }
+        RgbaImage::new(width, height, pixels)
+    }
+}
+
+/// Loads the fonts installed on the system, once.
+#[cfg(feature = "resvg")]
+fn system_fonts() -> std::sync::Arc<resvg::usvg::fontdb::Database> {
+    use std::sync::{Arc, OnceLock};
+
+    static FONTS: OnceLock<Arc<resvg::usvg::fontdb::Database>> = OnceLock::new();
+    FONTS
+        .get_or_init(|| {
+            let mut fonts = resvg::usvg::fontdb::Database::new();
+            fonts.load_system_fonts();
+            Arc::new(fonts)
+        })
+        .clone()
+}
+
+#[cfg(feature = "image")]
+impl RgbaImage {
+    /// Converts an image from the image crate, scaling it to a new size with a smoothing filter.
+    ///
+    /// This gives better results than [`resize`](#method.resize) when shrinking photos or
+    /// detailed artwork to the size of a key.
+    pub fn from_image_resized(
+        image: &::image::DynamicImage,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, ImageError> {
+        if image.width() == 0 || image.height() == 0 {
+            return Err(ImageError::Empty);
+        }
+        let (width, height) = (width.max(1), height.max(1));
+        let image = image.resize_exact(width, height, ::image::imageops::FilterType::Triangle);
+        RgbaImage::try_from(image)
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<::image::RgbaImage> for RgbaImage {
+    type Error = ImageError;
+
+    fn try_from(image: ::image::RgbaImage) -> Result<Self, ImageError> {
+        RgbaImage::new(image.width(), image.height(), image.into_raw())
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<::image::DynamicImage> for RgbaImage {
+    type Error = ImageError;
+
+    fn try_from(image: ::image::DynamicImage) -> Result<Self, ImageError> {
+        RgbaImage::try_from(image.into_rgba8())
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<::image::DynamicImage> for ImagePayload {
+    type Error = ImageError;
+
+    fn try_from(image: ::image::DynamicImage) -> Result<Self, ImageError> {
+        RgbaImage::try_from(image).map(ImagePayload::from)
+    }
+}
+
+#[cfg(feature = "image")]
+impl TryFrom<::image::DynamicImage> for FeedbackValue {
+    type Error = ImageError;
+
+    fn try_from(image: ::image::DynamicImage) -> Result<Self, ImageError> {
+        RgbaImage::try_from(image).map(FeedbackValue::from)
+    }
+}
+
+#[cfg(feature = "png")]
+fn write_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+#[cfg(feature = "png")]
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(all(test, feature = "png"))]
+mod test {
+    use super::{ImageError, RgbaImage};
+
+    #[test]
+    fn png() {
+        let image = RgbaImage::new(1, 1, vec![255, 0, 0, 255]).unwrap();
+        let png = image.to_png();
+        assert_eq!(b"\x89PNG\r\n\x1a\n", &png[..8]);
+        assert_eq!(b"IHDR", &png[12..16]);
+        // Every IEND chunk has the same CRC.
+        assert_eq!([0xae, 0x42, 0x60, 0x82], png[png.len() - 4..]);
+
+        // the pixel data is compressed
+        let image = RgbaImage::new(144, 144, [255, 0, 0, 255].repeat(144 * 144)).unwrap();
+        let png = image.to_png();
+        assert!(png.len() < 1024, "{} bytes", png.len());
+        let idat = &png[33 + 8..png.len() - 16];
+        let scanlines = miniz_oxide::inflate::decompress_to_vec_zlib(idat).unwrap();
+        assert_eq!((144 * 4 + 1) * 144, scanlines.len());
+        assert_eq!(&[0, 255, 0, 0, 255], &scanlines[..5]);
+
+        let resized = image.resize(3, 2);
+        assert_eq!(3 * 2 * 4, resized.pixels().len());
+        assert_eq!(&[255, 0, 0, 255], &resized.pixels()[20..]);
+
+        assert!(matches!(
+            RgbaImage::new(2, 2, vec![0; 4]),
+            Err(ImageError::SizeMismatch {
+                expected: 16,
+                actual: 4
+            })
+        ));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn image_crate() {
+        use crate::ImagePayload;
+        use std::convert::TryFrom;
+
+        let image = ::image::RgbaImage::from_pixel(4, 2, ::image::Rgba([0, 0, 255, 255]));
+        let converted = RgbaImage::try_from(image.clone()).unwrap();
+        assert_eq!((4, 2), (converted.width(), converted.height()));
+        assert_eq!(image.as_raw(), converted.pixels());
+
+        let image = ::image::DynamicImage::ImageRgba8(image);
+        let resized = RgbaImage::from_image_resized(&image, 72, 72).unwrap();
+        assert_eq!((72, 72), (resized.width(), resized.height()));
+        assert_eq!(&[0, 0, 255, 255], &resized.pixels()[..4]);
+
+        let payload = ImagePayload::try_from(image).unwrap();
+        assert!(payload.image.unwrap().starts_with("data:image/png;base64,"));
+
+        // empty images cannot be encoded or resized
+        let empty = ::image::RgbaImage::new(0, 0);
+        assert!(matches!(
+            RgbaImage::try_from(empty.clone()),
+            Err(ImageError::Empty)
+        ));
+        let empty = ::image::DynamicImage::ImageRgba8(empty);
+        assert!(matches!(
+            RgbaImage::from_image_resized(&empty, 72, 72),
+            Err(ImageError::Empty)
+        ));
+        assert!(matches!(
+            ImagePayload::try_from(empty),
+            Err(ImageError::Empty)
+        ));
+    }
+
+    #[cfg(feature = "resvg")]
+    #[test]
+    fn svg() {
+        let svg = r#"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10">
+            <rect width="10" height="10" fill="blue" fill-opacity="0.5"/>
+        </svg>"#;
+        let image = RgbaImage::from_svg(svg, 72, 36).unwrap();
+        assert_eq!((72, 36), (image.width(), image.height()));
+        // the colors are not premultiplied
+        let left = &image.pixels()[..4];
+        assert_eq!(&[0, 0, 255], &left[..3]);
+        assert!((127..=128).contains(&left[3]), "{:?}", left);
+        // the right half is transparent
+        assert_eq!(0, image.pixels()[71 * 4 + 3]);
+
+        assert!(matches!(
+            RgbaImage::from_svg("not svg", 72, 72),
+            Err(ImageError::BadSvg(_))
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+//! Types for custom layout files for the touch display of the Stream Deck +.
+//!
+//! A custom layout is a JSON file within the plugin which describes the items drawn on the touch
+//! display for a dial. It is selected with
+//! [SetFeedbackLayout](../enum.MessageOut.html#variant.SetFeedbackLayout) using
+//! [`Layout::Custom`](../feedback/enum.Layout.html#variant.Custom), or with the `Layout` property of
+//! the encoder in the manifest.
+//!
+//! [Official Documentation](https://docs.elgato.com/sdk/plugins/layouts-sd#custom-layouts)
+
+use super::feedback::{Bar, BarFill, GBar, Pixmap, Text};
+use failure::Fail;
+use serde_derive::{Deserialize, Serialize};
+use serde_repr::{Deserialize_repr, Serialize_repr};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// The URL of the JSON schema for layout files.
+pub const SCHEMA: &str = "https://schemas.elgato.com/streamdeck/plugins/layout.json";
+
+/// The width of the area of the touch display for each dial, in pixels.
+pub const CANVAS_WIDTH: u16 = 200;
+/// The height of the area of the touch display for each dial, in pixels.
+pub const CANVAS_HEIGHT: u16 = 100;
+/// The highest allowed [`z_order`](struct.Item.html#structfield.z_order).
+pub const MAX_Z_ORDER: u16 = 700;
+
+/// The contents of a custom layout file.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct CustomLayout {
+    /// The JSON schema of the file, which allows editors to validate it.
+    #[serde(rename = "$schema", default, skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    /// The unique identifier of the layout.
+    pub id: String,
+    /// The items drawn on the touch display.
+    pub items: Vec<Item>,
+}
+
+impl CustomLayout {
+    /// Starts building a layout with an identifier.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::feedback::Text;
+    /// # use streamdeck_rs::layout::{CustomLayout, Item, ItemKind, Rect};
+    /// let layout = CustomLayout::builder("volume")
+    ///     .item(Item::new("title", Rect::new(16, 10, 168, 24), ItemKind::Text(Text::default())))
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(1, layout.items.len());
+    /// ```
+    pub fn builder<I: Into<String>>(id: I) -> CustomLayoutBuilder {
+        CustomLayoutBuilder {
+            layout: CustomLayout {
+                schema: Some(SCHEMA.to_string()),
+                id: id.into(),
+                items: Vec::new(),
+            },
+        }
+    }
+
+    /// Checks that the layout will be drawn by the Stream Deck software.
+    ///
+    /// The layout must have an identifier and at least one item, and each item must have a unique
+    /// key, fit within the canvas, and have a valid z order.
+    pub fn validate(&self) -> Result<(), LayoutError> {
+        if self.id.is_empty() {
+            return Err(LayoutError::MissingId);
+        }
+        if self.items.is_empty() {
+            return Err(LayoutError::NoItems);
+        }
+        let mut keys = HashSet::new();
+        for (index, item) in self.items.iter().enumerate() {
+            if item.key.is_empty() {
+                return Err(LayoutError::MissingKey(index));
+            }
+            if !keys.insert(item.key.as_str()) {
+                return Err(LayoutError::DuplicateKey(item.key.clone()));
+            }
+            let rect = item.rect;
+            if u32::from(rect.x) + u32::from(rect.width) > u32::from(CANVAS_WIDTH)
+                || u32::from(rect.y) + u32::from(rect.height) > u32::from(CANVAS_HEIGHT)
+            {
+                return Err(LayoutError::OutOfBounds(item.key.clone(), rect));
+            }
+            if item.z_order.is_some_and(|z_order| z_order > MAX_Z_ORDER) {
+                return Err(LayoutError::BadZOrder(item.key.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Validates the layout and writes it to a file.
+    ///
+    /// The path is usually within the plugin directory, such as `layouts/volume.json`, and the
+    /// same path relative to the plugin is used to select the layout. Missing directories are
+    /// created.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<(), LayoutError> {
+        self.validate()?;
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(LayoutError::Io)?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(LayoutError::Json)?;
+        fs::write(path, json).map_err(LayoutError::Io)
+    }
+}
+
+/// Builds a [`CustomLayout`](struct.CustomLayout.html).
+///
+/// Created by [`CustomLayout::builder`](struct.CustomLayout.html#method.builder).
+#[derive(Clone, Debug)]
+pub struct CustomLayoutBuilder {
+    layout: CustomLayout,
+}
+
+impl CustomLayoutBuilder {
+    /// Adds an item. Items are drawn in order unless they have a z order.
+    pub fn item(mut self, item: Item) -> Self {
+        self.layout.items.push(item);
+        self
+    }
+
+    /// Validates and returns the layout.
+    pub fn build(self) -> Result<CustomLayout, LayoutError> {
+        self.layout.validate()?;
+        Ok(self.layout)
+    }
+}
+
+/// An error that occurred validating or writing a custom layout.
+#[derive(Debug, Fail)]
+pub enum LayoutError {
+    /// The layout has no identifier.
+    #[fail(display = "layout has no id")]
+    MissingId,
+    /// The layout has no items.
+    #[fail(display = "layout has no items")]
+    NoItems,
+    /// The item at an index has no key.
+    #[fail(display = "item {} has no key", _0)]
+    MissingKey(usize),
+    /// More than one item has the same key.
+    #[fail(display = "more than one item has the key {}", _0)]
+    DuplicateKey(String),
+    /// An item does not fit within the canvas.
+    #[fail(display = "item {} at {:?} does not fit within the canvas", _0, _1)]
+    OutOfBounds(String, Rect),
+    /// An item has a z order greater than 700.
+    #[fail(display = "item {} has a z order greater than 700", _0)]
+    BadZOrder(String),
+    /// The layout could not be encoded.
+    #[fail(display = "could not encode layout")]
+    Json(#[fail(cause)] serde_json::Error),
+    /// The layout could not be written.
+    #[fail(display = "could not write layout")]
+    Io(#[fail(cause)] io::Error),
+}
+
+/// An item drawn on the touch display.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Item {
+    /// The key used to update the item with [SetFeedback](../enum.MessageOut.html#variant.SetFeedback).
+    pub key: String,
+    /// The area of the touch display covered by the item.
+    pub rect: Rect,
+    /// The order in which overlapping items are drawn, from 0 to 700. Items with higher values are
+    /// drawn on top.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub z_order: Option<u16>,
+    /// Whether the item is drawn.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub enabled: Option<bool>,
+    /// The opacity of the item, from 0 to 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f32>,
+    /// The fill behind the item.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<BarFill>,
+    /// The type of the item and the properties specific to that type.
+    #[serde(flatten)]
+    pub kind: ItemKind,
+}
+
+impl Item {
+    /// Creates an item with the default z order, opacity, and background.
+    pub fn new<K: Into<String>>(key: K, rect: Rect, kind: ItemKind) -> Self {
+        Item {
+            key: key.into(),
+            rect,
+            z_order: None,
+            enabled: None,
+            opacity: None,
+            background: None,
+            kind,
+        }
+    }
+}
+
+/// The type of an [`Item`](struct.Item.html) and the properties specific to that type.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ItemKind {
+    /// Text.
+    Text(Text),
+    /// An image.
+    Pixmap(Pixmap),
+    /// A bar showing a value within a range.
+    Bar(Bar),
+    /// A bar showing a value within a range with an indicator over a gradient.
+    #[serde(rename = "gbar")]
+    GBar(GBar),
+}
+
+/// An area of the touch display, in pixels from the top left corner of the area for the dial.
+///
+/// Serialized as `[x, y, width, height]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "[u16; 4]", into = "[u16; 4]")]
+pub struct Rect {
+    /// The distance from the left edge.
+    pub x: u16,
+    /// The distance from the top edge.
+    pub y: u16,
+    /// The width of the area.
+    pub width: u16,
+    /// The height of the area.
+    pub height: u16,
+}
+
+impl Rect {
+    /// Creates an area.
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+impl From<[u16; 4]> for Rect {
+    fn from([x, y, width, height]: [u16; 4]) -> Self {
+        Rect::new(x, y, width, height)
+    }
+}
+
+impl From<Rect> for [u16; 4] {
+    fn from(rect: Rect) -> Self {
+        [rect.x, rect.y, rect.width, rect.height]
+    }
+}
+
+/// A range of values for a bar.
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Range {
+    /// The value at the start of the bar.
+    pub min: f64,
+    /// The value at the end of the bar.
+    pub max: f64,
+}
+
+/// The font of a text item.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Font {
+    /// The size of the font in pixels.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub size: Option<u16>,
+    /// The weight of the font, from 100 to 1000.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<u16>,
+}
+
+/// The horizontal alignment of a text item.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlignment {
+    Left,
+    Center,
+    Right,
+}
+
+/// How text that does not fit within a text item is shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextOverflow {
+    /// The text is cut off.
+    Clip,
+    /// The text is cut off and ends with an ellipsis.
+    Ellipsis,
+    /// The text fades out.
+    Fade,
+}
+
+/// The shape of a bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+pub enum BarSubtype {
+    Rectangle = 0,
+    DoubleRectangle = 1,
+    Trapezoid = 2,
+    DoubleTrapezoid = 3,
+    Groove = 4,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{CustomLayout, Item, ItemKind, LayoutError, Rect};
+    use crate::feedback::Bar;
+
+    #[test]
+    fn custom_layout() {
+        let json = r##"{"$schema":"https://schemas.elgato.com/streamdeck/plugins/layout.json","id":"volume","items":[{"key":"title","rect":[16,10,136,24],"zOrder":1,"type":"text","font":{"size":16,"weight":600},"alignment":"left","text-overflow":"ellipsis"},{"key":"indicator","rect":[16,70,168,20],"type":"gbar","value":50.0,"range":{"min":0.0,"max":100.0},"subtype":4,"bar_fill_c":"0:#ff0000,1:#00ff00"}]}"##;
+        let layout: CustomLayout = serde_json::from_str(json).unwrap();
+        assert_eq!(Rect::new(16, 10, 136, 24), layout.items[0].rect);
+        match &layout.items[1].kind {
+            ItemKind::GBar(bar) => assert_eq!(Some(50.0), bar.value),
+            _ => panic!("expected a gbar"),
+        }
+        assert_eq!(json, serde_json::to_string(&layout).unwrap());
+    }
+
+    #[test]
+    fn validate() {
+        let bar = |key: &str, rect| Item::new(key, rect, ItemKind::Bar(Bar::default()));
+        assert!(matches!(
+            CustomLayout::builder("bars")
+                .item(bar("a", Rect::new(0, 0, 200, 100)))
+                .item(bar("a", Rect::new(0, 0, 10, 10)))
+                .build(),
+            Err(LayoutError::DuplicateKey(_))
+        ));
+        assert!(matches!(
+            CustomLayout::builder("bars")
+                .item(bar("a", Rect::new(150, 0, 51, 10)))
+                .build(),
+            Err(LayoutError::OutOfBounds(_, _))
+        ));
+        assert!(matches!(
+            CustomLayout::builder("bars").build(),
+            Err(LayoutError::NoItems)
+        ));
+    }
+}
@@ -1,17 +1,61 @@
+// failure_derive implements Fail in a const item beside each error type, which rustc reports as
+// a non-local impl definition.
+#![allow(non_local_definitions)]
+
+#[macro_use]
+mod received;
+
+pub mod animation;
+pub mod applications;
+pub mod builder;
+pub mod devices;
+pub mod diagnostics;
+pub mod dispatch;
+pub mod feedback;
+pub mod host;
+pub mod id;
+pub mod image;
+pub mod layout;
+#[cfg(feature = "locale")]
+pub mod locale;
 #[cfg(feature = "logging")]
 pub mod logging;
+pub mod metrics;
+pub mod property_inspector;
 pub mod registration;
+pub mod report;
+pub mod rpc;
+pub mod settings;
 pub mod socket;
+pub mod state;
+pub mod version;
 
+pub use crate::id::{ActionUuid, Context, DeviceId};
 pub use crate::registration::RegistrationInfo;
 pub use crate::socket::StreamDeckSocket;
 
+use failure::Fail;
 use serde::{de, ser};
 use serde_derive::{Deserialize, Serialize};
-use serde_json::Value;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::fmt;
+use std::str::FromStr;
+
+/// A property inspector message kept as JSON text.
+///
+/// Use this as `M` to forward messages between the plugin and the property inspector without
+/// parsing them. Received messages must be parsed with
+/// [`Message::from_json`](enum.Message.html#method.from_json), which
+/// [`StreamDeckSocket`](socket/struct.StreamDeckSocket.html) does.
+#[cfg(feature = "raw-value")]
+pub type RawPayload = Box<serde_json::value::RawValue>;
 
+/// The version of the Stream Deck software whose events and fields are implemented by this crate.
+///
+/// See [`RegistrationInfoApplication::version_mismatch`](registration/struct.RegistrationInfoApplication.html#method.version_mismatch).
+pub const SDK_VERSION: &str = "6.5";
+
+received_message! {
 /// A message received from the Stream Deck software.
 ///
 /// - `G` represents the global settings that are persisted within the Stream Deck software.
@@ -19,8 +63,9 @@ use std::fmt;
 /// - `M` represents the messages that are received from the property inspector.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 #[serde(tag = "event", rename_all = "camelCase")]
+#[non_exhaustive]
 pub enum Message<G, S, M> {
     /// A key has been pressed.
     ///
@@ -28,11 +73,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     KeyDown {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the key was pressed.
-        device: String,
+        device: DeviceId,
         /// Additional information about the key press.
         payload: KeyPayload<S>,
     },
@@ -42,11 +87,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     KeyUp {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the key was pressed.
-        device: String,
+        device: DeviceId,
         /// Additional information about the key press.
         payload: KeyPayload<S>,
     },
@@ -56,11 +101,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     WillAppear {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action will appear, or None if it does not appear on a device.
-        device: Option<String>,
+        device: Option<DeviceId>,
         /// Additional information about the action's appearance.
         payload: VisibilityPayload<S>,
     },
@@ -70,11 +115,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     WillDisappear {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action was visible, or None if it was not on a device.
-        device: Option<String>,
+        device: Option<DeviceId>,
         /// Additional information about the action's appearance.
         payload: VisibilityPayload<S>,
     },
@@ -84,11 +129,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     TitleParametersDidChange {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action is visible, or None if it is not on a device.
-        device: Option<String>,
+        device: Option<DeviceId>,
         /// Additional information about the new title.
         payload: TitleParametersPayload<S>,
     },
@@ -98,9 +143,9 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DeviceDidConnect {
         /// The ID of the device that has connected.
-        device: String,
+        device: DeviceId,
         /// Information about the device.
-        device_info: DeviceInfo,
+        device_info: Device,
     },
     /// A device has disconnected.
     ///
@@ -108,7 +153,7 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DeviceDidDisconnect {
         /// The ID of the device that has disconnected.
-        device: String,
+        device: DeviceId,
     },
     /// An application monitored by the manifest file has launched.
     ///
@@ -132,9 +177,9 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SendToPlugin {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// Information sent from the property inspector.
         payload: M,
     },
@@ -147,11 +192,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DidReceiveSettings {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
         /// The current settings for the action.
         payload: KeyPayload<S>,
     },
@@ -161,11 +206,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     PropertyInspectorDidAppear {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
     },
     /// The property inspector for an action is no longer visible.
     ///
@@ -173,11 +218,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     PropertyInspectorDidDisappear {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
     },
     /// The application has sent settings for an action.
     ///
@@ -203,11 +248,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     TouchTap {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
         /// Additional information about the touch event.
         payload: TouchTapPayload<S>,
     },
@@ -218,11 +263,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DialDown {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
         /// Additional information about the press event.
         payload: DialDownPayload<S>,
     },
@@ -233,11 +278,11 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DialUp {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
         /// Additional information about the release event.
         payload: DialUpPayload<S>,
     },
@@ -248,22 +293,223 @@ pub enum Message<G, S, M> {
     #[serde(rename_all = "camelCase")]
     DialRotate {
         /// The uuid of the action.
-        action: String,
+        action: ActionUuid,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device where the action exists.
-        device: String,
+        device: DeviceId,
         /// Additional information about the rotate event.
         payload: DialRotatePayload<S>,
     },
 
-    /// An event from an unsupported version of the Stream Deck software.
+    /// A `streamdeck://plugins/message/<plugin uuid>` URL has been opened.
     ///
-    /// This occurs when the Stream Deck software sends an event that is not
-    /// understood. Usually this will be because the Stream Deck software is
-    /// newer than the plugin, and it should be safe to ignore these.
-    #[serde(other)]
-    Unknown,
+    /// Added in Stream Deck software version 6.5.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received#didreceivedeeplink)
+    #[serde(rename_all = "camelCase")]
+    DidReceiveDeepLink {
+        /// Information about the deep link.
+        payload: DeepLinkPayload,
+    },
+}
+}
+
+/// Checks whether `event` is the name of one of the events `E`.
+pub(crate) fn is_known_event<E>(event: &str) -> bool
+where
+    E: de::DeserializeOwned,
+{
+    use serde::de::IntoDeserializer;
+
+    let known: Result<E, de::value::Error> = E::deserialize(event.into_deserializer());
+    known.is_ok()
+}
+
+/// Takes the name and payload of a message for an event that is not one of `E`.
+///
+/// This returns `None` for the events that are understood, and for messages without a name so
+/// that parsing them reports the error.
+pub(crate) fn unknown_event<E>(
+    message: &mut serde_json::Value,
+) -> Option<(String, serde_json::Value)>
+where
+    E: de::DeserializeOwned,
+{
+    use serde_json::Value;
+
+    // compact encodings such as MessagePack arrays put the name first
+    let event = match message {
+        Value::Object(fields) => fields.get("event"),
+        Value::Array(fields) => fields.first(),
+        _ => None,
+    };
+    let event = event?.as_str()?;
+    if is_known_event::<E>(event) {
+        return None;
+    }
+    let event = event.to_owned();
+    let payload = match message {
+        Value::Object(fields) => fields.remove("payload"),
+        Value::Array(fields) => fields.get_mut(1).map(Value::take),
+        _ => None,
+    };
+    Some((event, payload.unwrap_or(Value::Null)))
+}
+
+/// Just the event name of a message, used to choose how to parse the rest of it.
+#[derive(Deserialize)]
+pub(crate) struct EventName<'a> {
+    #[serde(borrow)]
+    pub event: std::borrow::Cow<'a, str>,
+}
+
+/// The fields of a [SendToPlugin](enum.Message.html#variant.SendToPlugin) message.
+#[derive(Deserialize)]
+struct SendToPluginFields<M> {
+    action: ActionUuid,
+    context: Context,
+    payload: M,
+}
+
+impl<G, S, M> Message<G, S, M>
+where
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    M: de::DeserializeOwned,
+{
+    /// Parses a message from JSON text.
+    ///
+    /// This is the same as `serde_json::from_str`, except that the payload of a
+    /// [SendToPlugin](enum.Message.html#variant.SendToPlugin) message is read directly from the
+    /// text. This allows `M` to be a [`RawPayload`](type.RawPayload.html), which keeps the
+    /// message from the property inspector as it was sent so it can be forwarded without being
+    /// parsed and serialized again.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        // Deserialize buffers the message, which a RawPayload cannot be read from. Other messages
+        // which mention the event are parsed twice, once to read the name.
+        if json.contains("sendToPlugin") {
+            let name: EventName = serde_json::from_str(json)?;
+            if name.event == "sendToPlugin" {
+                let fields: SendToPluginFields<M> = serde_json::from_str(json)?;
+                return Ok(Message::SendToPlugin {
+                    action: fields.action,
+                    context: fields.context,
+                    payload: fields.payload,
+                });
+            }
+        }
+        serde_json::from_str(json)
+    }
+
+    /// Parses a message received from the Stream Deck software using SIMD-accelerated JSON
+    /// parsing.
+    ///
+    /// This gives the same result as [`from_json`](#method.from_json), but the text is consumed
+    /// because the parser works on it in place, and a [`RawPayload`](type.RawPayload.html) is
+    /// written again from the parsed message rather than kept as it was sent. Errors are reported
+    /// as `serde_json` errors so that both parsers can be used interchangeably, but they do not
+    /// include a line and column.
+    #[cfg(feature = "simd-json")]
+    pub fn from_json_simd(json: String) -> Result<Self, serde_json::Error> {
+        let mut json = json.into_bytes();
+        let message: serde_json::Value =
+            simd_json::serde::from_slice(&mut json).map_err(de::Error::custom)?;
+        // Deserialize buffers the payload again, which a RawPayload cannot be read from.
+        if message.get("event").and_then(serde_json::Value::as_str) == Some("sendToPlugin") {
+            let fields: SendToPluginFields<M> = serde_json::from_value(message)?;
+            return Ok(Message::SendToPlugin {
+                action: fields.action,
+                context: fields.context,
+                payload: fields.payload,
+            });
+        }
+        serde_json::from_value(message)
+    }
+}
+
+impl<G, S, M> Message<G, S, M> {
+    /// The name of the event, as sent by the Stream Deck software.
+    pub fn event(&self) -> &str {
+        match self {
+            Message::KeyDown { .. } => "keyDown",
+            Message::KeyUp { .. } => "keyUp",
+            Message::WillAppear { .. } => "willAppear",
+            Message::WillDisappear { .. } => "willDisappear",
+            Message::TitleParametersDidChange { .. } => "titleParametersDidChange",
+            Message::DeviceDidConnect { .. } => "deviceDidConnect",
+            Message::DeviceDidDisconnect { .. } => "deviceDidDisconnect",
+            Message::ApplicationDidLaunch { .. } => "applicationDidLaunch",
+            Message::ApplicationDidTerminate { .. } => "applicationDidTerminate",
+            Message::SendToPlugin { .. } => "sendToPlugin",
+            Message::DidReceiveSettings { .. } => "didReceiveSettings",
+            Message::PropertyInspectorDidAppear { .. } => "propertyInspectorDidAppear",
+            Message::PropertyInspectorDidDisappear { .. } => "propertyInspectorDidDisappear",
+            Message::DidReceiveGlobalSettings { .. } => "didReceiveGlobalSettings",
+            Message::SystemDidWakeUp => "systemDidWakeUp",
+            Message::TouchTap { .. } => "touchTap",
+            Message::DialDown { .. } => "dialDown",
+            Message::DialUp { .. } => "dialUp",
+            Message::DialRotate { .. } => "dialRotate",
+            Message::DidReceiveDeepLink { .. } => "didReceiveDeepLink",
+            Message::Unknown { event, .. } => event,
+        }
+    }
+
+    /// Discards fields added by newer versions of the Stream Deck software.
+    pub(crate) fn clear_extra(&mut self) {
+        match self {
+            Message::KeyDown { payload, .. }
+            | Message::KeyUp { payload, .. }
+            | Message::DidReceiveSettings { payload, .. } => payload.extra.clear(),
+            Message::WillAppear { payload, .. } | Message::WillDisappear { payload, .. } => {
+                payload.extra.clear()
+            }
+            Message::TitleParametersDidChange { payload, .. } => payload.extra.clear(),
+            Message::DeviceDidConnect { device_info, .. } => device_info.extra.clear(),
+            Message::ApplicationDidLaunch { payload }
+            | Message::ApplicationDidTerminate { payload } => payload.extra.clear(),
+            Message::DidReceiveGlobalSettings { payload } => payload.extra.clear(),
+            Message::TouchTap { payload, .. } => payload.extra.clear(),
+            Message::DialDown { payload, .. } => payload.extra.clear(),
+            Message::DialUp { payload, .. } => payload.extra.clear(),
+            Message::DialRotate { payload, .. } => payload.extra.clear(),
+            Message::DidReceiveDeepLink { payload } => payload.extra.clear(),
+            Message::DeviceDidDisconnect { .. }
+            | Message::SendToPlugin { .. }
+            | Message::PropertyInspectorDidAppear { .. }
+            | Message::PropertyInspectorDidDisappear { .. }
+            | Message::SystemDidWakeUp
+            | Message::Unknown { .. } => {}
+        }
+    }
+
+    /// The instance of the action the message is for, if any.
+    pub fn context(&self) -> Option<&Context> {
+        match self {
+            Message::KeyDown { context, .. }
+            | Message::KeyUp { context, .. }
+            | Message::WillAppear { context, .. }
+            | Message::WillDisappear { context, .. }
+            | Message::TitleParametersDidChange { context, .. }
+            | Message::SendToPlugin { context, .. }
+            | Message::DidReceiveSettings { context, .. }
+            | Message::PropertyInspectorDidAppear { context, .. }
+            | Message::PropertyInspectorDidDisappear { context, .. }
+            | Message::TouchTap { context, .. }
+            | Message::DialDown { context, .. }
+            | Message::DialUp { context, .. }
+            | Message::DialRotate { context, .. } => Some(context),
+            Message::DeviceDidConnect { .. }
+            | Message::DeviceDidDisconnect { .. }
+            | Message::ApplicationDidLaunch { .. }
+            | Message::ApplicationDidTerminate { .. }
+            | Message::DidReceiveGlobalSettings { .. }
+            | Message::SystemDidWakeUp
+            | Message::DidReceiveDeepLink { .. }
+            | Message::Unknown { .. } => None,
+        }
+    }
 }
 
 /// A message to be sent to the Stream Deck software.
@@ -272,9 +518,14 @@ pub enum Message<G, S, M> {
 /// - `S` represents the action settings that are persisted within the Stream Deck software.
 /// - `M` represents the messages that are sent to the property inspector.
 ///
+/// Optional fields that are not set are left out, as the Stream Deck software expects, so
+/// formats that write structs as sequences cannot read these messages back. Store them in
+/// MessagePack with the named encoding (`rmp_serde::to_vec_named`).
+///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(tag = "event", rename_all = "camelCase")]
+#[non_exhaustive]
 pub enum MessageOut<G, S, M> {
     /// Set the title of an action instance.
     ///
@@ -282,7 +533,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetTitle {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The title to set.
         payload: TitlePayload,
     },
@@ -292,7 +543,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetImage {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The image to set.
         payload: ImagePayload,
     },
@@ -302,7 +553,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     ShowAlert {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
     },
     /// Temporarily overlay the key image with a checkmark.
     ///
@@ -310,7 +561,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     ShowOk {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
     },
     /// Retrieve settings for an instance of an action via DidReceiveSettings.
     ///
@@ -318,7 +569,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     GetSettings {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
     },
     /// Store settings for an instance of an action.
     ///
@@ -326,7 +577,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetSettings {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The settings to save.
         payload: S,
     },
@@ -338,7 +589,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetState {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The desired state.
         payload: StatePayload,
     },
@@ -348,9 +599,12 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SendToPropertyInspector {
         /// The uuid of the action.
-        action: String,
+        ///
+        /// Newer versions of the Stream Deck software do not require this.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        action: Option<ActionUuid>,
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The message to send.
         payload: M,
     },
@@ -360,9 +614,9 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SwitchToProfile {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The device to change the profile of.
-        device: String,
+        device: DeviceId,
         /// The profile to activate.
         payload: ProfilePayload,
     },
@@ -380,7 +634,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     GetGlobalSettings {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
     },
     /// Store plugin settings.
     ///
@@ -388,7 +642,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetGlobalSettings {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The settings to save.
         payload: G,
     },
@@ -406,9 +660,9 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetFeedback {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The data to send to the display.
-        payload: Value,
+        payload: feedback::FeedbackPayload,
     },
     /// Set feedback layout.
     ///
@@ -416,7 +670,7 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetFeedbackLayout {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The data to send to the display.
         payload: SetFeedbackLayoutPayload,
     },
@@ -426,14 +680,59 @@ pub enum MessageOut<G, S, M> {
     #[serde(rename_all = "camelCase")]
     SetTriggerDescription {
         /// The instance of the action (key or part of a multiaction).
-        context: String,
+        context: Context,
         /// The data to send to the display.
         payload: SetTriggerDescriptionPayload,
     },
 }
 
+impl<G, S, M> MessageOut<G, S, M> {
+    /// The name of the event, as sent to the Stream Deck software.
+    pub fn event(&self) -> &'static str {
+        match self {
+            MessageOut::SetTitle { .. } => "setTitle",
+            MessageOut::SetImage { .. } => "setImage",
+            MessageOut::ShowAlert { .. } => "showAlert",
+            MessageOut::ShowOk { .. } => "showOk",
+            MessageOut::GetSettings { .. } => "getSettings",
+            MessageOut::SetSettings { .. } => "setSettings",
+            MessageOut::SetState { .. } => "setState",
+            MessageOut::SendToPropertyInspector { .. } => "sendToPropertyInspector",
+            MessageOut::SwitchToProfile { .. } => "switchToProfile",
+            MessageOut::OpenUrl { .. } => "openUrl",
+            MessageOut::GetGlobalSettings { .. } => "getGlobalSettings",
+            MessageOut::SetGlobalSettings { .. } => "setGlobalSettings",
+            MessageOut::LogMessage { .. } => "logMessage",
+            MessageOut::SetFeedback { .. } => "setFeedback",
+            MessageOut::SetFeedbackLayout { .. } => "setFeedbackLayout",
+            MessageOut::SetTriggerDescription { .. } => "setTriggerDescription",
+        }
+    }
+
+    /// The instance of the action the message is for, if any.
+    pub fn context(&self) -> Option<&Context> {
+        match self {
+            MessageOut::SetTitle { context, .. }
+            | MessageOut::SetImage { context, .. }
+            | MessageOut::ShowAlert { context }
+            | MessageOut::ShowOk { context }
+            | MessageOut::GetSettings { context }
+            | MessageOut::SetSettings { context, .. }
+            | MessageOut::SetState { context, .. }
+            | MessageOut::SendToPropertyInspector { context, .. }
+            | MessageOut::SwitchToProfile { context, .. }
+            | MessageOut::GetGlobalSettings { context }
+            | MessageOut::SetGlobalSettings { context, .. }
+            | MessageOut::SetFeedback { context, .. }
+            | MessageOut::SetFeedbackLayout { context, .. }
+            | MessageOut::SetTriggerDescription { context, .. } => Some(context),
+            MessageOut::OpenUrl { .. } | MessageOut::LogMessage { .. } => None,
+        }
+    }
+}
+
 /// The target of a command.
-#[derive(Debug, Deserialize_repr, Serialize_repr)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 pub enum Target {
     /// Both the device and a the display within the Stream Deck software.
@@ -447,7 +746,7 @@ pub enum Target {
 /// The title to set as part of a [SetTitle](enum.MessageOut.html#variant.SetTitle) message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#settitle)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TitlePayload {
     /// The new title.
@@ -459,10 +758,43 @@ pub struct TitlePayload {
     pub state: Option<u8>,
 }
 
+impl TitlePayload {
+    /// Creates a payload setting the title on both displays for all states.
+    pub fn new<T: Into<String>>(title: T) -> Self {
+        TitlePayload {
+            title: Some(title.into()),
+            target: Target::Both,
+            state: None,
+        }
+    }
+
+    /// Creates a payload reverting to the title chosen by the user on both displays for all
+    /// states.
+    pub fn reset() -> Self {
+        TitlePayload {
+            title: None,
+            target: Target::Both,
+            state: None,
+        }
+    }
+
+    /// Sets the title for only one display.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets the title for only one state.
+    pub fn with_state(mut self, state: u8) -> Self {
+        self.state = Some(state);
+        self
+    }
+}
+
 /// The image to set as part of a [SetImage](enum.MessageOut.html#variant.SetImage) message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#setimage)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ImagePayload {
     /// An image in the form of a data URI.
@@ -474,66 +806,295 @@ pub struct ImagePayload {
     pub state: Option<u8>,
 }
 
+impl ImagePayload {
+    /// Creates a payload from an image in the form of a data URI, setting it on both displays for
+    /// all states.
+    pub fn from_data_uri<T: Into<String>>(image: T) -> Self {
+        ImagePayload {
+            image: Some(image.into()),
+            target: Target::Both,
+            state: None,
+        }
+    }
+
+    /// Creates a payload reverting to the image chosen by the user on both displays for all
+    /// states.
+    pub fn reset() -> Self {
+        ImagePayload {
+            image: None,
+            target: Target::Both,
+            state: None,
+        }
+    }
+
+    /// Creates a payload from encoded image data with the given MIME type.
+    pub fn from_bytes(mime_type: &str, bytes: &[u8]) -> Self {
+        Self::from_data_uri(format!(
+            "data:{};base64,{}",
+            mime_type,
+            base64::encode(bytes)
+        ))
+    }
+
+    /// Creates a payload from PNG data.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::ImagePayload;
+    /// let payload = ImagePayload::from_png_bytes(b"\x89PNG");
+    /// assert_eq!(Some("data:image/png;base64,iVBORw=="), payload.image.as_deref());
+    /// ```
+    pub fn from_png_bytes(bytes: &[u8]) -> Self {
+        Self::from_bytes("image/png", bytes)
+    }
+
+    /// Creates a payload from JPEG data.
+    pub fn from_jpeg_bytes(bytes: &[u8]) -> Self {
+        Self::from_bytes("image/jpeg", bytes)
+    }
+
+    /// Creates a payload from the source of an SVG image.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::ImagePayload;
+    /// let payload = ImagePayload::from_svg(r#"<svg xmlns="http://www.w3.org/2000/svg"/>"#);
+    /// assert!(payload.image.unwrap().starts_with("data:image/svg+xml;base64,"));
+    /// ```
+    pub fn from_svg(svg: &str) -> Self {
+        Self::from_bytes("image/svg+xml", svg.as_bytes())
+    }
+
+    /// Creates a payload from an image file.
+    ///
+    /// The type of the image is chosen from the file extension, which must be one of `png`,
+    /// `jpg`, `jpeg`, `gif`, `bmp`, or `svg`.
+    pub fn from_file<P: AsRef<std::path::Path>>(path: P) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let mime_type = match extension.as_deref() {
+            Some("png") => "image/png",
+            Some("jpg") | Some("jpeg") => "image/jpeg",
+            Some("gif") => "image/gif",
+            Some("bmp") => "image/bmp",
+            Some("svg") => "image/svg+xml",
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("unknown image type: {}", path.display()),
+                ))
+            }
+        };
+        Ok(Self::from_bytes(mime_type, &std::fs::read(path)?))
+    }
+
+    /// Sets the image for only one display.
+    pub fn with_target(mut self, target: Target) -> Self {
+        self.target = target;
+        self
+    }
+
+    /// Sets the image for only one state.
+    pub fn with_state(mut self, state: u8) -> Self {
+        self.state = Some(state);
+        self
+    }
+}
+
 /// The state to set as part of a [SetState](enum.MessageOut.html#variant.SetState) message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#setstate)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct StatePayload {
     /// The new state.
     pub state: u8,
 }
 
+impl StatePayload {
+    /// Creates a payload setting the state.
+    pub fn new(state: u8) -> Self {
+        StatePayload { state }
+    }
+}
+
 /// The profile to activate as part of a [SwitchToProfile](enum.MessageOut.html#variant.SwitchToProfile) message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#SwitchToProfile)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProfilePayload {
     /// The name of the profile to activate.
+    ///
+    /// If empty, the Stream Deck software returns to the previously active profile.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub profile: String,
+    /// The zero-based index of the page of the profile to show.
+    ///
+    /// Added in Stream Deck software version 6.5.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u8>,
+}
+
+impl ProfilePayload {
+    /// Creates a payload switching to the first page of a profile.
+    pub fn new<T: Into<String>>(profile: T) -> Self {
+        ProfilePayload {
+            profile: profile.into(),
+            page: None,
+        }
+    }
+
+    /// Creates a payload returning to the previously active profile.
+    pub fn previous() -> Self {
+        Self::new(String::new())
+    }
+
+    /// Shows a page of the profile other than the first.
+    pub fn with_page(mut self, page: u8) -> Self {
+        self.page = Some(page);
+        self
+    }
 }
 
 /// The URL to launch as part of a [OpenUrl](enum.MessageOut.html#variant.OpenUrl) message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#openurl)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UrlPayload {
     /// The URL to launch.
     pub url: String,
 }
 
+impl UrlPayload {
+    /// Creates a payload opening a URL.
+    pub fn new<T: Into<String>>(url: T) -> Self {
+        UrlPayload { url: url.into() }
+    }
+}
+
 /// Additional information about the key pressed.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct KeyPayload<S> {
     /// The stored settings for the action instance.
     pub settings: S,
-    /// The location of the key that was pressed, or None if this action instance is part of a multi action.
-    pub coordinates: Option<Coordinates>,
+    /// The location of the key that was pressed.
+    #[serde(flatten)]
+    pub location: Location,
     /// The current state of the action instance.
     pub state: Option<u8>,
     /// The desired state of the action instance (if this instance is part of a multi action).
     pub user_desired_state: Option<u8>,
-    //TODO: is_in_multi_action ignored. replace coordinates with enum Location { Coordinates, MultiAction }.
+    /// The kind of control the action instance is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<Controller>,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Additional information about a key's appearance.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VisibilityPayload<S> {
     /// The stored settings for the action instance.
     pub settings: S,
-    /// The location of the key, or None if this action instance is part of a multi action.
-    pub coordinates: Option<Coordinates>,
+    /// The location of the key.
+    #[serde(flatten)]
+    pub location: Location,
     /// The state of the action instance.
     pub state: Option<u8>,
-    //TODO: is_in_multi_action ignored. replace coordinates with enum Location { Coordinates, MultiAction }.
+    /// The kind of control the action instance is on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub controller: Option<Controller>,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Where an action instance is.
+///
+/// This is represented by the `coordinates` and `isInMultiAction` fields of a payload.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "RawLocation", into = "RawLocation")]
+pub enum Location {
+    /// The action instance is on a key at these coordinates.
+    Coordinates(Coordinates),
+    /// The action instance is part of a multi action.
+    MultiAction,
+    /// The payload gave neither coordinates nor that the action instance is part of a multi
+    /// action.
+    Unknown,
+}
+
+impl Location {
+    /// The location of the key, or None if the action instance is part of a multi action or its
+    /// location is not known.
+    pub fn coordinates(&self) -> Option<Coordinates> {
+        match self {
+            Location::Coordinates(coordinates) => Some(*coordinates),
+            Location::MultiAction | Location::Unknown => None,
+        }
+    }
+
+    /// Checks whether the action instance is part of a multi action.
+    pub fn is_in_multi_action(&self) -> bool {
+        *self == Location::MultiAction
+    }
+}
+
+#[derive(Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RawLocation {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    coordinates: Option<Coordinates>,
+    #[serde(default)]
+    is_in_multi_action: bool,
+}
+
+impl From<RawLocation> for Location {
+    fn from(raw: RawLocation) -> Self {
+        match (raw.coordinates, raw.is_in_multi_action) {
+            // instances in multi actions have no coordinates
+            (_, true) => Location::MultiAction,
+            (Some(coordinates), false) => Location::Coordinates(coordinates),
+            (None, false) => Location::Unknown,
+        }
+    }
+}
+
+impl From<Location> for RawLocation {
+    fn from(location: Location) -> Self {
+        RawLocation {
+            coordinates: location.coordinates(),
+            is_in_multi_action: location.is_in_multi_action(),
+        }
+    }
+}
+
+/// The kind of control an action instance is on.
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#willappear)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum Controller {
+    /// A key.
+    Keypad,
+    /// A dial and its section of the touchscreen.
+    Encoder,
+    /// A kind of control not known to this version of the crate.
+    #[serde(other)]
+    Unknown,
 }
 
 /// The new title of a key.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TitleParametersPayload<S> {
     /// The stored settings for the action instance.
@@ -546,38 +1107,60 @@ pub struct TitleParametersPayload<S> {
     pub title: String,
     /// Additional parameters for the display of the title.
     pub title_parameters: TitleParameters,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// The new global settings.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GlobalSettingsPayload<G> {
     /// The stored settings for the plugin.
     pub settings: G,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// A log message.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct LogMessagePayload {
     /// The log message text.
     pub message: String,
 }
 
+impl LogMessagePayload {
+    /// Creates a payload writing a message to the log.
+    pub fn new<T: Into<String>>(message: T) -> Self {
+        LogMessagePayload {
+            message: message.into(),
+        }
+    }
+}
+
 /// A layout update message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent#setfeedbacklayout-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SetFeedbackLayoutPayload {
-    /// A predefined layout identifier or the relative path to a JSON file that contains a custom layout.
-    pub layout: String,
+    /// A predefined layout or the relative path to a JSON file that contains a custom layout.
+    pub layout: feedback::Layout,
+}
+
+impl SetFeedbackLayoutPayload {
+    /// Creates a payload changing the layout.
+    pub fn new(layout: feedback::Layout) -> Self {
+        SetFeedbackLayoutPayload { layout }
+    }
 }
 
 /// A trigger description update message.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent#settriggerdescription-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SetTriggerDescriptionPayload {
     /// A value that describes the long-touch interaction with the touch display.
@@ -590,10 +1173,85 @@ pub struct SetTriggerDescriptionPayload {
     pub touch: Option<String>,
 }
 
+impl SetTriggerDescriptionPayload {
+    /// Creates a payload reverting all of the descriptions to those in the manifest.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Describes the long-touch interaction with the touch display.
+    pub fn long_touch<T: Into<String>>(mut self, description: T) -> Self {
+        self.long_touch = Some(description.into());
+        self
+    }
+
+    /// Describes the push interaction with the dial.
+    pub fn push<T: Into<String>>(mut self, description: T) -> Self {
+        self.push = Some(description.into());
+        self
+    }
+
+    /// Describes the rotate interaction with the dial.
+    pub fn rotate<T: Into<String>>(mut self, description: T) -> Self {
+        self.rotate = Some(description.into());
+        self
+    }
+
+    /// Describes the touch interaction with the touch display.
+    pub fn touch<T: Into<String>>(mut self, description: T) -> Self {
+        self.touch = Some(description.into());
+        self
+    }
+
+    /// Reverts the description of the long-touch interaction to the one in the manifest.
+    pub fn clear_long_touch(mut self) -> Self {
+        self.long_touch = None;
+        self
+    }
+
+    /// Reverts the description of the push interaction to the one in the manifest.
+    pub fn clear_push(mut self) -> Self {
+        self.push = None;
+        self
+    }
+
+    /// Reverts the description of the rotate interaction to the one in the manifest.
+    pub fn clear_rotate(mut self) -> Self {
+        self.rotate = None;
+        self
+    }
+
+    /// Reverts the description of the touch interaction to the one in the manifest.
+    pub fn clear_touch(mut self) -> Self {
+        self.touch = None;
+        self
+    }
+
+    /// Reverts all of the descriptions to those in the manifest.
+    pub fn clear_all(self) -> Self {
+        Self::new()
+    }
+}
+
+/// The descriptions of the interactions with a dial, built up from
+/// [`TriggerDescription::new`](struct.SetTriggerDescriptionPayload.html#method.new).
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::TriggerDescription;
+/// let description = TriggerDescription::new()
+///     .rotate("Volume")
+///     .push("Mute")
+///     .clear_touch();
+/// assert_eq!(Some("Mute".to_string()), description.push);
+/// ```
+pub type TriggerDescription = SetTriggerDescriptionPayload;
+
 /// Additional information about a touch tap event.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received#touchtap-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TouchTapPayload<S> {
     /// The stored settings for the action instance.
@@ -601,39 +1259,93 @@ pub struct TouchTapPayload<S> {
     /// The location of the action triggered.
     pub coordinates: Option<Coordinates>,
     /// The coordinates of the touch event within the LCD slot associated with the action.
-    pub tap_pos: (u8, u8),
+    pub tap_pos: TapPosition,
     /// Whether the tap was long.
     pub hold: bool,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// The position of a touch within the area of the touch display for a dial, in pixels from the
+/// top left corner.
+///
+/// This is sent as an array of `[x, y]`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(from = "(u8, u8)", into = "(u8, u8)")]
+pub struct TapPosition {
+    /// The horizontal position.
+    pub x: u8,
+    /// The vertical position.
+    pub y: u8,
+}
+
+impl TapPosition {
+    /// Returns the position as fractions of the size of the area, from 0 at the top left to 1 at
+    /// the bottom right.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::TapPosition;
+    /// # use streamdeck_rs::layout::{CANVAS_HEIGHT, CANVAS_WIDTH};
+    /// let position = TapPosition { x: 50, y: 50 };
+    /// assert_eq!((0.25, 0.5), position.relative_to(CANVAS_WIDTH, CANVAS_HEIGHT));
+    /// ```
+    pub fn relative_to(&self, width: u16, height: u16) -> (f64, f64) {
+        (
+            f64::from(self.x) / f64::from(width.max(1)),
+            f64::from(self.y) / f64::from(height.max(1)),
+        )
+    }
+}
+
+impl From<(u8, u8)> for TapPosition {
+    fn from((x, y): (u8, u8)) -> Self {
+        TapPosition { x, y }
+    }
+}
+
+impl From<TapPosition> for (u8, u8) {
+    fn from(position: TapPosition) -> Self {
+        (position.x, position.y)
+    }
 }
 
 /// Additional information about an encoder press event.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received#dialdown-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DialDownPayload<S> {
     /// The stored settings for the action instance.
     pub settings: S,
     /// The location of the action triggered.
     pub coordinates: Option<Coordinates>,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Additional information about an encoder release event.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received#dialup-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DialUpPayload<S> {
     /// The stored settings for the action instance.
     pub settings: S,
     /// The location of the action triggered.
     pub coordinates: Option<Coordinates>,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Additional information about an encoder rotate event.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received#dialrotate-sd)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DialRotatePayload<S> {
     /// The stored settings for the action instance.
@@ -644,14 +1356,27 @@ pub struct DialRotatePayload<S> {
     pub ticks: i64,
     /// Whether the encoder was being pressed down during the rotation.
     pub pressed: bool,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Information about a hardware device.
 ///
+/// This is used both for the devices in the [registration info](registration/struct.RegistrationInfo.html)
+/// and for devices that connect later.
+///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#devicedidconnect)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
-pub struct DeviceInfo {
+pub struct Device {
+    /// The ID of the specific device, if known.
+    ///
+    /// The registration info includes the ID with each device. For
+    /// [DeviceDidConnect](enum.Message.html#variant.DeviceDidConnect) the ID is sent outside of
+    /// the device information, so this is None unless it is attached using [`with_id`](#method.with_id).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<DeviceId>,
     /// The user-provided name of the device.
     ///
     /// Added in Stream Deck software version 4.3.
@@ -661,20 +1386,70 @@ pub struct DeviceInfo {
     /// The type of the device, or None if the Stream Deck software is running with no device attached.
     #[serde(rename = "type")]
     pub _type: Option<DeviceType>,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
+impl Device {
+    /// Attaches the ID of the device.
+    pub fn with_id<I: Into<DeviceId>>(mut self, id: I) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// The number of dials on the device.
+    ///
+    /// The size sent by the Stream Deck software only covers the keys, so this is based on the
+    /// [type](enum.DeviceType.html#method.capabilities) of the device, and is 0 if it is not known.
+    pub fn encoder_count(&self) -> u8 {
+        self._type
+            .and_then(|device_type| device_type.capabilities())
+            .map_or(0, |capabilities| capabilities.encoders)
+    }
+
+    /// The number of areas of the touch display, one above each dial, or 0 if the device has no
+    /// touch display.
+    pub fn touch_slot_count(&self) -> u8 {
+        self._type
+            .and_then(|device_type| device_type.capabilities())
+            .filter(|capabilities| capabilities.touch_strip)
+            .map_or(0, |capabilities| capabilities.encoders)
+    }
+}
+
+/// Information about a hardware device.
+///
+/// This is now the same type as [`Device`](struct.Device.html), which also has an `id`.
+#[deprecated(note = "use Device instead")]
+pub type DeviceInfo = Device;
+
 /// Information about a monitored application that has launched or terminated.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ApplicationPayload {
     /// The name of the application.
     pub application: String,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Information about a deep link that has been opened.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeepLinkPayload {
+    /// The part of the URL after the plugin UUID, including the path, query, and fragment.
+    pub url: String,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// The location of a key on a device.
 ///
 /// Locations are specified using zero-indexed values starting from the top left corner of the device.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Coordinates {
     /// The x coordinate of the key.
@@ -683,10 +1458,48 @@ pub struct Coordinates {
     pub row: u8,
 }
 
+impl Coordinates {
+    /// Creates a location from a column and row.
+    pub fn new(column: u8, row: u8) -> Self {
+        Coordinates { column, row }
+    }
+
+    /// Converts the location to the index of the key on a device, counting row by row from the
+    /// top left key, or `None` if the location is not on the device.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::{Coordinates, DeviceSize};
+    /// let size = DeviceSize { columns: 5, rows: 3 };
+    /// assert_eq!(Some(7), Coordinates::new(2, 1).to_index(&size));
+    /// assert_eq!(Some(Coordinates::new(2, 1)), Coordinates::from_index(7, &size));
+    /// ```
+    pub fn to_index(&self, size: &DeviceSize) -> Option<usize> {
+        if size.contains(*self) {
+            Some(self.row as usize * size.columns as usize + self.column as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Converts the index of a key on a device, counting row by row from the top left key, to its
+    /// location, or `None` if the index is not on the device.
+    pub fn from_index(index: usize, size: &DeviceSize) -> Option<Self> {
+        if index >= size.key_count() {
+            return None;
+        }
+        Some(Coordinates::new(
+            (index % size.columns as usize) as u8,
+            (index / size.columns as usize) as u8,
+        ))
+    }
+}
+
 /// The vertical alignment of a title.
 ///
 /// Titles are always centered horizontally.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Alignment {
     /// The title should appear at the top of the key.
@@ -700,7 +1513,7 @@ pub enum Alignment {
 /// Style information for a title.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#titleparametersdidchange)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TitleParameters {
     /// The name of the font family.
@@ -708,7 +1521,7 @@ pub struct TitleParameters {
     /// The font size.
     pub font_size: u8,
     /// Whether the font is bold and/or italic.
-    pub font_style: String,
+    pub font_style: FontStyle,
     /// Whether the font is underlined.
     pub font_underline: bool,
     /// Whether the title is displayed.
@@ -716,11 +1529,91 @@ pub struct TitleParameters {
     /// The vertical alignment of the title.
     pub title_alignment: Alignment,
     /// The color of the title.
-    pub title_color: String,
+    pub title_color: Color,
+}
+
+/// The style of the font of a title.
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#titleparametersdidchange)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FontStyle {
+    /// The default style, which is regular.
+    Default,
+    /// Neither bold nor italic.
+    Regular,
+    /// Bold.
+    Bold,
+    /// Italic.
+    Italic,
+    /// Bold and italic.
+    BoldItalic,
+    /// A style not documented in the 6.0 SDK.
+    Unknown(String),
+}
+
+impl FontStyle {
+    /// Whether the font is bold.
+    pub fn is_bold(&self) -> bool {
+        matches!(self, FontStyle::Bold | FontStyle::BoldItalic)
+    }
+
+    /// Whether the font is italic.
+    pub fn is_italic(&self) -> bool {
+        matches!(self, FontStyle::Italic | FontStyle::BoldItalic)
+    }
+}
+
+impl ser::Serialize for FontStyle {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(match self {
+            FontStyle::Default => "",
+            FontStyle::Regular => "Regular",
+            FontStyle::Bold => "Bold",
+            FontStyle::Italic => "Italic",
+            FontStyle::BoldItalic => "Bold Italic",
+            FontStyle::Unknown(value) => value,
+        })
+    }
+}
+
+impl<'de> de::Deserialize<'de> for FontStyle {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        struct Visitor;
+
+        impl<'de> de::Visitor<'de> for Visitor {
+            type Value = FontStyle;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a string")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<FontStyle, E>
+            where
+                E: de::Error,
+            {
+                Ok(match value {
+                    "" => FontStyle::Default,
+                    "Regular" => FontStyle::Regular,
+                    "Bold" => FontStyle::Bold,
+                    "Italic" => FontStyle::Italic,
+                    "Bold Italic" => FontStyle::BoldItalic,
+                    value => FontStyle::Unknown(value.to_string()),
+                })
+            }
+        }
+
+        deserializer.deserialize_str(Visitor)
+    }
 }
 
 /// The size of a device in keys.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceSize {
     /// The number of key columns on the device.
@@ -729,10 +1622,76 @@ pub struct DeviceSize {
     pub rows: u8,
 }
 
+impl DeviceSize {
+    /// The number of keys on the device.
+    pub fn key_count(&self) -> usize {
+        self.columns as usize * self.rows as usize
+    }
+
+    /// Checks whether a location is on the device.
+    pub fn contains(&self, coordinates: Coordinates) -> bool {
+        coordinates.column < self.columns && coordinates.row < self.rows
+    }
+
+    /// The locations of all of the keys, row by row.
+    pub fn keys(&self) -> impl Iterator<Item = Coordinates> {
+        self.rect(Coordinates::new(0, 0), Coordinates::new(u8::MAX, u8::MAX))
+    }
+
+    /// The locations of the keys in a row, from left to right.
+    pub fn keys_in_row(&self, row: u8) -> impl Iterator<Item = Coordinates> {
+        self.rect(Coordinates::new(0, row), Coordinates::new(u8::MAX, row))
+    }
+
+    /// The locations of the keys in a column, from top to bottom.
+    pub fn keys_in_column(&self, column: u8) -> impl Iterator<Item = Coordinates> {
+        self.rect(
+            Coordinates::new(column, 0),
+            Coordinates::new(column, u8::MAX),
+        )
+    }
+
+    /// The locations of the keys in the rectangle with the corners `from` and `to`, row by row.
+    ///
+    /// Both corners are included. The corners may be given in any order, and the parts of the
+    /// rectangle that are not on the device are skipped.
+    pub fn rect(&self, from: Coordinates, to: Coordinates) -> impl Iterator<Item = Coordinates> {
+        // end is exclusive, so clamping it to the size keeps the range on the device
+        let span = |a: u8, b: u8, size: u8| a.min(b)..(a.max(b) as u16 + 1).min(size as u16) as u8;
+        let columns = span(from.column, to.column, self.columns);
+        let rows = span(from.row, to.row, self.rows);
+        rows.flat_map(move |row| {
+            columns
+                .clone()
+                .map(move |column| Coordinates::new(column, row))
+        })
+    }
+
+    /// The locations of the keys directly above, below, left, and right of a key.
+    pub fn neighbors(&self, coordinates: Coordinates) -> impl Iterator<Item = Coordinates> {
+        let Coordinates { column, row } = coordinates;
+        let size = *self;
+        vec![
+            row.checked_sub(1).map(|row| Coordinates::new(column, row)),
+            column
+                .checked_sub(1)
+                .map(|column| Coordinates::new(column, row)),
+            column
+                .checked_add(1)
+                .map(|column| Coordinates::new(column, row)),
+            row.checked_add(1).map(|row| Coordinates::new(column, row)),
+        ]
+        .into_iter()
+        .flatten()
+        .filter(move |neighbor| size.contains(*neighbor))
+    }
+}
+
 /// The type of connected hardware device.
 ///
 /// [Official Documentation](https://docs.elgato.com/sdk/plugins/manifest/#profiles)
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum DeviceType {
     /// The [Stream Deck](https://www.elgato.com/en/gaming/stream-deck).
     StreamDeck, // 0
@@ -762,10 +1721,133 @@ pub enum DeviceType {
     ///
     /// Added in Stream Deck software version 6.0
     StreamDeckPlus, // 7
-    /// A device not documented in the 6.0 SDK.
+    /// The [SCUF Envision Pro](https://www.scufgaming.com/envision-pro-controller) controller.
+    ///
+    /// Added in Stream Deck software version 6.4
+    ScufController, // 8
+    /// The [Stream Deck Neo](https://www.elgato.com/en/stream-deck-neo).
+    ///
+    /// Added in Stream Deck software version 6.4
+    StreamDeckNeo, // 9
+    /// The [Stream Deck Studio](https://www.elgato.com/en/stream-deck-studio).
+    ///
+    /// Added in Stream Deck software version 6.8
+    StreamDeckStudio, // 10
+    /// A virtual Stream Deck, shown on screen by the Stream Deck software.
+    ///
+    /// Devices connected through the Stream Deck Network Dock are reported as the type of the
+    /// connected device.
+    ///
+    /// Added in Stream Deck software version 6.8
+    VirtualStreamDeck, // 11
+    /// A device not known to this crate.
     Unknown(u64),
 }
 
+impl DeviceType {
+    /// Describes the hardware of the device type.
+    ///
+    /// Returns `None` for devices whose hardware varies, like the Stream Deck Mobile app and the
+    /// virtual Stream Deck, and for unknown devices. The [`DeviceSize`](struct.DeviceSize.html)
+    /// reported for a connected device should be preferred for the number of keys.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::DeviceType;
+    /// let capabilities = DeviceType::StreamDeckPlus.capabilities().unwrap();
+    /// assert_eq!(4, capabilities.encoders);
+    /// assert!(capabilities.touch_strip);
+    /// ```
+    pub fn capabilities(&self) -> Option<DeviceCapabilities> {
+        let (keys, key_size, encoders, touch_strip) = match self {
+            DeviceType::StreamDeck => (15, Some(72), 0, false),
+            DeviceType::StreamDeckMini => (6, Some(80), 0, false),
+            DeviceType::StreamDeckXl => (32, Some(96), 0, false),
+            DeviceType::CorsairGKeys => (6, Some(72), 0, false),
+            DeviceType::StreamDeckPedal => (3, None, 0, false),
+            DeviceType::CorsairVoyager => (10, Some(72), 0, false),
+            DeviceType::StreamDeckPlus => (8, Some(120), 4, true),
+            DeviceType::ScufController => (4, None, 0, false),
+            DeviceType::StreamDeckNeo => (8, Some(96), 0, false),
+            DeviceType::StreamDeckStudio => (32, Some(144), 2, false),
+            DeviceType::StreamDeckMobile
+            | DeviceType::VirtualStreamDeck
+            | DeviceType::Unknown(_) => return None,
+        };
+        Some(DeviceCapabilities {
+            keys,
+            key_size,
+            encoders,
+            touch_strip,
+        })
+    }
+
+    /// The recommended width and height of key images in pixels for the
+    /// [`device_pixel_ratio`](registration/struct.RegistrationInfo.html#structfield.device_pixel_ratio)
+    /// of the system.
+    ///
+    /// Images of this size are shown without being rescaled by the Stream Deck software.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::DeviceType;
+    /// assert_eq!(Some(144), DeviceType::StreamDeck.key_image_size(2.0));
+    /// assert_eq!(Some(108), DeviceType::StreamDeck.key_image_size(1.5));
+    /// assert_eq!(None, DeviceType::StreamDeckPedal.key_image_size(2.0));
+    /// ```
+    pub fn key_image_size(&self, pixel_ratio: f64) -> Option<u16> {
+        self.capabilities()?
+            .key_size
+            .map(|size| scale(size, pixel_ratio))
+    }
+
+    /// The recommended width and height in pixels of the area of the touch display for each dial,
+    /// for the `device_pixel_ratio` of the system, or `None` if the device has no touch display.
+    pub fn touch_strip_slot_size(&self, pixel_ratio: f64) -> Option<(u16, u16)> {
+        if !self.capabilities()?.touch_strip {
+            return None;
+        }
+        Some((
+            scale(layout::CANVAS_WIDTH, pixel_ratio),
+            scale(layout::CANVAS_HEIGHT, pixel_ratio),
+        ))
+    }
+}
+
+/// Scales a size in points to pixels, treating pixel ratios below one as one.
+fn scale(size: u16, pixel_ratio: f64) -> u16 {
+    (f64::from(size) * pixel_ratio.max(1.0)).round() as u16
+}
+
+/// The hardware of a type of device, as returned by
+/// [`DeviceType::capabilities`](enum.DeviceType.html#method.capabilities).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct DeviceCapabilities {
+    /// The number of keys.
+    pub keys: u8,
+    /// The width and height of the key images in pixels at the standard pixel ratio, or `None` if
+    /// the keys have no display.
+    pub key_size: Option<u16>,
+    /// The number of dials.
+    pub encoders: u8,
+    /// Whether the device has a touch display above the dials.
+    pub touch_strip: bool,
+}
+
+impl DeviceCapabilities {
+    /// Whether the device has any keys.
+    pub fn has_keys(&self) -> bool {
+        self.keys > 0
+    }
+
+    /// Whether the keys can show images.
+    pub fn has_key_display(&self) -> bool {
+        self.key_size.is_some()
+    }
+}
+
 impl ser::Serialize for DeviceType {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -780,6 +1862,10 @@ impl ser::Serialize for DeviceType {
             DeviceType::StreamDeckPedal => 5,
             DeviceType::CorsairVoyager => 6,
             DeviceType::StreamDeckPlus => 7,
+            DeviceType::ScufController => 8,
+            DeviceType::StreamDeckNeo => 9,
+            DeviceType::StreamDeckStudio => 10,
+            DeviceType::VirtualStreamDeck => 11,
             DeviceType::Unknown(value) => *value,
         })
     }
@@ -812,6 +1898,10 @@ impl<'de> de::Deserialize<'de> for DeviceType {
                     5 => DeviceType::StreamDeckPedal,
                     6 => DeviceType::CorsairVoyager,
                     7 => DeviceType::StreamDeckPlus,
+                    8 => DeviceType::ScufController,
+                    9 => DeviceType::StreamDeckNeo,
+                    10 => DeviceType::StreamDeckStudio,
+                    11 => DeviceType::VirtualStreamDeck,
                     value => DeviceType::Unknown(value),
                 })
             }
@@ -821,22 +1911,365 @@ impl<'de> de::Deserialize<'de> for DeviceType {
     }
 }
 
+/// A color, as used by the Stream Deck software for user colors and feedback items.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum Color {
     Rgb { r: u8, g: u8, b: u8 },
     Rgba { r: u8, g: u8, b: u8, a: u8 },
 }
 
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Color::Rgb { r, g, b } => write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+            Color::Rgba { r, g, b, a } => write!(f, "#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
+        }
+    }
+}
+
+/// An error that occurred while parsing a [`Color`](enum.Color.html).
+#[derive(Debug, Fail)]
+pub enum ParseColorError {
+    /// The color did not begin with `#`.
+    ///
+    /// This is no longer returned, as colors without `#` are reported as `Unrecognized`.
+    #[fail(display = "expected string to begin with '#'")]
+    MissingHash,
+    /// The color was not the length of a hex color.
+    #[fail(display = "invalid length {}, expected a hex color", _0)]
+    BadLength(usize),
+    /// A color component was not a hex number.
+    #[fail(display = "invalid color component {:?}", _0)]
+    BadComponent(String),
+    /// The color was not a hex color, a color function, or a known color name.
+    #[fail(display = "unrecognized color {:?}", _0)]
+    Unrecognized(String),
+}
+
+impl From<[u8; 3]> for Color {
+    fn from([r, g, b]: [u8; 3]) -> Self {
+        Color::Rgb { r, g, b }
+    }
+}
+
+impl From<[u8; 4]> for Color {
+    fn from([r, g, b, a]: [u8; 4]) -> Self {
+        Color::Rgba { r, g, b, a }
+    }
+}
+
+impl From<Color> for [u8; 4] {
+    fn from(color: Color) -> Self {
+        color.components()
+    }
+}
+
+#[cfg(feature = "palette")]
+impl From<palette::Srgb<u8>> for Color {
+    fn from(color: palette::Srgb<u8>) -> Self {
+        Color::from(<[u8; 3]>::from(color))
+    }
+}
+
+#[cfg(feature = "palette")]
+impl From<palette::Srgba<u8>> for Color {
+    fn from(color: palette::Srgba<u8>) -> Self {
+        Color::from(<[u8; 4]>::from(color))
+    }
+}
+
+#[cfg(feature = "palette")]
+impl From<Color> for palette::Srgba<u8> {
+    fn from(color: Color) -> Self {
+        palette::Srgba::from(color.components())
+    }
+}
+
+#[cfg(feature = "csscolorparser")]
+impl From<csscolorparser::Color> for Color {
+    fn from(color: csscolorparser::Color) -> Self {
+        Color::from(color.to_rgba8())
+    }
+}
+
+#[cfg(feature = "csscolorparser")]
+impl From<Color> for csscolorparser::Color {
+    fn from(color: Color) -> Self {
+        let [r, g, b, a] = color.components();
+        csscolorparser::Color::from_rgba8(r, g, b, a)
+    }
+}
+
+/// The basic named colors from CSS.
+const NAMED_COLORS: &[(&str, (u8, u8, u8))] = &[
+    ("black", (0x00, 0x00, 0x00)),
+    ("silver", (0xc0, 0xc0, 0xc0)),
+    ("gray", (0x80, 0x80, 0x80)),
+    ("grey", (0x80, 0x80, 0x80)),
+    ("white", (0xff, 0xff, 0xff)),
+    ("maroon", (0x80, 0x00, 0x00)),
+    ("red", (0xff, 0x00, 0x00)),
+    ("purple", (0x80, 0x00, 0x80)),
+    ("fuchsia", (0xff, 0x00, 0xff)),
+    ("magenta", (0xff, 0x00, 0xff)),
+    ("green", (0x00, 0x80, 0x00)),
+    ("lime", (0x00, 0xff, 0x00)),
+    ("olive", (0x80, 0x80, 0x00)),
+    ("yellow", (0xff, 0xff, 0x00)),
+    ("navy", (0x00, 0x00, 0x80)),
+    ("blue", (0x00, 0x00, 0xff)),
+    ("teal", (0x00, 0x80, 0x80)),
+    ("aqua", (0x00, 0xff, 0xff)),
+    ("cyan", (0x00, 0xff, 0xff)),
+    ("orange", (0xff, 0xa5, 0x00)),
+];
+
+impl Color {
+    /// The red, green, blue, and alpha components of the color. Colors without alpha are opaque.
+    pub fn components(&self) -> [u8; 4] {
+        match *self {
+            Color::Rgb { r, g, b } => [r, g, b, 0xff],
+            Color::Rgba { r, g, b, a } => [r, g, b, a],
+        }
+    }
+
+    /// Returns the color with a different alpha.
+    pub fn with_alpha(&self, a: u8) -> Color {
+        let [r, g, b, _] = self.components();
+        Color::Rgba { r, g, b, a }
+    }
+
+    /// Blends the color with another, from the color itself at 0 to the other color at 1.
+    ///
+    /// The result has alpha only if either color has alpha.
+    pub fn mix(&self, other: &Color, amount: f64) -> Color {
+        let amount = amount.clamp(0.0, 1.0);
+        let (from, to) = (self.components(), other.components());
+        let blend = |i: usize| {
+            (f64::from(from[i]) + (f64::from(to[i]) - f64::from(from[i])) * amount).round() as u8
+        };
+        match (self, other) {
+            (Color::Rgb { .. }, Color::Rgb { .. }) => Color::Rgb {
+                r: blend(0),
+                g: blend(1),
+                b: blend(2),
+            },
+            _ => Color::Rgba {
+                r: blend(0),
+                g: blend(1),
+                b: blend(2),
+                a: blend(3),
+            },
+        }
+    }
+
+    /// Moves the color toward white by an amount from 0 to 1, keeping its alpha.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::Color;
+    /// let gray = Color::Rgb { r: 0x80, g: 0x80, b: 0x80 };
+    /// assert_eq!("#c0c0c0", gray.lighten(0.5).to_string());
+    /// ```
+    pub fn lighten(&self, amount: f64) -> Color {
+        self.mix(
+            &Color::Rgb {
+                r: 0xff,
+                g: 0xff,
+                b: 0xff,
+            }
+            .with_alpha(self.components()[3]),
+            amount,
+        )
+        .keep_kind(self)
+    }
+
+    /// Moves the color toward black by an amount from 0 to 1, keeping its alpha.
+    pub fn darken(&self, amount: f64) -> Color {
+        self.mix(
+            &Color::Rgb { r: 0, g: 0, b: 0 }.with_alpha(self.components()[3]),
+            amount,
+        )
+        .keep_kind(self)
+    }
+
+    /// The relative luminance of the color as defined by WCAG, from 0 for black to 1 for white.
+    ///
+    /// Alpha is ignored.
+    pub fn luminance(&self) -> f64 {
+        let linear = |c: u8| {
+            let c = f64::from(c) / 255.0;
+            if c <= 0.03928 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        let [r, g, b, _] = self.components();
+        0.2126 * linear(r) + 0.7152 * linear(g) + 0.0722 * linear(b)
+    }
+
+    /// The contrast ratio between two colors as defined by WCAG, from 1 for the same color to 21
+    /// for black and white.
+    ///
+    /// This can be used to choose whether text drawn over a background should be light or dark.
+    pub fn contrast_ratio(&self, other: &Color) -> f64 {
+        let (a, b) = (self.luminance(), other.luminance());
+        (a.max(b) + 0.05) / (a.min(b) + 0.05)
+    }
+
+    /// Drops the alpha of the result if the original color had none.
+    fn keep_kind(self, original: &Color) -> Color {
+        match (original, self) {
+            (Color::Rgb { .. }, Color::Rgba { r, g, b, .. }) => Color::Rgb { r, g, b },
+            (_, color) => color,
+        }
+    }
+
+    fn parse_hex(digits: &str) -> Result<Self, ParseColorError> {
+        let component = |value: &str| {
+            u8::from_str_radix(value, 16)
+                .map_err(|_| ParseColorError::BadComponent(value.to_string()))
+        };
+        // Short colors repeat each digit, so #abc is #aabbcc.
+        let short = |i: usize| component(&digits[i..i + 1]).map(|value| value * 0x11);
+        let long = |i: usize| component(&digits[i * 2..i * 2 + 2]);
+
+        match digits.len() {
+            3 => Ok(Color::Rgb {
+                r: short(0)?,
+                g: short(1)?,
+                b: short(2)?,
+            }),
+            4 => Ok(Color::Rgba {
+                r: short(0)?,
+                g: short(1)?,
+                b: short(2)?,
+                a: short(3)?,
+            }),
+            6 => Ok(Color::Rgb {
+                r: long(0)?,
+                g: long(1)?,
+                b: long(2)?,
+            }),
+            8 => Ok(Color::Rgba {
+                r: long(0)?,
+                g: long(1)?,
+                b: long(2)?,
+                a: long(3)?,
+            }),
+            _ => Err(ParseColorError::BadLength(digits.len() + 1)),
+        }
+    }
+
+    fn parse_function(arguments: &str) -> Result<Self, ParseColorError> {
+        let bad = |value: &str| ParseColorError::BadComponent(value.to_string());
+        // Each component is either a number or a percentage of `max`.
+        let number = |value: &str, max: f64| {
+            let parsed = match value.strip_suffix('%') {
+                Some(percent) => percent.trim().parse::<f64>().map(|p| p / 100.0 * max),
+                None => value.parse::<f64>(),
+            };
+            parsed.map_err(|_| bad(value))
+        };
+        let channel = |value: &str| number(value, 255.0).map(|c| c.round().clamp(0.0, 255.0) as u8);
+
+        // Both `rgb(1, 2, 3 / 0.5)` and `rgb(1 2 3 / 0.5)` are allowed.
+        let (colors, alpha) = match arguments.split_once('/') {
+            Some((colors, alpha)) => (colors, Some(alpha.trim())),
+            None => (arguments, None),
+        };
+        let mut parts: Vec<&str> = colors
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|part| !part.is_empty())
+            .collect();
+        let alpha = match (alpha, parts.len()) {
+            (Some(alpha), 3) => Some(alpha),
+            (None, 4) => parts.pop(),
+            (None, 3) => None,
+            _ => return Err(bad(arguments)),
+        };
+
+        let (r, g, b) = (channel(parts[0])?, channel(parts[1])?, channel(parts[2])?);
+        match alpha {
+            Some(alpha) => {
+                let a = number(alpha, 1.0)?;
+                Ok(Color::Rgba {
+                    r,
+                    g,
+                    b,
+                    a: (a.clamp(0.0, 1.0) * 255.0).round() as u8,
+                })
+            }
+            None => Ok(Color::Rgb { r, g, b }),
+        }
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseColorError;
+
+    /// Parses a color.
+    ///
+    /// Colors are accepted as hex colors with 3, 4, 6, or 8 digits, the CSS functions `rgb()`
+    /// and `rgba()`, or the basic CSS color names.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::Color;
+    /// let red = Color::Rgb { r: 255, g: 0, b: 0 };
+    /// assert_eq!(red, "#f00".parse().unwrap());
+    /// assert_eq!(red, "rgb(255, 0, 0)".parse().unwrap());
+    /// assert_eq!(red, "Red".parse().unwrap());
+    /// ```
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if !value.is_ascii() {
+            return Err(ParseColorError::BadComponent(value.to_string()));
+        }
+        if let Some(digits) = value.strip_prefix('#') {
+            return Color::parse_hex(digits);
+        }
+
+        let lower = value.to_ascii_lowercase();
+        let function = lower
+            .strip_prefix("rgba(")
+            .or_else(|| lower.strip_prefix("rgb("));
+        if let Some(arguments) = function {
+            return match arguments.strip_suffix(')') {
+                Some(arguments) => Color::parse_function(arguments),
+                None => Err(ParseColorError::BadComponent(value.to_string())),
+            };
+        }
+
+        if lower == "transparent" {
+            return Ok(Color::Rgba {
+                r: 0,
+                g: 0,
+                b: 0,
+                a: 0,
+            });
+        }
+        NAMED_COLORS
+            .iter()
+            .find(|(name, _)| *name == lower)
+            .map(|(_, (r, g, b))| Color::Rgb {
+                r: *r,
+                g: *g,
+                b: *b,
+            })
+            .ok_or_else(|| ParseColorError::Unrecognized(value.to_string()))
+    }
+}
+
 impl ser::Serialize for Color {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: ser::Serializer,
     {
-        let html_color = match self {
-            Color::Rgb { r, g, b } => format!("#{:02x}{:02x}{:02x}", r, g, b),
-            Color::Rgba { r, g, b, a } => format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a),
-        };
-        serializer.serialize_str(&html_color)
+        serializer.collect_str(self)
     }
 }
 
@@ -851,42 +2284,14 @@ impl<'de> de::Deserialize<'de> for Color {
             type Value = Color;
 
             fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("a hex color")
+                formatter.write_str("a color")
             }
 
             fn visit_str<E>(self, value: &str) -> Result<Color, E>
             where
                 E: de::Error,
             {
-                let parse_component = |value: &str| {
-                    u8::from_str_radix(value, 16)
-                        .map_err(|_| E::invalid_value(de::Unexpected::Str(value), &self))
-                };
-
-                let parse_rgb = |value: &str| {
-                    if &value[0..1] != "#" {
-                        return Err(E::custom("expected string to begin with '#'"));
-                    }
-
-                    let r = parse_component(&value[1..3])?;
-                    let g = parse_component(&value[3..5])?;
-                    let b = parse_component(&value[5..7])?;
-
-                    Ok((r, g, b))
-                };
-
-                match value.len() {
-                    7 => {
-                        let (r, g, b) = parse_rgb(value)?;
-                        Ok(Color::Rgb { r, g, b })
-                    }
-                    9 => {
-                        let (r, g, b) = parse_rgb(value)?;
-                        let a = parse_component(&value[7..9])?;
-                        Ok(Color::Rgba { r, g, b, a })
-                    }
-                    _ => Err(E::invalid_length(value.len(), &self)),
-                }
+                value.parse().map_err(E::custom)
             }
         }
 
@@ -896,7 +2301,90 @@ impl<'de> de::Deserialize<'de> for Color {
 
 #[cfg(test)]
 mod test {
-    use super::Color;
+    use super::{
+        Color, Controller, Coordinates, Device, DeviceSize, DeviceType, FontStyle, ImagePayload,
+        KeyPayload, Location, Message, MessageOut, ProfilePayload, TitlePayload,
+    };
+    use crate::registration::RegistrationInfo;
+    use serde_json::Value;
+
+    #[test]
+    fn controller() {
+        let message: Message<Value, Value, Value> = serde_json::from_str(
+            r#"{"event":"willAppear","action":"com.example.action","context":"abc","device":"def","payload":{"settings":{},"coordinates":{"column":0,"row":0},"state":0,"controller":"Encoder"}}"#,
+        )
+        .unwrap();
+        match message {
+            Message::WillAppear { payload, .. } => {
+                assert_eq!(Some(Controller::Encoder), payload.controller);
+                assert_eq!(
+                    Location::Coordinates(Coordinates::new(0, 0)),
+                    payload.location
+                );
+            }
+            _ => panic!("wrong message type"),
+        }
+        let controller: Controller = serde_json::from_str(r#""Pedal""#).unwrap();
+        assert_eq!(Controller::Unknown, controller);
+    }
+
+    #[test]
+    fn location() {
+        let payload: KeyPayload<Value> = serde_json::from_str(
+            r#"{"settings":{},"isInMultiAction":true,"state":0,"userDesiredState":1}"#,
+        )
+        .unwrap();
+        assert!(payload.location.is_in_multi_action());
+        assert_eq!(
+            r#"{"settings":{},"isInMultiAction":true,"state":0,"userDesiredState":1}"#,
+            serde_json::to_string(&payload).unwrap()
+        );
+
+        let payload: KeyPayload<Value> =
+            serde_json::from_str(r#"{"settings":{},"coordinates":{"column":1,"row":2}}"#).unwrap();
+        assert_eq!(
+            Location::Coordinates(Coordinates::new(1, 2)),
+            payload.location
+        );
+
+        let payload: KeyPayload<Value> = serde_json::from_str(r#"{"settings":{}}"#).unwrap();
+        assert_eq!(Location::Unknown, payload.location);
+        assert!(!payload.location.is_in_multi_action());
+        assert_eq!(None, payload.location.coordinates());
+        let json = serde_json::to_string(&payload).unwrap();
+        let payload: KeyPayload<Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(Location::Unknown, payload.location);
+    }
+
+    #[test]
+    fn device_regions() {
+        let size = DeviceSize {
+            columns: 5,
+            rows: 3,
+        };
+        assert_eq!(15, size.key_count());
+        assert_eq!(size.key_count(), size.keys().count());
+        assert_eq!(
+            vec![Coordinates::new(0, 1), Coordinates::new(4, 1)],
+            size.keys_in_row(1).step_by(4).collect::<Vec<_>>()
+        );
+        assert_eq!(3, size.keys_in_column(2).count());
+        assert_eq!(
+            vec![
+                Coordinates::new(3, 1),
+                Coordinates::new(4, 1),
+                Coordinates::new(3, 2),
+                Coordinates::new(4, 2)
+            ],
+            size.rect(Coordinates::new(6, 2), Coordinates::new(3, 1))
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![Coordinates::new(1, 0), Coordinates::new(0, 1)],
+            size.neighbors(Coordinates::new(0, 0)).collect::<Vec<_>>()
+        );
+        assert_eq!(0, size.keys_in_row(3).count());
+    }
 
     #[test]
     fn color() {
@@ -921,5 +2409,375 @@ mod test {
 
         let json_str: String = serde_json::to_string(&vec![color_a, color_b]).expect("JSON array");
         assert_eq!(as_json, json_str);
+
+        assert_eq!("#11111111", "#1111".parse::<Color>().unwrap().to_string());
+        assert_eq!(
+            Ok(Color::Rgba {
+                r: 255,
+                g: 128,
+                b: 0,
+                a: 128
+            }),
+            "rgba(100%, 128, 0, 0.5)".parse().map_err(|_| ())
+        );
+        assert_eq!(
+            Ok(Color::Rgb {
+                r: 0,
+                g: 128,
+                b: 128
+            }),
+            "Teal".parse().map_err(|_| ())
+        );
+        assert!("#12345".parse::<Color>().is_err());
+        assert!("rgb(1, 2)".parse::<Color>().is_err());
+        assert!("chartreuse".parse::<Color>().is_err());
+
+        let white = Color::from([255, 255, 255]);
+        let black = Color::from([0, 0, 0]);
+        assert!((white.contrast_ratio(&black) - 21.0).abs() < 1e-9);
+        assert_eq!("#80808080", black.lighten(0.5).with_alpha(0x80).to_string());
+        assert_eq!([0, 0, 0, 255], <[u8; 4]>::from(white.darken(1.0)));
+    }
+
+    #[cfg(feature = "palette")]
+    #[test]
+    fn palette_color() {
+        let color = Color::from(palette::Srgb::new(0x12u8, 0x34, 0x56));
+        assert_eq!("#123456", color.to_string());
+        let rgba = palette::Srgba::<u8>::from(color.with_alpha(0x78));
+        assert_eq!(palette::Srgba::new(0x12, 0x34, 0x56, 0x78), rgba);
+        assert_eq!("#12345678", Color::from(rgba).to_string());
+    }
+
+    #[cfg(feature = "csscolorparser")]
+    #[test]
+    fn css_color() {
+        let parsed: csscolorparser::Color = "rgb(102, 51, 153)".parse().unwrap();
+        let color = Color::from(parsed);
+        assert_eq!("#663399ff", color.to_string());
+        assert_eq!("#663399", csscolorparser::Color::from(color).to_css_hex());
+    }
+
+    #[test]
+    fn font_style() {
+        let styles: Vec<FontStyle> =
+            serde_json::from_str(r#"["", "Bold Italic", "Condensed"]"#).expect("font styles");
+        assert_eq!(
+            vec![
+                FontStyle::Default,
+                FontStyle::BoldItalic,
+                FontStyle::Unknown("Condensed".to_string())
+            ],
+            styles
+        );
+        assert!(styles[1].is_bold() && styles[1].is_italic());
+        assert_eq!(
+            r#"["","Bold Italic","Condensed"]"#,
+            serde_json::to_string(&styles).unwrap()
+        );
+    }
+
+    #[test]
+    fn device_type() {
+        let types: Vec<DeviceType> = serde_json::from_str("[9,11,42]").expect("device types");
+        assert_eq!(
+            vec![
+                DeviceType::StreamDeckNeo,
+                DeviceType::VirtualStreamDeck,
+                DeviceType::Unknown(42)
+            ],
+            types
+        );
+        assert_eq!("[9,11,42]", serde_json::to_string(&types).unwrap());
+    }
+
+    #[test]
+    fn unknown_event() {
+        let json = r#"{"event":"futureEvent","context":"abc","payload":{"value":1}}"#;
+        let message: Message<Value, Value, Value> = Message::from_json(json).expect("message");
+        match &message {
+            Message::Unknown { event, payload } => {
+                assert_eq!("futureEvent", event);
+                assert_eq!(1, payload["value"]);
+            }
+            _ => panic!("expected unknown message"),
+        }
+        assert_eq!("futureEvent", message.event());
+        assert_eq!(
+            r#"{"event":"futureEvent","payload":{"value":1}}"#,
+            serde_json::to_string(&message).unwrap()
+        );
+
+        let invalid = r#"{"event":"keyDown","payload":{}}"#;
+        assert!(Message::<Value, Value, Value>::from_json(invalid).is_err());
+
+        // the fallback is part of Deserialize, so it does not depend on from_json
+        let parsed: Message<Value, Value, Value> = serde_json::from_str(json).unwrap();
+        assert_eq!(message, parsed);
+        assert!(serde_json::from_str::<Message<Value, Value, Value>>(invalid).is_err());
+        assert_eq!(message, message_pack_round_trip(&message));
+    }
+
+    #[test]
+    fn known_event_error() {
+        use serde_derive::Deserialize;
+
+        #[derive(Debug, Deserialize)]
+        struct Settings {
+            #[allow(dead_code)]
+            foo: u32,
+        }
+
+        let json = r#"{"event":"keyDown","action":"com.example.plugin.action","context":"a","device":"d","payload":{"settings":{},"coordinates":{"column":0,"row":0},"state":0}}"#;
+        let error = Message::<Value, Settings, Value>::from_json(json).unwrap_err();
+        assert!(
+            error.to_string().starts_with("missing field `foo`"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn deep_link() {
+        let json =
+            r#"{"event":"didReceiveDeepLink","payload":{"url":"/hello?name=world#greeting"}}"#;
+        let message: Message<Value, Value, Value> = serde_json::from_str(json).expect("message");
+        match &message {
+            Message::DidReceiveDeepLink { payload } => {
+                assert_eq!("/hello?name=world#greeting", payload.url)
+            }
+            _ => panic!("expected deep link"),
+        }
+        assert_eq!("didReceiveDeepLink", message.event());
+        assert_eq!(None, message.context());
+    }
+
+    #[test]
+    fn events() {
+        let action = r#""action":"com.example.plugin.action","context":"a","device":"d""#;
+        let key = r#""settings":{},"coordinates":{"column":1,"row":2},"state":0"#;
+        let samples = [
+            format!(r#"{{"event":"keyDown",{},"payload":{{{}}}}}"#, action, key),
+            format!(r#"{{"event":"keyUp",{},"payload":{{{}}}}}"#, action, key),
+            format!(r#"{{"event":"willAppear",{},"payload":{{{}}}}}"#, action, key),
+            format!(r#"{{"event":"willDisappear",{},"payload":{{{}}}}}"#, action, key),
+            format!(
+                r##"{{"event":"titleParametersDidChange",{},"payload":{{{},"title":"t","titleParameters":{{"fontFamily":"","fontSize":12,"fontStyle":"","fontUnderline":false,"showTitle":true,"titleAlignment":"bottom","titleColor":"#ffffff"}}}}}}"##,
+                action, key
+            ),
+            r#"{"event":"deviceDidConnect","device":"d","deviceInfo":{"size":{"columns":5,"rows":3},"type":0}}"#.to_string(),
+            r#"{"event":"deviceDidDisconnect","device":"d"}"#.to_string(),
+            r#"{"event":"applicationDidLaunch","payload":{"application":"app"}}"#.to_string(),
+            r#"{"event":"applicationDidTerminate","payload":{"application":"app"}}"#.to_string(),
+            r#"{"event":"sendToPlugin","action":"com.example.plugin.action","context":"a","payload":1}"#.to_string(),
+            format!(r#"{{"event":"didReceiveSettings",{},"payload":{{{}}}}}"#, action, key),
+            format!(r#"{{"event":"propertyInspectorDidAppear",{}}}"#, action),
+            format!(r#"{{"event":"propertyInspectorDidDisappear",{}}}"#, action),
+            r#"{"event":"didReceiveGlobalSettings","payload":{"settings":{}}}"#.to_string(),
+            r#"{"event":"systemDidWakeUp"}"#.to_string(),
+            format!(
+                r#"{{"event":"touchTap",{},"payload":{{"settings":{{}},"tapPos":[1,2],"hold":false}}}}"#,
+                action
+            ),
+            format!(r#"{{"event":"dialDown",{},"payload":{{"settings":{{}}}}}}"#, action),
+            format!(r#"{{"event":"dialUp",{},"payload":{{"settings":{{}}}}}}"#, action),
+            format!(
+                r#"{{"event":"dialRotate",{},"payload":{{"settings":{{}},"ticks":-1,"pressed":false}}}}"#,
+                action
+            ),
+            r#"{"event":"didReceiveDeepLink","payload":{"url":"/"}}"#.to_string(),
+        ];
+        for sample in &samples {
+            let message: Message<Value, Value, Value> = serde_json::from_str(sample).expect(sample);
+            // fails to build when a variant is added, as a reminder to add a sample for it
+            match &message {
+                Message::KeyDown { .. }
+                | Message::KeyUp { .. }
+                | Message::WillAppear { .. }
+                | Message::WillDisappear { .. }
+                | Message::TitleParametersDidChange { .. }
+                | Message::DeviceDidConnect { .. }
+                | Message::DeviceDidDisconnect { .. }
+                | Message::ApplicationDidLaunch { .. }
+                | Message::ApplicationDidTerminate { .. }
+                | Message::SendToPlugin { .. }
+                | Message::DidReceiveSettings { .. }
+                | Message::PropertyInspectorDidAppear { .. }
+                | Message::PropertyInspectorDidDisappear { .. }
+                | Message::DidReceiveGlobalSettings { .. }
+                | Message::SystemDidWakeUp
+                | Message::TouchTap { .. }
+                | Message::DialDown { .. }
+                | Message::DialUp { .. }
+                | Message::DialRotate { .. }
+                | Message::DidReceiveDeepLink { .. } => {}
+                Message::Unknown { .. } => panic!("unknown {}", sample),
+            }
+            let round_trip: Message<Value, Value, Value> =
+                serde_json::from_value(serde_json::to_value(&message).unwrap()).unwrap();
+            assert_eq!(message, round_trip);
+            #[cfg(feature = "simd-json")]
+            assert_eq!(
+                message,
+                Message::from_json_simd(sample.clone()).expect(sample)
+            );
+
+            // a known event that cannot be parsed is an error rather than an unknown event
+            let invalid = format!(r#"{{"event":"{}","payload":0}}"#, message.event());
+            assert!(
+                Message::<Value, Value, Value>::from_json(&invalid).is_err()
+                    || matches!(message, Message::SystemDidWakeUp),
+                "{}",
+                invalid
+            );
+            #[cfg(feature = "simd-json")]
+            assert!(
+                Message::<Value, Value, Value>::from_json_simd(invalid.clone()).is_err()
+                    || matches!(message, Message::SystemDidWakeUp),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn extra_fields() {
+        let json = r#"{"event":"keyDown","action":"com.example.plugin.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"state":0,"newField":true}}"#;
+        let message: Message<Value, Value, Value> = serde_json::from_str(json).expect("message");
+        match &message {
+            Message::KeyDown { payload, .. } => {
+                assert_eq!(Some(&Value::Bool(true)), payload.extra.get("newField"))
+            }
+            _ => panic!("expected key down message"),
+        }
+        assert!(serde_json::to_string(&message)
+            .unwrap()
+            .contains(r#""newField":true"#));
+
+        for json in &[
+            r#"{"event":"willAppear","action":"com.example.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"state":0,"newField":true}}"#,
+            r##"{"event":"titleParametersDidChange","action":"com.example.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"state":0,"title":"","titleParameters":{"fontFamily":"","fontSize":12,"fontStyle":"","fontUnderline":false,"showTitle":true,"titleAlignment":"top","titleColor":"#ffffff"},"newField":true}}"##,
+            r#"{"event":"didReceiveGlobalSettings","payload":{"settings":{},"newField":true}}"#,
+            r#"{"event":"touchTap","action":"com.example.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"tapPos":[1,2],"hold":false,"newField":true}}"#,
+            r#"{"event":"dialRotate","action":"com.example.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"ticks":1,"pressed":false,"newField":true}}"#,
+            r#"{"event":"applicationDidLaunch","payload":{"application":"a","newField":true}}"#,
+            r#"{"event":"didReceiveDeepLink","payload":{"url":"/a","newField":true}}"#,
+        ] {
+            let message: Message<Value, Value, Value> = serde_json::from_str(json).expect(json);
+            assert!(!matches!(message, Message::Unknown { .. }), "{}", json);
+            assert!(serde_json::to_string(&message)
+                .unwrap()
+                .contains(r#""newField":true"#));
+        }
+    }
+
+    #[cfg(feature = "raw-value")]
+    #[test]
+    fn raw_payload() {
+        use super::{MessageOut, RawPayload};
+
+        let json = r#"{"event":"sendToPlugin","action":"com.example.action","context":"abc","payload":{"a": [1, 2]}}"#;
+        let message: Message<Value, Value, RawPayload> = Message::from_json(json).expect("message");
+        let payload = match message {
+            Message::SendToPlugin { payload, .. } => payload,
+            _ => unreachable!(),
+        };
+        assert_eq!(r#"{"a": [1, 2]}"#, payload.get());
+
+        #[cfg(feature = "simd-json")]
+        match Message::<Value, Value, RawPayload>::from_json_simd(json.to_string()).unwrap() {
+            Message::SendToPlugin { payload, .. } => assert_eq!(r#"{"a":[1,2]}"#, payload.get()),
+            _ => unreachable!(),
+        }
+
+        let message: MessageOut<Value, Value, RawPayload> =
+            MessageOut::send_to_property_inspector("abc", payload);
+        assert_eq!(
+            r#"{"event":"sendToPropertyInspector","context":"abc","payload":{"a": [1, 2]}}"#,
+            serde_json::to_string(&message).unwrap()
+        );
+    }
+
+    #[test]
+    fn message_pack() {
+        let message: Message<Value, Value, Value> = Message::DeviceDidConnect {
+            device: "device".into(),
+            device_info: Device {
+                id: None,
+                name: Some("Stream Deck +".to_string()),
+                size: DeviceSize {
+                    columns: 4,
+                    rows: 2,
+                },
+                _type: Some(DeviceType::StreamDeckPlus),
+                extra: Default::default(),
+            },
+        };
+        match message_pack_round_trip(&message) {
+            Message::<Value, Value, Value>::DeviceDidConnect {
+                device,
+                device_info,
+            } => {
+                assert_eq!("device", device);
+                assert_eq!(Some("Stream Deck +"), device_info.name.as_deref());
+                assert!(matches!(
+                    device_info._type,
+                    Some(DeviceType::StreamDeckPlus)
+                ));
+            }
+            message => panic!("unexpected message {:?}", message),
+        }
+
+        // optional fields that are not set are left out
+        let messages: Vec<MessageOut<Value, Value, Value>> = vec![
+            MessageOut::SetTitle {
+                context: "context".into(),
+                payload: TitlePayload::new("title"),
+            },
+            MessageOut::SetImage {
+                context: "context".into(),
+                payload: ImagePayload::from_data_uri("data:image/png;base64,"),
+            },
+            MessageOut::SwitchToProfile {
+                context: "context".into(),
+                device: "device".into(),
+                payload: ProfilePayload::previous(),
+            },
+        ];
+        for message in messages {
+            assert_eq!(message, message_pack_round_trip(&message));
+        }
+
+        let info: RegistrationInfo = serde_json::from_str(
+            r##"{
+                "application": {"language": "ja", "platform": "mac", "platformVersion": "14.2.1", "version": "6.0.0"},
+                "plugin": {"uuid": "com.example.plugin", "version": "1.0"},
+                "devicePixelRatio": 2,
+                "devices": [{"id": "device", "name": "Stream Deck", "size": {"columns": 5, "rows": 3}, "type": 0}],
+                "colors": {"highlightColor": "#0078FFFF"}
+            }"##,
+        )
+        .expect("registration info");
+        let info = message_pack_round_trip(&info);
+        assert_eq!("6.0.0", info.application.version);
+        assert_eq!(Some("14.2.1"), info.application.platform_version.as_deref());
+        assert!(matches!(
+            info.devices[0]._type,
+            Some(DeviceType::StreamDeck)
+        ));
+
+        let mut info = info;
+        info.application.platform_version = None;
+        assert_eq!(info, message_pack_round_trip(&info));
+    }
+
+    /// Encodes a value with the named (map) MessagePack encoding, which is the one that is
+    /// supported because optional fields may be left out, and decodes it.
+    fn message_pack_round_trip<T>(value: &T) -> T
+    where
+        T: serde::Serialize + serde::de::DeserializeOwned,
+    {
+        let named = rmp_serde::to_vec_named(value).expect("named MessagePack");
+        rmp_serde::from_slice(&named).expect("decoded named MessagePack")
     }
 }
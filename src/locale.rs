@@ -0,0 +1,335 @@
+//! Formatting values for display according to the language of the Stream Deck software.
+//!
+//! Only the languages supported by the Stream Deck software are covered, so this does not need a
+//! full internationalization library. Languages that are not known are formatted as English.
+
+use crate::registration::Language;
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Timelike};
+
+/// Formats numbers, dates, and times for a language.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::locale::Formatter;
+/// # use streamdeck_rs::registration::Language;
+/// assert_eq!("1,234.5", Formatter::new(&Language::English).number(1234.5, 1));
+/// assert_eq!("1.234,5", Formatter::new(&Language::German).number(1234.5, 1));
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct Formatter {
+    locale: Locale,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Locale {
+    En,
+    Fr,
+    De,
+    Es,
+    It,
+    Ja,
+    Ko,
+    Pt,
+    Zh,
+    ZhTw,
+}
+
+#[derive(Clone, Copy)]
+enum Unit {
+    Second,
+    Minute,
+    Hour,
+    Day,
+}
+
+impl Formatter {
+    /// Creates a formatter for a language.
+    pub fn new(language: &Language) -> Self {
+        let locale = match language {
+            Language::French => Locale::Fr,
+            Language::German => Locale::De,
+            Language::Spanish => Locale::Es,
+            Language::Japanese => Locale::Ja,
+            Language::ChineseChina => Locale::Zh,
+            Language::ChineseTaiwan => Locale::ZhTw,
+            Language::Italian => Locale::It,
+            Language::Korean => Locale::Ko,
+            Language::Portuguese => Locale::Pt,
+            Language::English | Language::Unknown(_) => Locale::En,
+        };
+        Formatter { locale }
+    }
+
+    /// Formats a number with a fixed number of digits after the decimal separator.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        let (group, decimal) = match self.locale {
+            Locale::En | Locale::Ja | Locale::Ko | Locale::Zh | Locale::ZhTw => (",", "."),
+            Locale::Fr => ("\u{202f}", ","),
+            Locale::De | Locale::Es | Locale::It | Locale::Pt => (".", ","),
+        };
+        // Spanish only groups numbers with at least five digits.
+        let min_grouping = if self.locale == Locale::Es { 5 } else { 4 };
+
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut result = String::with_capacity(formatted.len() + integer.len() / 3 + 1);
+        if value.is_sign_negative() && formatted.bytes().any(|b| (b'1'..=b'9').contains(&b)) {
+            result.push('-');
+        }
+        if integer.len() >= min_grouping && integer.bytes().all(|b| b.is_ascii_digit()) {
+            for (i, digit) in integer.chars().enumerate() {
+                if i > 0 && (integer.len() - i) % 3 == 0 {
+                    result.push_str(group);
+                }
+                result.push(digit);
+            }
+        } else {
+            result.push_str(integer);
+        }
+        if let Some(fraction) = fraction {
+            result.push_str(decimal);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// Formats a date in the short numeric style of the language, e.g. `3/14/2024` or `14.03.2024`.
+    pub fn date(&self, date: NaiveDate) -> String {
+        let (year, month, day) = (date.year(), date.month(), date.day());
+        match self.locale {
+            Locale::En => format!("{}/{}/{}", month, day, year),
+            Locale::Fr | Locale::It | Locale::Pt => format!("{:02}/{:02}/{}", day, month, year),
+            Locale::De => format!("{:02}.{:02}.{}", day, month, year),
+            Locale::Es => format!("{}/{}/{}", day, month, year),
+            Locale::Ja => format!("{}/{:02}/{:02}", year, month, day),
+            Locale::Ko => format!("{}. {}. {}.", year, month, day),
+            Locale::Zh | Locale::ZhTw => format!("{}/{}/{}", year, month, day),
+        }
+    }
+
+    /// Formats a time of day in hours and minutes, e.g. `3:07 PM` or `15:07`.
+    pub fn time(&self, time: NaiveTime) -> String {
+        let (hour, minute) = (time.hour(), time.minute());
+        match self.locale {
+            Locale::En => {
+                let (pm, hour) = time.hour12();
+                format!("{}:{:02} {}", hour, minute, if pm { "PM" } else { "AM" })
+            }
+            Locale::Ko | Locale::ZhTw => {
+                let (pm, hour) = time.hour12();
+                let period = match (self.locale, pm) {
+                    (Locale::Ko, false) => "오전",
+                    (Locale::Ko, true) => "오후",
+                    (_, false) => "上午",
+                    (_, true) => "下午",
+                };
+                let space = if self.locale == Locale::Ko { " " } else { "" };
+                format!("{}{}{}:{:02}", period, space, hour, minute)
+            }
+            Locale::Fr | Locale::De | Locale::It | Locale::Pt | Locale::Zh => {
+                format!("{:02}:{:02}", hour, minute)
+            }
+            Locale::Es | Locale::Ja => format!("{}:{:02}", hour, minute),
+        }
+    }
+
+    /// Formats a time relative to now, e.g. `in 5 minutes` or `vor 2 Stunden`.
+    ///
+    /// Positive offsets are in the future and negative offsets are in the past. The offset is
+    /// rounded down to the largest whole unit, from seconds up to days.
+    pub fn relative(&self, offset: TimeDelta) -> String {
+        let future = offset >= TimeDelta::zero();
+        let offset = offset.abs();
+        let (count, unit) = if offset.num_days() > 0 {
+            (offset.num_days(), Unit::Day)
+        } else if offset.num_hours() > 0 {
+            (offset.num_hours(), Unit::Hour)
+        } else if offset.num_minutes() > 0 {
+            (offset.num_minutes(), Unit::Minute)
+        } else {
+            (offset.num_seconds(), Unit::Second)
+        };
+        let one = count == 1;
+
+        match self.locale {
+            Locale::En => {
+                let unit = match unit {
+                    Unit::Second => "second",
+                    Unit::Minute => "minute",
+                    Unit::Hour => "hour",
+                    Unit::Day => "day",
+                };
+                let s = if one { "" } else { "s" };
+                if future {
+                    format!("in {} {}{}", count, unit, s)
+                } else {
+                    format!("{} {}{} ago", count, unit, s)
+                }
+            }
+            Locale::Fr => {
+                let unit = match unit {
+                    Unit::Second => "seconde",
+                    Unit::Minute => "minute",
+                    Unit::Hour => "heure",
+                    Unit::Day => "jour",
+                };
+                let s = if one { "" } else { "s" };
+                if future {
+                    format!("dans {} {}{}", count, unit, s)
+                } else {
+                    format!("il y a {} {}{}", count, unit, s)
+                }
+            }
+            Locale::De => {
+                let unit = match (unit, one) {
+                    (Unit::Second, true) => "Sekunde",
+                    (Unit::Second, false) => "Sekunden",
+                    (Unit::Minute, true) => "Minute",
+                    (Unit::Minute, false) => "Minuten",
+                    (Unit::Hour, true) => "Stunde",
+                    (Unit::Hour, false) => "Stunden",
+                    (Unit::Day, true) => "Tag",
+                    (Unit::Day, false) => "Tagen",
+                };
+                format!("{} {} {}", if future { "in" } else { "vor" }, count, unit)
+            }
+            Locale::Es => {
+                let unit = match unit {
+                    Unit::Second => "segundo",
+                    Unit::Minute => "minuto",
+                    Unit::Hour => "hora",
+                    Unit::Day => "día",
+                };
+                let s = if one { "" } else { "s" };
+                if future {
+                    format!("dentro de {} {}{}", count, unit, s)
+                } else {
+                    format!("hace {} {}{}", count, unit, s)
+                }
+            }
+            Locale::It => {
+                let unit = match (unit, one) {
+                    (Unit::Second, true) => "secondo",
+                    (Unit::Second, false) => "secondi",
+                    (Unit::Minute, true) => "minuto",
+                    (Unit::Minute, false) => "minuti",
+                    (Unit::Hour, true) => "ora",
+                    (Unit::Hour, false) => "ore",
+                    (Unit::Day, true) => "giorno",
+                    (Unit::Day, false) => "giorni",
+                };
+                if future {
+                    format!("tra {} {}", count, unit)
+                } else {
+                    format!("{} {} fa", count, unit)
+                }
+            }
+            Locale::Pt => {
+                let unit = match unit {
+                    Unit::Second => "segundo",
+                    Unit::Minute => "minuto",
+                    Unit::Hour => "hora",
+                    Unit::Day => "dia",
+                };
+                let s = if one { "" } else { "s" };
+                if future {
+                    format!("em {} {}{}", count, unit, s)
+                } else {
+                    format!("há {} {}{}", count, unit, s)
+                }
+            }
+            Locale::Ja => {
+                let unit = match unit {
+                    Unit::Second => "秒",
+                    Unit::Minute => "分",
+                    Unit::Hour => "時間",
+                    Unit::Day => "日",
+                };
+                format!("{} {}{}", count, unit, if future { "後" } else { "前" })
+            }
+            Locale::Zh => {
+                let unit = match unit {
+                    Unit::Second => "秒钟",
+                    Unit::Minute => "分钟",
+                    Unit::Hour => "小时",
+                    Unit::Day => "天",
+                };
+                format!("{}{}{}", count, unit, if future { "后" } else { "前" })
+            }
+            Locale::Ko => {
+                let unit = match unit {
+                    Unit::Second => "초",
+                    Unit::Minute => "분",
+                    Unit::Hour => "시간",
+                    Unit::Day => "일",
+                };
+                format!("{}{} {}", count, unit, if future { "후" } else { "전" })
+            }
+            Locale::ZhTw => {
+                let unit = match unit {
+                    Unit::Second => "秒",
+                    Unit::Minute => "分鐘",
+                    Unit::Hour => "小時",
+                    Unit::Day => "天",
+                };
+                format!("{} {}{}", count, unit, if future { "後" } else { "前" })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Formatter;
+    use crate::registration::Language;
+    use chrono::{NaiveDate, NaiveTime, TimeDelta};
+
+    #[test]
+    fn formats() {
+        let en = Formatter::new(&Language::English);
+        let fr = Formatter::new(&Language::French);
+        let es = Formatter::new(&Language::Spanish);
+        let de = Formatter::new(&Language::German);
+
+        assert_eq!("-1,234,567.89", en.number(-1234567.891, 2));
+        assert_eq!("0", en.number(-0.1, 0));
+        assert_eq!("1\u{202f}234,50", fr.number(1234.5, 2));
+        assert_eq!("1234", es.number(1234.0, 0));
+        assert_eq!("12.345", es.number(12345.0, 0));
+
+        let date = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        assert_eq!("3/4/2024", en.date(date));
+        assert_eq!("04.03.2024", de.date(date));
+
+        let time = NaiveTime::from_hms_opt(15, 7, 0).unwrap();
+        assert_eq!("3:07 PM", en.time(time));
+        assert_eq!("15:07", fr.time(time));
+
+        assert_eq!("in 1 minute", en.relative(TimeDelta::seconds(90)));
+        assert_eq!("vor 2 Tagen", de.relative(TimeDelta::days(-2)));
+        assert_eq!("hace 3 horas", es.relative(TimeDelta::hours(-3)));
+
+        let it = Formatter::new(&Language::Italian);
+        let pt = Formatter::new(&Language::Portuguese);
+        let ko = Formatter::new(&Language::Korean);
+        let zh_tw = Formatter::new(&Language::ChineseTaiwan);
+
+        assert_eq!("1.234,5", it.number(1234.5, 1));
+        assert_eq!("1.234,5", pt.number(1234.5, 1));
+        assert_eq!("1,234.5", ko.number(1234.5, 1));
+        assert_eq!("1,234.5", zh_tw.number(1234.5, 1));
+        assert_eq!("04/03/2024", pt.date(date));
+        assert_eq!("2024. 3. 4.", ko.date(date));
+        assert_eq!("오후 3:07", ko.time(time));
+        assert_eq!("下午3:07", zh_tw.time(time));
+        assert_eq!("2 giorni fa", it.relative(TimeDelta::days(-2)));
+        assert_eq!("em 1 minuto", pt.relative(TimeDelta::seconds(90)));
+        assert_eq!("3시간 전", ko.relative(TimeDelta::hours(-3)));
+        assert_eq!("5 分鐘後", zh_tw.relative(TimeDelta::minutes(5)));
+    }
+}
@@ -1,19 +1,32 @@
+use crate::registration::RegistrationInfoApplication;
 use crate::{LogMessagePayload, MessageOut};
 use futures::channel::mpsc;
-use slog::{Drain, Key, OwnedKVList, Record, KV};
+use slog::{Drain, Key, Logger, OwnedKVList, Record, KV};
 use std::fmt::{self, Write};
 use std::sync::Mutex;
 
 pub struct StreamDeckDrain<G, S, M> {
     sink: Mutex<mpsc::UnboundedSender<MessageOut<G, S, M>>>,
+    plugin_uuid: Option<String>,
 }
 
 impl<G, S, M> StreamDeckDrain<G, S, M> {
     pub fn new(sink: mpsc::UnboundedSender<MessageOut<G, S, M>>) -> Self {
         Self {
             sink: Mutex::new(sink),
+            plugin_uuid: None,
         }
     }
+
+    /// Prefixes each log message with the plugin uuid.
+    ///
+    /// The Stream Deck software writes the log messages of every plugin to the same file, so this
+    /// makes it possible to tell which plugin wrote which message. When a message has `action`
+    /// or `context` values, they are moved into the prefix as well.
+    pub fn with_plugin_uuid<U: Into<String>>(mut self, uuid: U) -> Self {
+        self.plugin_uuid = Some(uuid.into());
+        self
+    }
 }
 
 impl<G, S, M> Drain for StreamDeckDrain<G, S, M> {
@@ -21,12 +34,34 @@ impl<G, S, M> Drain for StreamDeckDrain<G, S, M> {
     type Err = mpsc::TrySendError<MessageOut<G, S, M>>;
 
     fn log(&self, record: &Record, values: &OwnedKVList) -> Result<Self::Ok, Self::Err> {
-        let mut message = format!("{} {}", record.level().as_short_str(), record.msg());
-
-        let mut serializer = Serializer { stack: Vec::new() };
+        let mut serializer = Serializer {
+            stack: Vec::new(),
+            capture_context: self.plugin_uuid.is_some(),
+            action: None,
+            context: None,
+        };
         record.kv().serialize(record, &mut serializer).unwrap();
         values.serialize(record, &mut serializer).unwrap();
 
+        let mut message = String::new();
+        if let Some(uuid) = &self.plugin_uuid {
+            write!(message, "[{}", uuid).unwrap();
+            if let Some(action) = &serializer.action {
+                write!(message, " {}", action).unwrap();
+            }
+            if let Some(context) = &serializer.context {
+                write!(message, " {}", context).unwrap();
+            }
+            message.push_str("] ");
+        }
+        write!(
+            message,
+            "{} {}",
+            record.level().as_short_str(),
+            record.msg()
+        )
+        .unwrap();
+
         let kv_len = serializer.stack.iter().fold(0, |a, b| a + b.len() + 2);
         message.reserve_exact(kv_len);
         while let Some(value) = serializer.stack.pop() {
@@ -42,8 +77,41 @@ impl<G, S, M> Drain for StreamDeckDrain<G, S, M> {
     }
 }
 
+/// Logs a warning if the Stream Deck software is newer than the version implemented by this crate.
+///
+/// This is intended to be called once after connecting, so that events received as
+/// [`Message::Unknown`](../enum.Message.html#variant.Unknown) can be explained by the log.
+pub fn warn_version_mismatch(logger: &Logger, application: &RegistrationInfoApplication) {
+    if let Some(mismatch) = application.version_mismatch() {
+        slog::warn!(logger, "Stream Deck software is newer than supported; some events may not be understood";
+            "host_version" => &mismatch.host_version,
+            "sdk_version" => mismatch.sdk_version);
+    }
+}
+
 struct Serializer {
     stack: Vec<String>,
+    capture_context: bool,
+    action: Option<String>,
+    context: Option<String>,
+}
+
+impl Serializer {
+    /// Takes the value for the prefix if `key` is part of the prefix and the value is not known yet.
+    fn capture(&mut self, key: Key, val: &dyn fmt::Display) -> bool {
+        if !self.capture_context {
+            return false;
+        }
+        let slot = match key {
+            "action" => &mut self.action,
+            "context" => &mut self.context,
+            _ => return false,
+        };
+        if slot.is_none() {
+            *slot = Some(val.to_string());
+        }
+        true
+    }
 }
 
 impl slog::Serializer for Serializer {
@@ -112,11 +180,61 @@ impl slog::Serializer for Serializer {
         Ok(())
     }
     fn emit_str(&mut self, key: Key, val: &str) -> slog::Result {
+        if self.capture(key, &val) {
+            return Ok(());
+        }
         self.stack.push(format!("{}: {}", key, val));
         Ok(())
     }
     fn emit_arguments(&mut self, key: Key, val: &fmt::Arguments) -> slog::Result {
+        if self.capture(key, val) {
+            return Ok(());
+        }
         self.stack.push(format!("{}: {}", key, val));
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::StreamDeckDrain;
+    use crate::MessageOut;
+    use futures::channel::mpsc;
+    use serde_json::Value;
+    use slog::{o, Drain, Logger};
+
+    fn logged(receiver: &mut mpsc::UnboundedReceiver<MessageOut<Value, Value, Value>>) -> String {
+        match receiver.try_recv() {
+            Ok(MessageOut::LogMessage { payload }) => payload.message,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn plugin_uuid_prefix() {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let drain = StreamDeckDrain::new(sender).with_plugin_uuid("com.example.plugin");
+        let logger = Logger::root(drain.fuse(), o!("version" => 1));
+
+        slog::info!(logger, "started");
+        assert_eq!(
+            "[com.example.plugin] INFO started, version: 1",
+            logged(&mut receiver)
+        );
+
+        let action = logger.new(o!("action" => "com.example.plugin.action", "context" => "abc"));
+        slog::warn!(action, "slow"; "context" => "def", "ms" => 20);
+        assert_eq!(
+            "[com.example.plugin com.example.plugin.action def] WARN slow, version: 1, ms: 20",
+            logged(&mut receiver)
+        );
+    }
+
+    #[test]
+    fn no_prefix() {
+        let (sender, mut receiver) = mpsc::unbounded();
+        let logger = Logger::root(StreamDeckDrain::new(sender).fuse(), o!());
+        slog::info!(logger, "started"; "context" => "abc");
+        assert_eq!("INFO started, context: abc", logged(&mut receiver));
+    }
+}
@@ -0,0 +1,98 @@
+//! Measurements of the connection to the Stream Deck software.
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+/// Measurements of a [`StreamDeckSocket`](../socket/struct.StreamDeckSocket.html).
+///
+/// Byte counts are of the text of messages, without the framing of the web socket.
+#[derive(Clone, Debug, Default)]
+pub struct Metrics {
+    pub(crate) ping_rtt: Option<Duration>,
+    pub(crate) pings_sent: u64,
+    pub(crate) pongs_received: u64,
+    pub(crate) events_received: BTreeMap<String, u64>,
+    pub(crate) messages_sent: u64,
+    pub(crate) decode_failures: u64,
+    pub(crate) encode_failures: u64,
+    pub(crate) bytes_received: u64,
+    pub(crate) bytes_sent: u64,
+    pub(crate) queue_depth: usize,
+}
+
+impl Metrics {
+    /// The round trip time of the most recent ping, or None if no ping has been answered.
+    ///
+    /// See [`StreamDeckSocket::send_ping`](../socket/struct.StreamDeckSocket.html#method.send_ping).
+    pub fn ping_rtt(&self) -> Option<Duration> {
+        self.ping_rtt
+    }
+
+    /// The number of pings sent.
+    pub fn pings_sent(&self) -> u64 {
+        self.pings_sent
+    }
+
+    /// The number of pings that have been answered.
+    pub fn pongs_received(&self) -> u64 {
+        self.pongs_received
+    }
+
+    /// The number of messages received and decoded.
+    pub fn messages_received(&self) -> u64 {
+        self.events_received.values().sum()
+    }
+
+    /// The number of messages received and decoded for an event, such as `keyDown`.
+    pub fn events_received(&self, event: &str) -> u64 {
+        self.events_received.get(event).copied().unwrap_or_default()
+    }
+
+    /// Iterates over the events that have been received and the number of each, ordered by event.
+    pub fn iter_events_received(&self) -> impl Iterator<Item = (&str, u64)> {
+        self.events_received
+            .iter()
+            .map(|(event, count)| (event.as_str(), *count))
+    }
+
+    /// The number of messages sent.
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent
+    }
+
+    /// The number of messages received that could not be decoded.
+    pub fn decode_failures(&self) -> u64 {
+        self.decode_failures
+    }
+
+    /// The number of messages that could not be encoded to be sent.
+    pub fn encode_failures(&self) -> u64 {
+        self.encode_failures
+    }
+
+    /// The number of bytes of messages received, including messages that could not be decoded.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    /// The number of bytes of messages sent.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// The number of messages waiting to be sent by a
+    /// [`QueuedSocket`](../socket/struct.QueuedSocket.html), including updates held by its rate
+    /// limit.
+    pub fn queue_depth(&self) -> usize {
+        self.queue_depth
+    }
+
+    pub(crate) fn receive(&mut self, event: &str) {
+        match self.events_received.get_mut(event) {
+            Some(count) => *count += 1,
+            None => {
+                self.events_received.insert(event.to_string(), 1);
+            }
+        }
+    }
+}
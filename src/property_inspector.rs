@@ -0,0 +1,23 @@
+//! Support for property inspectors, the HTML pages used to configure actions.
+//!
+//! [Official Documentation](https://docs.elgato.com/sdk/plugins/property-inspector)
+
+mod client;
+#[cfg(feature = "wasm")]
+pub mod components;
+#[cfg(feature = "dev-server")]
+pub mod dev_server;
+mod message;
+mod registration;
+#[cfg(feature = "wasm")]
+pub mod socket;
+#[cfg(feature = "schemars")]
+pub mod template;
+mod tracker;
+
+pub use self::client::{Disconnected, PropertyInspectorClient, PropertyInspectorEvent};
+pub use self::message::{Message, MessageOut};
+pub use self::registration::{
+    ActionInfo, ActionInfoPayload, PropertyInspectorParams, PropertyInspectorParamsError,
+};
+pub use self::tracker::{BufferPolicy, PropertyInspectorTracker};
@@ -0,0 +1,298 @@
+use super::{Message, MessageOut, PropertyInspectorParams};
+use crate::{ActionUuid, Context, LogMessagePayload, UrlPayload};
+use failure::Fail;
+use futures::channel::{mpsc, oneshot};
+use futures::prelude::*;
+use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
+
+/// Sends messages to the Stream Deck software on behalf of a property inspector, without building
+/// [`MessageOut`](enum.MessageOut.html) values by hand.
+///
+/// The client queues messages in a channel, which should be forwarded to the socket, and
+/// [`events`](#method.events) turns the messages received from the socket into
+/// [`PropertyInspectorEvent`](enum.PropertyInspectorEvent.html)s while answering the requests
+/// waiting for settings.
+///
+/// The client can be cloned cheaply, and clones send to the same channel.
+///
+/// # Examples
+///
+/// ```
+/// # use futures::prelude::*;
+/// # use serde_json::Value;
+/// # use streamdeck_rs::property_inspector::{Message, PropertyInspectorClient, PropertyInspectorParams};
+/// # async fn example<St: Stream<Item = Message<Value, Value, Value>> + Unpin>(
+/// #     params: PropertyInspectorParams<Value>,
+/// #     messages: St,
+/// # ) {
+/// let (client, outgoing) = PropertyInspectorClient::<Value, Value, Value, Value>::new(&params);
+/// // forward `outgoing` to the socket, then
+/// let mut events = client.events(messages);
+/// let settings = client.get_global_settings();
+/// # }
+/// ```
+pub struct PropertyInspectorClient<G, S, MI, MO> {
+    action: ActionUuid,
+    context: Context,
+    sender: mpsc::UnboundedSender<MessageOut<G, S, MO>>,
+    waiting: Arc<Mutex<Waiting<G, S>>>,
+    _mi: PhantomData<MI>,
+}
+
+/// Requests waiting for settings.
+struct Waiting<G, S> {
+    settings: Vec<oneshot::Sender<S>>,
+    global_settings: Vec<oneshot::Sender<G>>,
+}
+
+/// An event received by a property inspector.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyInspectorEvent<G, S, M> {
+    /// The settings for the action were received, either because they were requested or because
+    /// the plugin changed them.
+    Settings(S),
+    /// The settings for the plugin were received, either because they were requested or because
+    /// they were changed.
+    GlobalSettings(G),
+    /// The plugin has sent data.
+    FromPlugin(M),
+}
+
+/// The messages could not be sent because the channel was closed.
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "the property inspector is disconnected")]
+pub struct Disconnected;
+
+impl<G, S, MI, MO> Clone for PropertyInspectorClient<G, S, MI, MO> {
+    fn clone(&self) -> Self {
+        PropertyInspectorClient {
+            action: self.action.clone(),
+            context: self.context.clone(),
+            sender: self.sender.clone(),
+            waiting: self.waiting.clone(),
+            _mi: PhantomData,
+        }
+    }
+}
+
+impl<G, S, MI, MO> PropertyInspectorClient<G, S, MI, MO> {
+    /// Creates a client for the property inspector described by `params`, returning the channel
+    /// of messages to send to the Stream Deck software.
+    pub fn new<A>(
+        params: &PropertyInspectorParams<A>,
+    ) -> (Self, mpsc::UnboundedReceiver<MessageOut<G, S, MO>>) {
+        let (sender, receiver) = mpsc::unbounded();
+        let client = PropertyInspectorClient {
+            action: params.action_info.action.clone(),
+            context: params.uuid.clone(),
+            sender,
+            waiting: Arc::new(Mutex::new(Waiting {
+                settings: Vec::new(),
+                global_settings: Vec::new(),
+            })),
+            _mi: PhantomData,
+        };
+        (client, receiver)
+    }
+
+    /// The uuid of the action being configured.
+    pub fn action(&self) -> &ActionUuid {
+        &self.action
+    }
+
+    /// The uuid the property inspector registered with.
+    pub fn context(&self) -> &Context {
+        &self.context
+    }
+
+    fn send(&self, message: MessageOut<G, S, MO>) -> Result<(), Disconnected> {
+        self.sender
+            .unbounded_send(message)
+            .map_err(|_| Disconnected)
+    }
+
+    /// Stores the settings for the action.
+    pub fn set_settings(&self, settings: S) -> Result<(), Disconnected> {
+        self.send(MessageOut::SetSettings {
+            context: self.context.clone(),
+            payload: settings,
+        })
+    }
+
+    /// Stores the settings for the plugin.
+    pub fn set_global_settings(&self, settings: G) -> Result<(), Disconnected> {
+        self.send(MessageOut::SetGlobalSettings {
+            context: self.context.clone(),
+            payload: settings,
+        })
+    }
+
+    /// Requests the settings for the action.
+    ///
+    /// The settings are received by [`events`](#method.events), which must be running for the
+    /// returned future to complete.
+    pub fn get_settings(&self) -> impl Future<Output = Result<S, Disconnected>> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiting.lock().unwrap().settings.push(sender);
+        let sent = self.send(MessageOut::GetSettings {
+            context: self.context.clone(),
+        });
+        async move {
+            sent?;
+            receiver.await.map_err(|_| Disconnected)
+        }
+    }
+
+    /// Requests the settings for the plugin.
+    ///
+    /// The settings are received by [`events`](#method.events), which must be running for the
+    /// returned future to complete.
+    pub fn get_global_settings(&self) -> impl Future<Output = Result<G, Disconnected>> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiting.lock().unwrap().global_settings.push(sender);
+        let sent = self.send(MessageOut::GetGlobalSettings {
+            context: self.context.clone(),
+        });
+        async move {
+            sent?;
+            receiver.await.map_err(|_| Disconnected)
+        }
+    }
+
+    /// Sends data to the plugin.
+    pub fn send_to_plugin(&self, message: MO) -> Result<(), Disconnected> {
+        self.send(MessageOut::SendToPlugin {
+            action: self.action.clone(),
+            context: self.context.clone(),
+            payload: message,
+        })
+    }
+
+    /// Opens a URL in the default browser.
+    pub fn open_url<T: Into<String>>(&self, url: T) -> Result<(), Disconnected> {
+        self.send(MessageOut::OpenUrl {
+            payload: UrlPayload::new(url),
+        })
+    }
+
+    /// Writes to the Stream Deck log.
+    pub fn log_message<T: Into<String>>(&self, message: T) -> Result<(), Disconnected> {
+        self.send(MessageOut::LogMessage {
+            payload: LogMessagePayload::new(message),
+        })
+    }
+
+    /// Converts the messages received from the Stream Deck software into events, completing the
+    /// requests waiting for settings along the way.
+    ///
+    /// Messages for other contexts are ignored.
+    pub fn events<St>(&self, messages: St) -> impl Stream<Item = PropertyInspectorEvent<G, S, MI>>
+    where
+        St: Stream<Item = Message<G, S, MI>>,
+        G: Clone,
+        S: Clone,
+    {
+        let context = self.context.clone();
+        let waiting = self.waiting.clone();
+        messages.filter_map(move |message| {
+            let event = match message {
+                Message::DidReceiveSettings {
+                    context: ref from,
+                    payload,
+                    ..
+                } if *from == context => {
+                    let waiters = std::mem::take(&mut waiting.lock().unwrap().settings);
+                    for waiter in waiters {
+                        let _ = waiter.send(payload.settings.clone());
+                    }
+                    Some(PropertyInspectorEvent::Settings(payload.settings))
+                }
+                Message::DidReceiveGlobalSettings { payload } => {
+                    let waiters = std::mem::take(&mut waiting.lock().unwrap().global_settings);
+                    for waiter in waiters {
+                        let _ = waiter.send(payload.settings.clone());
+                    }
+                    Some(PropertyInspectorEvent::GlobalSettings(payload.settings))
+                }
+                Message::SendToPropertyInspector {
+                    context: ref from,
+                    payload,
+                    ..
+                } if *from == context => Some(PropertyInspectorEvent::FromPlugin(payload)),
+                _ => None,
+            };
+            future::ready(event)
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PropertyInspectorClient, PropertyInspectorEvent};
+    use crate::property_inspector::{Message, MessageOut, PropertyInspectorParams};
+    use crate::GlobalSettingsPayload;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn client() {
+        let params: PropertyInspectorParams<Value> = PropertyInspectorParams::new(
+            28196,
+            "abc",
+            "registerPropertyInspector",
+            r#"{
+                "application": {"language": "en", "platform": "mac", "version": "6.5.0"},
+                "plugin": {"uuid": "com.example.plugin", "version": "1.0"},
+                "devicePixelRatio": 2,
+                "devices": []
+            }"#,
+            r#"{
+                "action": "com.example.plugin.action",
+                "context": "abc",
+                "device": "device",
+                "payload": {"settings": {}}
+            }"#,
+        )
+        .unwrap();
+        let (client, mut outgoing) =
+            PropertyInspectorClient::<Value, Value, Value, Value>::new(&params);
+
+        client.send_to_plugin(json!("refresh")).unwrap();
+        match outgoing.try_recv().unwrap() {
+            MessageOut::SendToPlugin {
+                action, context, ..
+            } => {
+                assert_eq!("com.example.plugin.action", action);
+                assert_eq!("abc", context);
+            }
+            _ => unreachable!(),
+        }
+
+        let settings = client.get_global_settings();
+        assert!(matches!(
+            outgoing.try_recv().unwrap(),
+            MessageOut::GetGlobalSettings { .. }
+        ));
+        let messages = stream::iter(vec![
+            Message::SendToPropertyInspector {
+                action: "com.example.plugin.action".parse().unwrap(),
+                context: "other".into(),
+                payload: json!("ignored"),
+            },
+            Message::DidReceiveGlobalSettings {
+                payload: GlobalSettingsPayload {
+                    settings: json!({"volume": 5}),
+                    extra: Default::default(),
+                },
+            },
+        ]);
+        let events: Vec<_> = block_on(client.events(messages).collect());
+        assert_eq!(
+            vec![PropertyInspectorEvent::GlobalSettings(json!({"volume": 5}))],
+            events
+        );
+        assert_eq!(json!({"volume": 5}), block_on(settings).unwrap());
+    }
+}
@@ -0,0 +1,429 @@
+//! [Yew](https://yew.rs) components for writing property inspectors in Rust.
+//!
+//! The components use the [sdpi](https://github.com/elgatosf/streamdeck-propertyinspector)
+//! markup so they match the appearance of the Stream Deck software when `sdpi.css` is included.
+//!
+//! [`PropertyInspector`](fn.PropertyInspector.html) connects a
+//! [`PropertyInspectorSocket`](../socket/struct.PropertyInspectorSocket.html) and keeps track of
+//! the settings of the action. The components inside it, such as
+//! [`SettingTextField`](fn.SettingTextField.html), are bound to a setting and store it when it is
+//! changed:
+//!
+//! ```ignore
+//! use std::rc::Rc;
+//! use streamdeck_rs::property_inspector::components::*;
+//!
+//! html! {
+//!     <PropertyInspector params={ Rc::new(params) }>
+//!         <SettingTextField label="Name" setting="name" />
+//!         <SettingCheckbox label="Enabled" setting="enabled" />
+//!         <PluginButton text="Refresh" message={ serde_json::json!("refresh") } />
+//!     </PropertyInspector>
+//! }
+//! ```
+//!
+//! The other components hold no state of their own. Each one displays a value taken from the
+//! settings and reports changes through a callback, which should update the settings and send
+//! them to the Stream Deck software.
+
+use super::socket::PropertyInspectorSocket;
+use super::{PropertyInspectorClient, PropertyInspectorEvent, PropertyInspectorParams};
+use futures::prelude::*;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::rc::Rc;
+use web_sys::{HtmlInputElement, HtmlSelectElement};
+use yew::platform::spawn_local;
+use yew::prelude::*;
+
+/// The properties of a [`TextField`](fn.TextField.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct TextFieldProps {
+    /// The label shown beside the field.
+    pub label: AttrValue,
+    /// The current value of the setting.
+    pub value: AttrValue,
+    /// Text shown when the field is empty.
+    #[prop_or_default]
+    pub placeholder: Option<AttrValue>,
+    /// Called with the new value when the user edits the field.
+    pub onchange: Callback<String>,
+}
+
+/// A text field bound to a string setting.
+#[function_component(TextField)]
+pub fn text_field(props: &TextFieldProps) -> Html {
+    let onchange = props.onchange.clone();
+    let oninput = Callback::from(move |event: InputEvent| {
+        let input: HtmlInputElement = event.target_unchecked_into();
+        onchange.emit(input.value());
+    });
+    html! {
+        <div class="sdpi-item">
+            <div class="sdpi-item-label">{ props.label.clone() }</div>
+            <input class="sdpi-item-value" type="text" value={ props.value.clone() }
+                placeholder={ props.placeholder.clone() } { oninput } />
+        </div>
+    }
+}
+
+/// The properties of a [`Checkbox`](fn.Checkbox.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct CheckboxProps {
+    /// The label shown beside the checkbox.
+    pub label: AttrValue,
+    /// The current value of the setting.
+    pub checked: bool,
+    /// Called with the new value when the user toggles the checkbox.
+    pub onchange: Callback<bool>,
+}
+
+/// A checkbox bound to a boolean setting.
+#[function_component(Checkbox)]
+pub fn checkbox(props: &CheckboxProps) -> Html {
+    let onchange = props.onchange.clone();
+    let onchange = Callback::from(move |event: Event| {
+        let input: HtmlInputElement = event.target_unchecked_into();
+        onchange.emit(input.checked());
+    });
+    let id = use_state(next_id);
+    html! {
+        <div class="sdpi-item" type="checkbox">
+            <div class="sdpi-item-label">{ props.label.clone() }</div>
+            <div class="sdpi-item-value">
+                <input id={ (*id).clone() } type="checkbox" checked={ props.checked } { onchange } />
+                <label for={ (*id).clone() }><span></span></label>
+            </div>
+        </div>
+    }
+}
+
+/// An option of a [`Select`](fn.Select.html).
+#[derive(Clone, Debug, PartialEq)]
+pub struct SelectOption {
+    /// The value stored in the setting when the option is selected.
+    pub value: AttrValue,
+    /// The text shown for the option.
+    pub label: AttrValue,
+}
+
+impl SelectOption {
+    /// Creates an option.
+    pub fn new<V: Into<AttrValue>, L: Into<AttrValue>>(value: V, label: L) -> Self {
+        SelectOption {
+            value: value.into(),
+            label: label.into(),
+        }
+    }
+}
+
+/// The properties of a [`Select`](fn.Select.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct SelectProps {
+    /// The label shown beside the list.
+    pub label: AttrValue,
+    /// The options to choose from.
+    pub options: Vec<SelectOption>,
+    /// The value of the currently selected option.
+    pub value: AttrValue,
+    /// Called with the value of the new option when the user changes the selection.
+    pub onchange: Callback<String>,
+}
+
+/// A drop down list bound to a setting with a fixed set of values.
+#[function_component(Select)]
+pub fn select(props: &SelectProps) -> Html {
+    let onchange = props.onchange.clone();
+    let onchange = Callback::from(move |event: Event| {
+        let select: HtmlSelectElement = event.target_unchecked_into();
+        onchange.emit(select.value());
+    });
+    html! {
+        <div class="sdpi-item" type="select">
+            <div class="sdpi-item-label">{ props.label.clone() }</div>
+            <select class="sdpi-item-value select" { onchange }>
+                { for props.options.iter().map(|option| html! {
+                    <option value={ option.value.clone() } selected={ option.value == props.value }>
+                        { option.label.clone() }
+                    </option>
+                }) }
+            </select>
+        </div>
+    }
+}
+
+/// The properties of a [`SendToPluginButton`](fn.SendToPluginButton.html).
+///
+/// `M` represents the messages sent to the plugin.
+#[derive(Clone, PartialEq, Properties)]
+pub struct SendToPluginButtonProps<M: Clone + PartialEq + 'static> {
+    /// The label shown beside the button.
+    #[prop_or_default]
+    pub label: Option<AttrValue>,
+    /// The text on the button.
+    pub text: AttrValue,
+    /// The message to send to the plugin when the button is clicked.
+    pub message: M,
+    /// Called with the message when the button is clicked. This should send the message to the
+    /// plugin using the `sendToPlugin` event.
+    pub onsend: Callback<M>,
+}
+
+/// A button that sends a message to the plugin.
+#[function_component(SendToPluginButton)]
+pub fn send_to_plugin_button<M: Clone + PartialEq + 'static>(
+    props: &SendToPluginButtonProps<M>,
+) -> Html {
+    let onclick = {
+        let message = props.message.clone();
+        props.onsend.reform(move |_: MouseEvent| message.clone())
+    };
+    html! {
+        <div class="sdpi-item" type="button">
+            <div class="sdpi-item-label">{ props.label.clone().unwrap_or_default() }</div>
+            <button class="sdpi-item-value" { onclick }>{ props.text.clone() }</button>
+        </div>
+    }
+}
+
+/// The connection shared with the components inside a
+/// [`PropertyInspector`](fn.PropertyInspector.html), available through `use_context`.
+#[derive(Clone)]
+pub struct PropertyInspectorHandle {
+    client: PropertyInspectorClient<Value, Value, Value, Value>,
+    settings: Rc<Value>,
+    update: Callback<Rc<Value>>,
+}
+
+impl PartialEq for PropertyInspectorHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Rc::ptr_eq(&self.settings, &other.settings)
+    }
+}
+
+impl PropertyInspectorHandle {
+    /// The client used to send messages to the Stream Deck software.
+    pub fn client(&self) -> &PropertyInspectorClient<Value, Value, Value, Value> {
+        &self.client
+    }
+
+    /// The current settings of the action.
+    pub fn settings(&self) -> &Value {
+        &self.settings
+    }
+
+    /// Changes a setting and stores the settings of the action.
+    pub fn set(&self, setting: &str, value: Value) {
+        let mut settings = (*self.settings).clone();
+        if !settings.is_object() {
+            settings = Value::Object(Default::default());
+        }
+        settings[setting] = value;
+        // the settings are only lost if the connection is closed
+        let _ = self.client.set_settings(settings.clone());
+        self.update.emit(Rc::new(settings));
+    }
+}
+
+/// The properties of a [`PropertyInspector`](fn.PropertyInspector.html).
+#[derive(PartialEq, Properties)]
+pub struct PropertyInspectorProps {
+    /// The parameters passed to `connectElgatoStreamDeckSocket`.
+    pub params: Rc<PropertyInspectorParams<Value>>,
+    /// Called with each message sent by the plugin.
+    #[prop_or_default]
+    pub onmessage: Callback<Value>,
+    /// The components of the property inspector.
+    #[prop_or_default]
+    pub children: Children,
+}
+
+/// Connects to the Stream Deck software and provides a
+/// [`PropertyInspectorHandle`](struct.PropertyInspectorHandle.html) to the components inside it.
+///
+/// The connection is made when the component is first rendered and lasts as long as the page.
+#[function_component(PropertyInspector)]
+pub fn property_inspector(props: &PropertyInspectorProps) -> Html {
+    let settings = {
+        let params = props.params.clone();
+        use_state(move || Rc::new(params.action_info.payload.settings.clone()))
+    };
+    let onmessage = use_mut_ref(Callback::default);
+    *onmessage.borrow_mut() = props.onmessage.clone();
+    let client = {
+        let params = props.params.clone();
+        let settings = settings.setter();
+        use_memo((), move |()| connect(&params, settings, onmessage))
+    };
+    let handle = PropertyInspectorHandle {
+        client: (*client).clone(),
+        settings: (*settings).clone(),
+        update: {
+            let settings = settings.setter();
+            Callback::from(move |value| settings.set(value))
+        },
+    };
+    html! {
+        <ContextProvider<PropertyInspectorHandle> context={ handle }>
+            { props.children.clone() }
+        </ContextProvider<PropertyInspectorHandle>>
+    }
+}
+
+/// Connects the socket and starts forwarding messages between it and the client.
+fn connect(
+    params: &PropertyInspectorParams<Value>,
+    settings: UseStateSetter<Rc<Value>>,
+    onmessage: Rc<RefCell<Callback<Value>>>,
+) -> PropertyInspectorClient<Value, Value, Value, Value> {
+    let (client, outgoing) = PropertyInspectorClient::new(params);
+    let socket = match PropertyInspectorSocket::<Value, Value, Value, Value>::connect(params) {
+        Ok(socket) => socket,
+        Err(error) => {
+            web_sys::console::error_1(&error.to_string().into());
+            return client;
+        }
+    };
+    let (sink, stream) = socket.split();
+    spawn_local(outgoing.map(Ok).forward(sink).map(|_| ()));
+    let messages = stream.filter_map(|message| {
+        if let Err(error) = &message {
+            web_sys::console::error_1(&error.to_string().into());
+        }
+        future::ready(message.ok())
+    });
+    let events = client.events(messages).for_each(move |event| {
+        match event {
+            PropertyInspectorEvent::Settings(value) => settings.set(Rc::new(value)),
+            PropertyInspectorEvent::FromPlugin(message) => onmessage.borrow().emit(message),
+            PropertyInspectorEvent::GlobalSettings(_) => {}
+        }
+        future::ready(())
+    });
+    spawn_local(events);
+    client
+}
+
+/// Gets the handle provided by the surrounding [`PropertyInspector`](fn.PropertyInspector.html).
+#[hook]
+fn use_handle() -> PropertyInspectorHandle {
+    use_context::<PropertyInspectorHandle>()
+        .expect("the component must be inside a PropertyInspector")
+}
+
+/// The properties of a [`SettingTextField`](fn.SettingTextField.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct SettingTextFieldProps {
+    /// The label shown beside the field.
+    pub label: AttrValue,
+    /// The name of the setting.
+    pub setting: AttrValue,
+    /// Text shown when the field is empty.
+    #[prop_or_default]
+    pub placeholder: Option<AttrValue>,
+}
+
+/// A [`TextField`](fn.TextField.html) bound to a string setting of the action.
+#[function_component(SettingTextField)]
+pub fn setting_text_field(props: &SettingTextFieldProps) -> Html {
+    let handle = use_handle();
+    let value = handle.settings()[props.setting.as_str()]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let onchange = {
+        let setting = props.setting.clone();
+        Callback::from(move |value| handle.set(&setting, Value::String(value)))
+    };
+    html! {
+        <TextField label={ props.label.clone() } value={ value }
+            placeholder={ props.placeholder.clone() } { onchange } />
+    }
+}
+
+/// The properties of a [`SettingCheckbox`](fn.SettingCheckbox.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct SettingCheckboxProps {
+    /// The label shown beside the checkbox.
+    pub label: AttrValue,
+    /// The name of the setting.
+    pub setting: AttrValue,
+}
+
+/// A [`Checkbox`](fn.Checkbox.html) bound to a boolean setting of the action.
+#[function_component(SettingCheckbox)]
+pub fn setting_checkbox(props: &SettingCheckboxProps) -> Html {
+    let handle = use_handle();
+    let checked = handle.settings()[props.setting.as_str()]
+        .as_bool()
+        .unwrap_or_default();
+    let onchange = {
+        let setting = props.setting.clone();
+        Callback::from(move |checked| handle.set(&setting, Value::Bool(checked)))
+    };
+    html! {
+        <Checkbox label={ props.label.clone() } { checked } { onchange } />
+    }
+}
+
+/// The properties of a [`SettingSelect`](fn.SettingSelect.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct SettingSelectProps {
+    /// The label shown beside the list.
+    pub label: AttrValue,
+    /// The name of the setting.
+    pub setting: AttrValue,
+    /// The options to choose from.
+    pub options: Vec<SelectOption>,
+}
+
+/// A [`Select`](fn.Select.html) bound to a string setting of the action.
+#[function_component(SettingSelect)]
+pub fn setting_select(props: &SettingSelectProps) -> Html {
+    let handle = use_handle();
+    let value = handle.settings()[props.setting.as_str()]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let onchange = {
+        let setting = props.setting.clone();
+        Callback::from(move |value| handle.set(&setting, Value::String(value)))
+    };
+    html! {
+        <Select label={ props.label.clone() } options={ props.options.clone() } value={ value }
+            { onchange } />
+    }
+}
+
+/// The properties of a [`PluginButton`](fn.PluginButton.html).
+#[derive(Clone, PartialEq, Properties)]
+pub struct PluginButtonProps {
+    /// The label shown beside the button.
+    #[prop_or_default]
+    pub label: Option<AttrValue>,
+    /// The text on the button.
+    pub text: AttrValue,
+    /// The message to send to the plugin when the button is clicked.
+    pub message: Value,
+}
+
+/// A [`SendToPluginButton`](fn.SendToPluginButton.html) that sends its message through the
+/// connection of the surrounding [`PropertyInspector`](fn.PropertyInspector.html).
+#[function_component(PluginButton)]
+pub fn plugin_button(props: &PluginButtonProps) -> Html {
+    let handle = use_handle();
+    let onsend = Callback::from(move |message| {
+        // the message is only lost if the connection is closed
+        let _ = handle.client().send_to_plugin(message);
+    });
+    html! {
+        <SendToPluginButton<Value> label={ props.label.clone() } text={ props.text.clone() }
+            message={ props.message.clone() } { onsend } />
+    }
+}
+
+fn next_id() -> AttrValue {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    format!("sdpi-{}", NEXT.fetch_add(1, Ordering::Relaxed)).into()
+}
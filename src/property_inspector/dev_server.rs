@@ -0,0 +1,316 @@
+//! A web server for developing property inspectors in a regular browser.
+//!
+//! The server serves the files of a property inspector from a directory. HTML pages have a script
+//! added which reloads the page when any of the files change and, if a
+//! [`ShimConnection`](struct.ShimConnection.html) is configured, calls
+//! `connectElgatoStreamDeckSocket` the way the Stream Deck software would. Pointing the connection
+//! at a simulator or a relay allows the property inspector to talk to the plugin without
+//! reinstalling the plugin after each change.
+
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::net::SocketAddr;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+/// The path the injected script polls to detect changes.
+const VERSION_PATH: &str = "/__streamdeck/version";
+
+/// The arguments passed to `connectElgatoStreamDeckSocket` by the injected script.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShimConnection {
+    /// The port of the WebSocket to connect to.
+    pub port: u16,
+    /// The UUID to register with.
+    pub uuid: String,
+    /// The event to register with, usually `registerPropertyInspector`.
+    pub register_event: String,
+    /// The information about the Stream Deck software, plugin, and devices.
+    pub info: Value,
+    /// The information about the action, including its settings.
+    pub action_info: Value,
+}
+
+/// Serves a property inspector with live reload.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use streamdeck_rs::property_inspector::dev_server::DevServer;
+/// # async fn example() -> std::io::Result<()> {
+/// DevServer::new("property-inspector")
+///     .serve(([127, 0, 0, 1], 8080).into())
+///     .await
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct DevServer {
+    root: PathBuf,
+    connection: Option<ShimConnection>,
+}
+
+impl DevServer {
+    /// Creates a server for the files in a directory.
+    pub fn new<P: Into<PathBuf>>(root: P) -> Self {
+        DevServer {
+            root: root.into(),
+            connection: None,
+        }
+    }
+
+    /// Connects pages to a WebSocket as if they had been opened by the Stream Deck software.
+    pub fn with_connection(mut self, connection: ShimConnection) -> Self {
+        self.connection = Some(connection);
+        self
+    }
+
+    /// Serves requests until an error occurs accepting a connection.
+    ///
+    /// This must be run within a Tokio runtime.
+    pub async fn serve(self, address: SocketAddr) -> io::Result<()> {
+        let listener = TcpListener::bind(address).await?;
+        let shim = Arc::new(shim(self.connection.as_ref()));
+        let root = Arc::new(self.root);
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let root = root.clone();
+            let shim = shim.clone();
+            tokio::spawn(async move {
+                // the browser will retry or show the failure, so errors are not reported here
+                let _ = handle(stream, &root, &shim).await;
+            });
+        }
+    }
+}
+
+async fn handle(mut stream: TcpStream, root: &Path, shim: &str) -> io::Result<()> {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() > 16 * 1024 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.lines().next().unwrap_or_default().split(' ');
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(VERSION_PATH)) => {
+            let root = root.to_path_buf();
+            match tokio::task::spawn_blocking(move || version(&root)).await {
+                Ok(version) => Response::ok("text/plain", version.to_string().into_bytes()),
+                Err(_) => Response::status("500 Internal Server Error"),
+            }
+        }
+        (Some("GET"), Some(target)) => match resolve(root, target) {
+            Some(path) => match tokio::fs::read(&path).await {
+                Ok(body) => {
+                    let content_type = content_type(&path);
+                    let body = if content_type.starts_with("text/html") {
+                        inject(&String::from_utf8_lossy(&body), shim).into_bytes()
+                    } else {
+                        body
+                    };
+                    Response::ok(content_type, body)
+                }
+                Err(_) => Response::status("404 Not Found"),
+            },
+            None => Response::status("404 Not Found"),
+        },
+        _ => Response::status("405 Method Not Allowed"),
+    };
+
+    stream.write_all(&response.into_bytes()).await?;
+    stream.shutdown().await
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn ok(content_type: &'static str, body: Vec<u8>) -> Self {
+        Response {
+            status: "200 OK",
+            content_type,
+            body,
+        }
+    }
+
+    fn status(status: &'static str) -> Self {
+        Response {
+            status,
+            content_type: "text/plain",
+            body: status.as_bytes().to_vec(),
+        }
+    }
+
+    fn into_bytes(self) -> Vec<u8> {
+        let mut bytes = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nCache-Control: no-store\r\nConnection: close\r\n\r\n",
+            self.status,
+            self.content_type,
+            self.body.len()
+        )
+        .into_bytes();
+        bytes.extend(self.body);
+        bytes
+    }
+}
+
+/// Maps a request target to a file within the root, refusing to leave the root.
+fn resolve(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or_default();
+    let path = percent_decode(path)?;
+    let mut resolved = root.to_path_buf();
+    for component in Path::new(path.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => resolved.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    if resolved.is_dir() {
+        resolved.push("index.html");
+    }
+    Some(resolved)
+}
+
+fn percent_decode(value: &str) -> Option<String> {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            decoded.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(decoded).ok()
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css",
+        Some("js") => "text/javascript",
+        Some("json") => "application/json",
+        Some("wasm") => "application/wasm",
+        Some("png") => "image/png",
+        Some("svg") => "image/svg+xml",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// Computes a value that changes whenever a file under the root is added, removed, or modified.
+fn version(root: &Path) -> u64 {
+    fn visit(path: &Path, hasher: &mut DefaultHasher) {
+        let entries = match std::fs::read_dir(path) {
+            Ok(entries) => entries,
+            Err(_) => return,
+        };
+        let mut entries: Vec<_> = entries.filter_map(Result::ok).collect();
+        entries.sort_by_key(|entry| entry.path());
+        for entry in entries {
+            let path = entry.path();
+            path.hash(hasher);
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_dir() {
+                    visit(&path, hasher);
+                    continue;
+                }
+                metadata.len().hash(hasher);
+                if let Ok(modified) = metadata.modified() {
+                    modified.duration_since(UNIX_EPOCH).ok().hash(hasher);
+                }
+            }
+        }
+    }
+
+    let mut hasher = DefaultHasher::new();
+    visit(root, &mut hasher);
+    hasher.finish()
+}
+
+fn shim(connection: Option<&ShimConnection>) -> String {
+    let connection = serde_json::to_string(&connection)
+        .expect("connection")
+        .replace("</", "<\\/");
+    format!(
+        r#"<script>
+(function () {{
+  var connection = {connection};
+  if (connection) {{
+    window.addEventListener('load', function () {{
+      if (typeof connectElgatoStreamDeckSocket === 'function') {{
+        connectElgatoStreamDeckSocket(connection.port, connection.uuid, connection.registerEvent,
+          JSON.stringify(connection.info), JSON.stringify(connection.actionInfo));
+      }}
+    }});
+  }}
+  var version = null;
+  setInterval(function () {{
+    fetch('{version_path}').then(function (response) {{ return response.text(); }}).then(function (latest) {{
+      if (version !== null && latest !== version) {{
+        location.reload();
+      }}
+      version = latest;
+    }}).catch(function () {{}});
+  }}, 1000);
+}})();
+</script>
+"#,
+        connection = connection,
+        version_path = VERSION_PATH
+    )
+}
+
+/// Adds the script to the end of the body of a page.
+fn inject(html: &str, shim: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => {
+            let mut injected = String::with_capacity(html.len() + shim.len());
+            injected.push_str(&html[..index]);
+            injected.push_str(shim);
+            injected.push_str(&html[index..]);
+            injected
+        }
+        None => format!("{}{}", html, shim),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{inject, resolve};
+    use std::path::Path;
+
+    #[test]
+    fn requests() {
+        let root = Path::new("/nonexistent/pi");
+        assert_eq!(
+            Some(root.join("css/sdpi.css")),
+            resolve(root, "/css/sdpi%2Ecss?v=1")
+        );
+        assert_eq!(None, resolve(root, "/../secret"));
+        assert_eq!(
+            "<body>a<script></script></body>",
+            inject("<body>a</body>", "<script></script>")
+        );
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    ActionUuid, Context, DeviceId, GlobalSettingsPayload, KeyPayload, LogMessagePayload, UrlPayload,
+};
+use serde::de;
+use serde_derive::{Deserialize, Serialize};
+
+received_message! {
+/// A message received by a property inspector from the Stream Deck software.
+///
+/// - `G` represents the global settings that are persisted within the Stream Deck software.
+/// - `S` represents the settings that are persisted within the Stream Deck software.
+/// - `M` represents the messages that are received from the plugin.
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/property-inspector#events-received)
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum Message<G, S, M> {
+    /// The application has sent settings for the action.
+    ///
+    /// This message is sent in response to GetSettings, but also after the plugin changes the
+    /// settings.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#didreceivesettings)
+    #[serde(rename_all = "camelCase")]
+    DidReceiveSettings {
+        /// The uuid of the action.
+        action: ActionUuid,
+        /// The instance of the action (key or part of a multiaction).
+        context: Context,
+        /// The device where the action exists.
+        device: DeviceId,
+        /// The current settings for the action.
+        payload: KeyPayload<S>,
+    },
+    /// The application has sent settings for the plugin.
+    ///
+    /// This message is sent in response to GetGlobalSettings, but also after the plugin changes
+    /// the settings.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#didreceiveglobalsettings)
+    #[serde(rename_all = "camelCase")]
+    DidReceiveGlobalSettings {
+        /// The current settings for the plugin.
+        payload: GlobalSettingsPayload<G>,
+    },
+    /// The plugin has sent data.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-received/#sendtopropertyinspector)
+    #[serde(rename_all = "camelCase")]
+    SendToPropertyInspector {
+        /// The uuid of the action.
+        action: ActionUuid,
+        /// The instance of the action (key or part of a multiaction).
+        context: Context,
+        /// Information sent from the plugin.
+        payload: M,
+    },
+}
+}
+
+/// A message to be sent by a property inspector to the Stream Deck software.
+///
+/// - `G` represents the global settings that are persisted within the Stream Deck software.
+/// - `S` represents the settings that are persisted within the Stream Deck software.
+/// - `M` represents the messages that are sent to the plugin.
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/property-inspector#events-sent)
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "event", rename_all = "camelCase")]
+#[non_exhaustive]
+pub enum MessageOut<G, S, M> {
+    /// Retrieve settings for the action via DidReceiveSettings.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#getsettings)
+    #[serde(rename_all = "camelCase")]
+    GetSettings {
+        /// The uuid the property inspector registered with.
+        context: Context,
+    },
+    /// Store settings for the action.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#setsettings)
+    #[serde(rename_all = "camelCase")]
+    SetSettings {
+        /// The uuid the property inspector registered with.
+        context: Context,
+        /// The settings to save.
+        payload: S,
+    },
+    /// Retrieve plugin settings via DidReceiveGlobalSettings.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#getglobalsettings)
+    #[serde(rename_all = "camelCase")]
+    GetGlobalSettings {
+        /// The uuid the property inspector registered with.
+        context: Context,
+    },
+    /// Store plugin settings.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#setglobalsettings)
+    #[serde(rename_all = "camelCase")]
+    SetGlobalSettings {
+        /// The uuid the property inspector registered with.
+        context: Context,
+        /// The settings to save.
+        payload: G,
+    },
+    /// Open a URL in the default browser.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#openurl)
+    #[serde(rename_all = "camelCase")]
+    OpenUrl {
+        /// The url to open.
+        payload: UrlPayload,
+    },
+    /// Write to the log.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#logmessage)
+    #[serde(rename_all = "camelCase")]
+    LogMessage {
+        /// The message to log.
+        payload: LogMessagePayload,
+    },
+    /// Send data to the plugin.
+    ///
+    /// [Official Documentation](https://docs.elgato.com/sdk/plugins/events-sent/#sendtoplugin)
+    #[serde(rename_all = "camelCase")]
+    SendToPlugin {
+        /// The uuid of the action.
+        action: ActionUuid,
+        /// The uuid the property inspector registered with.
+        context: Context,
+        /// The message to send.
+        payload: M,
+    },
+}
+
+impl<G, S, M> Message<G, S, M>
+where
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    M: de::DeserializeOwned,
+{
+    /// Parses a message from JSON text.
+    ///
+    /// This is the same as `serde_json::from_str`.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+impl<G, S, M> Message<G, S, M> {
+    /// The name of the event.
+    pub fn event(&self) -> &str {
+        match self {
+            Message::DidReceiveSettings { .. } => "didReceiveSettings",
+            Message::DidReceiveGlobalSettings { .. } => "didReceiveGlobalSettings",
+            Message::SendToPropertyInspector { .. } => "sendToPropertyInspector",
+            Message::Unknown { event, .. } => event,
+        }
+    }
+}
+
+impl<G, S, M> MessageOut<G, S, M> {
+    /// The name of the event.
+    pub fn event(&self) -> &'static str {
+        match self {
+            MessageOut::GetSettings { .. } => "getSettings",
+            MessageOut::SetSettings { .. } => "setSettings",
+            MessageOut::GetGlobalSettings { .. } => "getGlobalSettings",
+            MessageOut::SetGlobalSettings { .. } => "setGlobalSettings",
+            MessageOut::OpenUrl { .. } => "openUrl",
+            MessageOut::LogMessage { .. } => "logMessage",
+            MessageOut::SendToPlugin { .. } => "sendToPlugin",
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Message, MessageOut};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn property_inspector_messages() {
+        let message: Message<Value, Value, Value> = serde_json::from_value(json!({
+            "action": "com.example.plugin.action",
+            "event": "sendToPropertyInspector",
+            "context": "abc",
+            "payload": {"status": "ok"}
+        }))
+        .unwrap();
+        assert_eq!("sendToPropertyInspector", message.event());
+        match message {
+            Message::SendToPropertyInspector { payload, .. } => {
+                assert_eq!(json!({"status": "ok"}), payload)
+            }
+            _ => unreachable!(),
+        }
+
+        let message: Message<Value, Value, Value> =
+            Message::from_json(r#"{"event":"somethingNew","payload":{"answer":42}}"#).unwrap();
+        assert_eq!("somethingNew", message.event());
+        assert_eq!(
+            json!({"event": "somethingNew", "payload": {"answer": 42}}),
+            serde_json::to_value(&message).unwrap()
+        );
+        let parsed: Message<Value, Value, Value> =
+            serde_json::from_value(json!({"event": "somethingNew", "payload": {"answer": 42}}))
+                .unwrap();
+        assert_eq!(message, parsed);
+        let named = rmp_serde::to_vec_named(&message).unwrap();
+        assert_eq!(message, rmp_serde::from_slice(&named).unwrap());
+
+        for event in &[
+            "didReceiveSettings",
+            "didReceiveGlobalSettings",
+            "sendToPropertyInspector",
+        ] {
+            // known events that cannot be parsed are errors rather than unknown events
+            let invalid = json!({"event": event, "payload": 0});
+            assert!(Message::<Value, Value, Value>::from_json(&invalid.to_string()).is_err());
+            assert!(serde_json::from_value::<Message<Value, Value, Value>>(invalid).is_err());
+        }
+        let error = Message::<Value, Value, Value>::from_json(
+            r#"{"event":"didReceiveGlobalSettings","payload":{}}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().starts_with("missing field `settings`"),
+            "{}",
+            error
+        );
+        let message: Message<Value, Value, Value> = serde_json::from_value(json!({
+            "event": "didReceiveGlobalSettings",
+            "payload": {"settings": {"theme": "dark"}}
+        }))
+        .unwrap();
+        assert_eq!("didReceiveGlobalSettings", message.event());
+
+        let message: MessageOut<Value, Value, Value> = MessageOut::SendToPlugin {
+            action: "com.example.plugin.action".parse().unwrap(),
+            context: "abc".into(),
+            payload: json!({"refresh": true}),
+        };
+        assert_eq!(
+            json!({
+                "event": "sendToPlugin",
+                "action": "com.example.plugin.action",
+                "context": "abc",
+                "payload": {"refresh": true}
+            }),
+            serde_json::to_value(&message).unwrap()
+        );
+    }
+}
@@ -0,0 +1,133 @@
+use crate::registration::RegistrationInfo;
+use crate::{ActionUuid, Context, Coordinates, DeviceId};
+use failure::Fail;
+use serde::de;
+use serde_derive::{Deserialize, Serialize};
+
+/// The parameters passed to `connectElgatoStreamDeckSocket` when the property inspector is loaded.
+///
+/// [Official Documentation](https://docs.elgato.com/sdk/plugins/registration-procedure#connectelgatostreamdecksocket-pi)
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropertyInspectorParams<S> {
+    /// The web socket port listening for the property inspector.
+    pub port: u16,
+    /// The uuid to register with, which is also the context of the action being configured.
+    pub uuid: Context,
+    /// The event the property inspector should send to register with the Stream Deck software.
+    pub event: String,
+    /// Information about the Stream Deck software, plugin, and devices.
+    pub info: RegistrationInfo,
+    /// Information about the action being configured.
+    pub action_info: ActionInfo<S>,
+}
+
+/// An error that occurred while parsing the property inspector parameters.
+#[derive(Debug, Fail)]
+pub enum PropertyInspectorParamsError {
+    /// The registration environment info could not be parsed.
+    #[fail(display = "info could not be parsed")]
+    BadInfo(#[fail(cause)] serde_json::Error),
+    /// The action info could not be parsed.
+    #[fail(display = "action info could not be parsed")]
+    BadActionInfo(#[fail(cause)] serde_json::Error),
+}
+
+impl<S: de::DeserializeOwned> PropertyInspectorParams<S> {
+    /// Parses the parameters of `connectElgatoStreamDeckSocket`, where the info and action info are
+    /// JSON text.
+    pub fn new<U, E>(
+        port: u16,
+        uuid: U,
+        event: E,
+        info: &str,
+        action_info: &str,
+    ) -> Result<Self, PropertyInspectorParamsError>
+    where
+        U: Into<Context>,
+        E: Into<String>,
+    {
+        let info = serde_json::from_str(info).map_err(PropertyInspectorParamsError::BadInfo)?;
+        let action_info = serde_json::from_str(action_info)
+            .map_err(PropertyInspectorParamsError::BadActionInfo)?;
+        Ok(PropertyInspectorParams {
+            port,
+            uuid: uuid.into(),
+            event: event.into(),
+            info,
+            action_info,
+        })
+    }
+}
+
+/// Information about the action a property inspector is configuring.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionInfo<S> {
+    /// The uuid of the action.
+    pub action: ActionUuid,
+    /// The instance of the action.
+    pub context: Context,
+    /// The device where the action exists.
+    pub device: DeviceId,
+    /// The settings and location of the action.
+    pub payload: ActionInfoPayload<S>,
+}
+
+/// The settings and location of the action a property inspector is configuring.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActionInfoPayload<S> {
+    /// The stored settings for the action instance.
+    pub settings: S,
+    /// The location of the action, or None if it is part of a multi-action.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub coordinates: Option<Coordinates>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{PropertyInspectorParams, PropertyInspectorParamsError};
+    use crate::Coordinates;
+    use serde_json::Value;
+
+    #[test]
+    fn params() {
+        let info = r#"{
+            "application": {"language": "en", "platform": "mac", "version": "6.5.0"},
+            "plugin": {"uuid": "com.example.plugin", "version": "1.0"},
+            "devicePixelRatio": 2,
+            "devices": []
+        }"#;
+        let action_info = r#"{
+            "action": "com.example.plugin.action",
+            "context": "abc",
+            "device": "device",
+            "payload": {"settings": {"volume": 5}, "coordinates": {"column": 1, "row": 2}}
+        }"#;
+        let params: PropertyInspectorParams<Value> = PropertyInspectorParams::new(
+            28196,
+            "abc",
+            "registerPropertyInspector",
+            info,
+            action_info,
+        )
+        .expect("params");
+        assert_eq!("abc", params.action_info.context);
+        assert_eq!(5, params.action_info.payload.settings["volume"]);
+        assert_eq!(
+            Some(Coordinates::new(1, 2)),
+            params.action_info.payload.coordinates
+        );
+
+        assert!(matches!(
+            PropertyInspectorParams::<Value>::new(
+                28196,
+                "abc",
+                "registerPropertyInspector",
+                info,
+                "{}"
+            ),
+            Err(PropertyInspectorParamsError::BadActionInfo(_))
+        ));
+    }
+}
@@ -0,0 +1,255 @@
+//! A web socket client for property inspectors written in Rust and compiled to WebAssembly.
+//!
+//! The Stream Deck software loads the property inspector in a browser and calls its
+//! `connectElgatoStreamDeckSocket` function, which should be exported from the WebAssembly module
+//! and connect a [`PropertyInspectorSocket`](struct.PropertyInspectorSocket.html):
+//!
+//! ```ignore
+//! use serde_json::Value;
+//! use streamdeck_rs::property_inspector::socket::PropertyInspectorSocket;
+//! use streamdeck_rs::property_inspector::PropertyInspectorParams;
+//! use wasm_bindgen::prelude::*;
+//!
+//! #[wasm_bindgen(js_name = connectElgatoStreamDeckSocket)]
+//! pub fn connect(port: u16, uuid: String, event: String, info: String, action_info: String) {
+//!     let params: PropertyInspectorParams<Value> =
+//!         PropertyInspectorParams::new(port, uuid, event, &info, &action_info).unwrap();
+//!     let socket: PropertyInspectorSocket<Value, Value, Value, Value> =
+//!         PropertyInspectorSocket::connect(&params).unwrap();
+//!     // ...
+//! }
+//! ```
+
+use super::{Message, MessageOut, PropertyInspectorParams};
+use crate::socket::Registration;
+use failure::Fail;
+use futures::channel::mpsc;
+use futures::prelude::*;
+use serde::{de, ser};
+use std::cell::RefCell;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll};
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use web_sys::{MessageEvent, WebSocket};
+
+/// Provides encoding and decoding for messages sent to/from the Stream Deck software by a
+/// property inspector.
+///
+/// - `G` represents global settings persisted within the Stream Deck software.
+/// - `S` represents settings persisted within the Stream Deck software.
+/// - `MI` represents messages received from the plugin.
+/// - `MO` represents messages sent to the plugin.
+///
+/// Messages sent before the connection is open are queued and sent, after the registration, once
+/// it opens. The stream ends when the connection is closed.
+pub struct PropertyInspectorSocket<G, S, MI, MO> {
+    socket: WebSocket,
+    outgoing: Rc<RefCell<Outgoing>>,
+    receiver: mpsc::UnboundedReceiver<Result<String, PropertyInspectorSocketError>>,
+    _onopen: Closure<dyn FnMut()>,
+    _onmessage: Closure<dyn FnMut(MessageEvent)>,
+    _onerror: Closure<dyn FnMut()>,
+    _onclose: Closure<dyn FnMut()>,
+    _g: PhantomData<G>,
+    _s: PhantomData<S>,
+    _mi: PhantomData<MI>,
+    _mo: PhantomData<MO>,
+}
+
+// the messages are only stored as text
+impl<G, S, MI, MO> Unpin for PropertyInspectorSocket<G, S, MI, MO> {}
+
+/// Messages waiting for the connection to open.
+struct Outgoing {
+    open: bool,
+    queued: Vec<String>,
+}
+
+impl<G, S, MI, MO> PropertyInspectorSocket<G, S, MI, MO> {
+    /// Begins connecting to the Stream Deck software and registers the property inspector.
+    ///
+    /// `params` are the parameters passed to `connectElgatoStreamDeckSocket`.
+    pub fn connect<A>(params: &PropertyInspectorParams<A>) -> Result<Self, ConnectError> {
+        let registration = Registration::new(&params.event, &params.uuid);
+        let registration =
+            serde_json::to_string(&registration).map_err(ConnectError::BadRegistration)?;
+        let socket = WebSocket::new(&format!("ws://127.0.0.1:{}", params.port))
+            .map_err(|error| ConnectError::ConnectionError(describe(&error)))?;
+
+        let outgoing = Rc::new(RefCell::new(Outgoing {
+            open: false,
+            queued: vec![registration],
+        }));
+        let (sender, receiver) = mpsc::unbounded();
+
+        let onopen = {
+            let socket = socket.clone();
+            let outgoing = outgoing.clone();
+            let sender = sender.clone();
+            Closure::wrap(Box::new(move || {
+                let mut outgoing = outgoing.borrow_mut();
+                outgoing.open = true;
+                for message in outgoing.queued.drain(..) {
+                    if let Err(error) = socket.send_with_str(&message) {
+                        let _ = sender.unbounded_send(Err(
+                            PropertyInspectorSocketError::WebSocketError(describe(&error)),
+                        ));
+                    }
+                }
+            }) as Box<dyn FnMut()>)
+        };
+        let onmessage = {
+            let sender = sender.clone();
+            Closure::wrap(Box::new(move |event: MessageEvent| {
+                // the Stream Deck software only sends text
+                if let Some(text) = event.data().as_string() {
+                    let _ = sender.unbounded_send(Ok(text));
+                }
+            }) as Box<dyn FnMut(MessageEvent)>)
+        };
+        let onerror = {
+            let sender = sender.clone();
+            Closure::wrap(Box::new(move || {
+                let _ = sender.unbounded_send(Err(PropertyInspectorSocketError::WebSocketError(
+                    "the connection failed".to_string(),
+                )));
+            }) as Box<dyn FnMut()>)
+        };
+        let onclose = {
+            let outgoing = outgoing.clone();
+            Closure::wrap(Box::new(move || {
+                outgoing.borrow_mut().open = false;
+                sender.close_channel();
+            }) as Box<dyn FnMut()>)
+        };
+
+        socket.set_onopen(Some(onopen.as_ref().unchecked_ref()));
+        socket.set_onmessage(Some(onmessage.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(onerror.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(onclose.as_ref().unchecked_ref()));
+
+        Ok(PropertyInspectorSocket {
+            socket,
+            outgoing,
+            receiver,
+            _onopen: onopen,
+            _onmessage: onmessage,
+            _onerror: onerror,
+            _onclose: onclose,
+            _g: PhantomData,
+            _s: PhantomData,
+            _mi: PhantomData,
+            _mo: PhantomData,
+        })
+    }
+
+    fn send_text(&self, text: String) -> Result<(), PropertyInspectorSocketError> {
+        let mut outgoing = self.outgoing.borrow_mut();
+        if !outgoing.open {
+            if self.socket.ready_state() == WebSocket::CONNECTING {
+                outgoing.queued.push(text);
+                return Ok(());
+            }
+            return Err(PropertyInspectorSocketError::Closed);
+        }
+        self.socket
+            .send_with_str(&text)
+            .map_err(|error| PropertyInspectorSocketError::WebSocketError(describe(&error)))
+    }
+}
+
+impl<G, S, MI, MO> Drop for PropertyInspectorSocket<G, S, MI, MO> {
+    fn drop(&mut self) {
+        // the closures are about to be freed, so the browser must not call them anymore
+        self.socket.set_onopen(None);
+        self.socket.set_onmessage(None);
+        self.socket.set_onerror(None);
+        self.socket.set_onclose(None);
+        let _ = self.socket.close();
+    }
+}
+
+/// Gets a description of an error thrown by the browser.
+fn describe(error: &JsValue) -> String {
+    error.as_string().unwrap_or_else(|| format!("{:?}", error))
+}
+
+/// Represents an error that occurred reading or writing the web socket.
+#[derive(Debug, Fail)]
+pub enum PropertyInspectorSocketError {
+    /// The web socket reported an error.
+    #[fail(display = "WebSocket error: {}", _0)]
+    WebSocketError(String),
+    /// The message could not be encoded/decoded.
+    #[fail(display = "Bad message")]
+    BadMessage(#[fail(cause)] serde_json::Error),
+    /// The web socket has been closed.
+    #[fail(display = "WebSocket closed")]
+    Closed,
+}
+
+/// Represents an error that occurred while connecting to the Stream Deck software.
+#[derive(Debug, Fail)]
+pub enum ConnectError {
+    /// The web socket could not be created.
+    #[fail(display = "Websocket connection error: {}", _0)]
+    ConnectionError(String),
+    /// The registration information could not be encoded.
+    #[fail(display = "Bad registration")]
+    BadRegistration(#[fail(cause)] serde_json::Error),
+}
+
+impl<G, S, MI, MO> Stream for PropertyInspectorSocket<G, S, MI, MO>
+where
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+{
+    type Item = Result<Message<G, S, MI>, PropertyInspectorSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.receiver.poll_next_unpin(cx).map(|item| {
+            item.map(|result| {
+                result.and_then(|text| {
+                    Message::from_json(&text).map_err(PropertyInspectorSocketError::BadMessage)
+                })
+            })
+        })
+    }
+}
+
+impl<G, S, MI, MO> Sink<MessageOut<G, S, MO>> for PropertyInspectorSocket<G, S, MI, MO>
+where
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = PropertyInspectorSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        // the browser buffers messages without limit
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        let text =
+            serde_json::to_string(&item).map_err(PropertyInspectorSocketError::BadMessage)?;
+        self.send_text(text)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(
+            self.socket
+                .close()
+                .map_err(|error| PropertyInspectorSocketError::WebSocketError(describe(&error))),
+        )
+    }
+}
@@ -0,0 +1,461 @@
+//! Generating a starting point for a property inspector from the settings type.
+//!
+//! The generated page uses the [sdpi](https://github.com/elgatosf/streamdeck-propertyinspector)
+//! styles and contains an input for each field of the settings, along with the script needed to
+//! load and save the settings. It is intended to be customized by hand afterwards.
+//!
+//! Property inspectors that build their form at runtime can use the [`Field`](struct.Field.html)
+//! descriptors from [`form_fields`](fn.form_fields.html) instead, which serialize as JSON.
+
+use schemars::{schema_for, JsonSchema};
+use serde_derive::Serialize;
+use serde_json::{Map, Value};
+use std::fmt::Write;
+
+/// Generates the HTML of a property inspector for the settings type `S`.
+///
+/// # Examples
+///
+/// ```
+/// # use schemars::JsonSchema;
+/// # use serde_derive::{Deserialize, Serialize};
+/// # use streamdeck_rs::property_inspector::template::html_skeleton;
+/// #[derive(Deserialize, Serialize, JsonSchema)]
+/// struct Settings {
+///     /// The text to show on the key.
+///     text: String,
+///     enabled: bool,
+/// }
+///
+/// let html = html_skeleton::<Settings>();
+/// assert!(html.contains(r#"data-setting="text""#));
+/// ```
+pub fn html_skeleton<S: JsonSchema>() -> String {
+    let schema = serde_json::to_value(schema_for!(S)).expect("JSON schema");
+    html_skeleton_from_schema(&schema)
+}
+
+/// Generates the HTML of a property inspector for the settings described by a JSON schema.
+pub fn html_skeleton_from_schema(schema: &Value) -> String {
+    let fields = fields(schema);
+
+    let mut html = String::from(HEADER);
+    for field in &fields {
+        write_field(&mut html, field);
+    }
+    html.push_str(FOOTER);
+    html
+}
+
+/// Describes the form fields for the settings type `S`, one for each field of the settings.
+///
+/// # Examples
+///
+/// ```
+/// # use schemars::JsonSchema;
+/// # use serde_derive::{Deserialize, Serialize};
+/// # use streamdeck_rs::property_inspector::template::form_fields;
+/// #[derive(Deserialize, Serialize, JsonSchema)]
+/// struct Settings {
+///     enabled: bool,
+/// }
+///
+/// let json = serde_json::to_string(&form_fields::<Settings>()).unwrap();
+/// assert_eq!(r#"[{"name":"enabled","label":"Enabled","type":"checkbox"}]"#, json);
+/// ```
+pub fn form_fields<S: JsonSchema>() -> Vec<Field> {
+    let schema = serde_json::to_value(schema_for!(S)).expect("JSON schema");
+    fields(&schema)
+}
+
+/// Describes the form fields for the settings described by a JSON schema.
+pub fn form_fields_from_schema(schema: &Value) -> Vec<Field> {
+    fields(schema)
+}
+
+/// A form field for one field of the settings.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Field {
+    /// The name of the setting, as it is serialized.
+    pub name: String,
+    /// The label shown beside the field.
+    pub label: String,
+    /// The kind of input.
+    #[serde(flatten)]
+    pub kind: FieldKind,
+}
+
+/// The kind of input used for a setting.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum FieldKind {
+    /// A text field for a string.
+    Text,
+    /// A number field.
+    #[serde(rename_all = "camelCase")]
+    Number {
+        /// Whether the number must be an integer.
+        integer: bool,
+        /// The smallest allowed value.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        minimum: Option<f64>,
+        /// The largest allowed value.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        maximum: Option<f64>,
+    },
+    /// A checkbox for a boolean.
+    Checkbox,
+    /// A drop down list for an enum.
+    Select {
+        /// The allowed values.
+        options: Vec<String>,
+    },
+    /// A text area for any other value, edited as JSON.
+    Json,
+}
+
+fn fields(schema: &Value) -> Vec<Field> {
+    let definitions = schema.get("definitions").and_then(Value::as_object);
+    let properties = match resolve(schema, definitions)
+        .get("properties")
+        .and_then(Value::as_object)
+    {
+        Some(properties) => properties,
+        None => return Vec::new(),
+    };
+
+    properties
+        .iter()
+        .map(|(name, property)| {
+            let property = resolve(property, definitions);
+            let label = property
+                .get("title")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| humanize(name));
+            Field {
+                name: name.clone(),
+                label,
+                kind: kind(property),
+            }
+        })
+        .collect()
+}
+
+/// Follows references and skips the null alternative of optional values.
+fn resolve<'a>(schema: &'a Value, definitions: Option<&'a Map<String, Value>>) -> &'a Value {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.trim_start_matches("#/definitions/");
+        if let Some(definition) = definitions.and_then(|definitions| definitions.get(name)) {
+            return resolve(definition, definitions);
+        }
+    }
+    for key in &["allOf", "anyOf"] {
+        if let Some(alternatives) = schema.get(*key).and_then(Value::as_array) {
+            if let Some(alternative) = alternatives
+                .iter()
+                .find(|alternative| alternative.get("type") != Some(&Value::from("null")))
+            {
+                return resolve(alternative, definitions);
+            }
+        }
+    }
+    schema
+}
+
+fn kind(schema: &Value) -> FieldKind {
+    if let Some(options) = options(schema) {
+        return FieldKind::Select { options };
+    }
+
+    let types: Vec<&str> = match schema.get("type") {
+        Some(Value::String(t)) => vec![t.as_str()],
+        Some(Value::Array(types)) => types.iter().filter_map(Value::as_str).collect(),
+        _ => Vec::new(),
+    };
+    match types.into_iter().find(|t| *t != "null") {
+        Some("string") => FieldKind::Text,
+        Some(t @ "integer") | Some(t @ "number") => FieldKind::Number {
+            integer: t == "integer",
+            minimum: schema.get("minimum").and_then(Value::as_f64),
+            maximum: schema.get("maximum").and_then(Value::as_f64),
+        },
+        Some("boolean") => FieldKind::Checkbox,
+        _ => FieldKind::Json,
+    }
+}
+
+fn options(schema: &Value) -> Option<Vec<String>> {
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return Some(
+            values
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect(),
+        );
+    }
+    let alternatives = schema.get("oneOf").and_then(Value::as_array)?;
+    alternatives
+        .iter()
+        .map(|alternative| {
+            alternative
+                .get("const")
+                .or_else(|| alternative.get("enum").and_then(|values| values.get(0)))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// Converts a field name like `fontSize` or `font_size` into a label like `Font size`.
+fn humanize(name: &str) -> String {
+    let mut label = String::with_capacity(name.len() + 4);
+    for (i, c) in name.chars().enumerate() {
+        if i == 0 {
+            label.extend(c.to_uppercase());
+        } else if c == '_' || c == '-' {
+            label.push(' ');
+        } else if c.is_uppercase() {
+            label.push(' ');
+            label.extend(c.to_lowercase());
+        } else {
+            label.push(c);
+        }
+    }
+    label
+}
+
+fn escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn write_field(html: &mut String, field: &Field) {
+    let name = escape(&field.name);
+    let label = escape(&field.label);
+    match &field.kind {
+        FieldKind::Text => write!(
+            html,
+            r#"    <div class="sdpi-item">
+      <div class="sdpi-item-label">{label}</div>
+      <input class="sdpi-item-value" id="{name}" data-setting="{name}" type="text">
+    </div>
+"#,
+            name = name,
+            label = label
+        ),
+        FieldKind::Number {
+            integer,
+            minimum,
+            maximum,
+        } => {
+            let mut attributes = String::new();
+            if *integer {
+                attributes.push_str(r#" step="1""#);
+            }
+            if let Some(minimum) = minimum {
+                write!(attributes, r#" min="{}""#, minimum).unwrap();
+            }
+            if let Some(maximum) = maximum {
+                write!(attributes, r#" max="{}""#, maximum).unwrap();
+            }
+            write!(
+                html,
+                r#"    <div class="sdpi-item">
+      <div class="sdpi-item-label">{label}</div>
+      <input class="sdpi-item-value" id="{name}" data-setting="{name}" type="number"{attributes}>
+    </div>
+"#,
+                name = name,
+                label = label,
+                attributes = attributes
+            )
+        }
+        FieldKind::Checkbox => write!(
+            html,
+            r#"    <div class="sdpi-item" type="checkbox">
+      <div class="sdpi-item-label">{label}</div>
+      <div class="sdpi-item-value">
+        <input id="{name}" data-setting="{name}" type="checkbox">
+        <label for="{name}"><span></span></label>
+      </div>
+    </div>
+"#,
+            name = name,
+            label = label
+        ),
+        FieldKind::Select { options } => {
+            write!(
+                html,
+                r#"    <div class="sdpi-item" type="select">
+      <div class="sdpi-item-label">{label}</div>
+      <select class="sdpi-item-value select" id="{name}" data-setting="{name}">
+"#,
+                name = name,
+                label = label
+            )
+            .unwrap();
+            for option in options {
+                let option = escape(option);
+                writeln!(
+                    html,
+                    r#"        <option value="{option}">{option}</option>"#,
+                    option = option
+                )
+                .unwrap();
+            }
+            write!(html, "      </select>\n    </div>\n")
+        }
+        FieldKind::Json => write!(
+            html,
+            r#"    <div class="sdpi-item" type="textarea">
+      <div class="sdpi-item-label">{label}</div>
+      <textarea class="sdpi-item-value" id="{name}" data-setting="{name}" data-type="json"></textarea>
+    </div>
+"#,
+            name = name,
+            label = label
+        ),
+    }
+    .unwrap();
+}
+
+const HEADER: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <meta name="viewport" content="width=device-width,initial-scale=1,maximum-scale=1,minimum-scale=1,user-scalable=no,minimal-ui,viewport-fit=cover">
+  <link rel="stylesheet" href="sdpi.css">
+</head>
+<body>
+  <div class="sdpi-wrapper">
+"#;
+
+const FOOTER: &str = r#"  </div>
+  <script>
+    let websocket = null;
+    let context = null;
+    let settings = {};
+
+    function readInput(input) {
+      if (input.type === 'checkbox') {
+        return input.checked;
+      } else if (input.type === 'number') {
+        return input.value === '' ? null : Number(input.value);
+      } else if (input.dataset.type === 'json') {
+        try {
+          return JSON.parse(input.value);
+        } catch (e) {
+          return settings[input.dataset.setting];
+        }
+      }
+      return input.value;
+    }
+
+    function writeInput(input, value) {
+      if (input.type === 'checkbox') {
+        input.checked = !!value;
+      } else if (input.dataset.type === 'json') {
+        input.value = value === undefined ? '' : JSON.stringify(value);
+      } else {
+        input.value = value === undefined || value === null ? '' : value;
+      }
+    }
+
+    function showSettings() {
+      document.querySelectorAll('[data-setting]').forEach(input => writeInput(input, settings[input.dataset.setting]));
+    }
+
+    function saveSettings() {
+      document.querySelectorAll('[data-setting]').forEach(input => settings[input.dataset.setting] = readInput(input));
+      if (websocket && websocket.readyState === 1) {
+        websocket.send(JSON.stringify({ event: 'setSettings', context: context, payload: settings }));
+      }
+    }
+
+    document.querySelectorAll('[data-setting]').forEach(input => input.addEventListener('change', saveSettings));
+
+    function connectElgatoStreamDeckSocket(inPort, inPropertyInspectorUUID, inRegisterEvent, inInfo, inActionInfo) {
+      context = inPropertyInspectorUUID;
+      settings = JSON.parse(inActionInfo).payload.settings || {};
+      showSettings();
+
+      websocket = new WebSocket('ws://127.0.0.1:' + inPort);
+      websocket.onopen = () => websocket.send(JSON.stringify({ event: inRegisterEvent, uuid: inPropertyInspectorUUID }));
+      websocket.onmessage = message => {
+        const data = JSON.parse(message.data);
+        if (data.event === 'didReceiveSettings') {
+          settings = data.payload.settings || {};
+          showSettings();
+        }
+      };
+    }
+  </script>
+</body>
+</html>
+"#;
+
+#[cfg(test)]
+mod test {
+    use super::{form_fields, html_skeleton, humanize, FieldKind};
+    use schemars::JsonSchema;
+    use serde_derive::{Deserialize, Serialize};
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, Serialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    enum Mode {
+        Count,
+        Toggle,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Deserialize, Serialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Settings {
+        label: Option<String>,
+        step_size: u8,
+        enabled: bool,
+        mode: Mode,
+    }
+
+    #[test]
+    fn skeleton() {
+        let html = html_skeleton::<Settings>();
+        assert!(html.contains(r#"id="label" data-setting="label" type="text""#));
+        assert!(html.contains(r#"data-setting="stepSize" type="number" step="1" min="0""#));
+        assert!(html.contains(r#"data-setting="enabled" type="checkbox""#));
+        assert!(html.contains(r#"<option value="toggle">toggle</option>"#));
+        assert!(html.contains("<div class=\"sdpi-item-label\">Step size</div>"));
+        assert_eq!("Font size", humanize("font_size"));
+    }
+
+    #[test]
+    fn fields() {
+        let fields = form_fields::<Settings>();
+        let step_size = fields.iter().find(|f| f.name == "stepSize").unwrap();
+        assert_eq!(
+            FieldKind::Number {
+                integer: true,
+                minimum: Some(0.0),
+                maximum: None,
+            },
+            step_size.kind
+        );
+        let mode = serde_json::to_value(fields.iter().find(|f| f.name == "mode").unwrap()).unwrap();
+        assert_eq!("select", mode["type"]);
+        assert_eq!(serde_json::json!(["count", "toggle"]), mode["options"]);
+    }
+}
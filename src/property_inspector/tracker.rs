@@ -0,0 +1,190 @@
+use crate::{Context, Message, MessageOut};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// What to do with messages sent to a property inspector that is not open.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BufferPolicy {
+    /// Discard the messages.
+    Drop,
+    /// Keep up to this many of the most recent messages for each context.
+    Buffer(usize),
+    /// Keep only the most recent message for each context.
+    KeepLatest,
+}
+
+/// Tracks which property inspectors are open so that messages are not sent to closed ones.
+///
+/// The Stream Deck software discards [SendToPropertyInspector](../enum.MessageOut.html#variant.SendToPropertyInspector)
+/// messages for contexts whose property inspector is not open. The tracker holds on to those
+/// messages according to its [`BufferPolicy`](enum.BufferPolicy.html) and releases them when the
+/// property inspector appears.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_json::Value;
+/// # use streamdeck_rs::{Message, MessageOut};
+/// # use streamdeck_rs::property_inspector::{BufferPolicy, PropertyInspectorTracker};
+/// # fn example(received: Message<Value, Value, Value>, outgoing: MessageOut<Value, Value, Value>) {
+/// let mut tracker = PropertyInspectorTracker::new(BufferPolicy::KeepLatest);
+///
+/// // for each received message
+/// for message in tracker.observe(&received) {
+///     // send the released message
+/// }
+///
+/// // for each message to be sent
+/// if let Some(message) = tracker.send(outgoing) {
+///     // send the message
+/// }
+/// # }
+/// ```
+pub struct PropertyInspectorTracker<G, S, M> {
+    policy: BufferPolicy,
+    open: HashSet<Context>,
+    buffered: HashMap<Context, VecDeque<MessageOut<G, S, M>>>,
+}
+
+impl<G, S, M> PropertyInspectorTracker<G, S, M> {
+    /// Creates a tracker where no property inspectors are open.
+    pub fn new(policy: BufferPolicy) -> Self {
+        PropertyInspectorTracker {
+            policy,
+            open: HashSet::new(),
+            buffered: HashMap::new(),
+        }
+    }
+
+    /// Checks whether the property inspector for a context is open.
+    pub fn is_open(&self, context: &str) -> bool {
+        self.open.contains(context)
+    }
+
+    /// Updates the tracker with a message received from the Stream Deck software.
+    ///
+    /// Returns the messages that were being held for a property inspector that has appeared.
+    pub fn observe<GI, SI, MI>(
+        &mut self,
+        message: &Message<GI, SI, MI>,
+    ) -> Vec<MessageOut<G, S, M>> {
+        match message {
+            Message::PropertyInspectorDidAppear { context, .. } => {
+                self.open.insert(context.clone());
+                self.buffered
+                    .remove(context)
+                    .map(Vec::from)
+                    .unwrap_or_default()
+            }
+            Message::PropertyInspectorDidDisappear { context, .. } => {
+                self.open.remove(context);
+                Vec::new()
+            }
+            Message::WillDisappear { context, .. } => {
+                self.open.remove(context);
+                self.buffered.remove(context);
+                Vec::new()
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Filters a message to be sent to the Stream Deck software.
+    ///
+    /// Returns the message if it should be sent now. Messages for property inspectors that are not
+    /// open are held or discarded according to the policy.
+    pub fn send(&mut self, message: MessageOut<G, S, M>) -> Option<MessageOut<G, S, M>> {
+        let context = match &message {
+            MessageOut::SendToPropertyInspector { context, .. } if !self.open.contains(context) => {
+                context.clone()
+            }
+            _ => return Some(message),
+        };
+
+        let limit = match self.policy {
+            BufferPolicy::Drop => return None,
+            BufferPolicy::Buffer(limit) => limit,
+            BufferPolicy::KeepLatest => 1,
+        };
+        if limit == 0 {
+            return None;
+        }
+        let buffer = self.buffered.entry(context).or_default();
+        while buffer.len() >= limit {
+            buffer.pop_front();
+        }
+        buffer.push_back(message);
+        None
+    }
+
+    /// The number of messages being held for a context.
+    pub fn buffered(&self, context: &str) -> usize {
+        self.buffered.get(context).map_or(0, VecDeque::len)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{BufferPolicy, PropertyInspectorTracker};
+    use crate::{Message, MessageOut};
+    use serde_json::{json, Value};
+
+    fn received(event: &str) -> Message<Value, Value, Value> {
+        serde_json::from_value(json!({
+            "action": "com.example.plugin.action",
+            "event": event,
+            "context": "a",
+            "device": "device",
+        }))
+        .unwrap()
+    }
+
+    fn sent(number: u32) -> MessageOut<Value, Value, Value> {
+        MessageOut::send_to_property_inspector("a", json!(number))
+    }
+
+    fn payloads(messages: Vec<MessageOut<Value, Value, Value>>) -> Vec<Value> {
+        messages
+            .into_iter()
+            .map(|message| match message {
+                MessageOut::SendToPropertyInspector { payload, .. } => payload,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn buffer() {
+        let mut tracker = PropertyInspectorTracker::new(BufferPolicy::Buffer(2));
+        assert!(tracker.send(MessageOut::show_ok("a")).is_some());
+        for number in 0..3 {
+            assert!(tracker.send(sent(number)).is_none());
+        }
+        assert_eq!(2, tracker.buffered("a"));
+
+        let released = tracker.observe(&received("propertyInspectorDidAppear"));
+        assert_eq!(vec![json!(1), json!(2)], payloads(released));
+        assert!(tracker.is_open("a"));
+        assert!(tracker.send(sent(3)).is_some());
+
+        tracker.observe(&received("propertyInspectorDidDisappear"));
+        assert!(!tracker.is_open("a"));
+        assert!(tracker.send(sent(4)).is_none());
+        assert_eq!(1, tracker.buffered("a"));
+    }
+
+    #[test]
+    fn policies() {
+        let mut tracker = PropertyInspectorTracker::new(BufferPolicy::KeepLatest);
+        tracker.send(sent(0));
+        tracker.send(sent(1));
+        let released = tracker.observe(&received("propertyInspectorDidAppear"));
+        assert_eq!(vec![json!(1)], payloads(released));
+
+        let mut tracker = PropertyInspectorTracker::new(BufferPolicy::Drop);
+        assert!(tracker.send(sent(0)).is_none());
+        assert_eq!(0, tracker.buffered("a"));
+        assert!(tracker
+            .observe(&received("propertyInspectorDidAppear"))
+            .is_empty());
+    }
+}
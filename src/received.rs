@@ -0,0 +1,76 @@
+/// Defines a `Message` enum for the events received from the Stream Deck software, adding an
+/// `Unknown` variant for the events that are not understood.
+///
+/// A derived `Deserialize` which falls back to an untagged variant discards the error for an
+/// event that is understood but cannot be parsed, so the generated `Deserialize` reads the name
+/// of the event first and only falls back to `Unknown` for the names that are not listed.
+macro_rules! received_message {
+    (
+        $(#[$attr:meta])*
+        pub enum Message<G, S, M> {
+            $(
+                $(#[$variant_attr:meta])*
+                $variant:ident $({
+                    $($(#[$field_attr:meta])* $field:ident: $ty:ty,)*
+                })?,
+            )*
+        }
+    ) => {
+        $(#[$attr])*
+        pub enum Message<G, S, M> {
+            $(
+                $(#[$variant_attr])*
+                $variant $({
+                    $($(#[$field_attr])* $field: $ty,)*
+                })?,
+            )*
+            /// An event from an unsupported version of the Stream Deck software.
+            ///
+            /// This occurs when the Stream Deck software sends an event that is not
+            /// understood. Usually this will be because the Stream Deck software is
+            /// newer than the plugin, and it should be safe to ignore these.
+            #[serde(untagged)]
+            Unknown {
+                /// The name of the event.
+                event: String,
+                /// The payload of the event, or null if there was none.
+                payload: serde_json::Value,
+            },
+        }
+
+        impl<'de, G, S, M> serde::de::Deserialize<'de> for Message<G, S, M>
+        where
+            G: serde::de::Deserialize<'de>,
+            S: serde::de::Deserialize<'de>,
+            M: serde::de::Deserialize<'de>,
+        {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: serde::de::Deserializer<'de>,
+            {
+                #[derive(serde_derive::Deserialize)]
+                #[serde(remote = "Message", tag = "event", rename_all = "camelCase")]
+                enum Known<G, S, M> {
+                    $(
+                        $(#[$variant_attr])*
+                        $variant $({
+                            $($(#[$field_attr])* $field: $ty,)*
+                        })?,
+                    )*
+                }
+
+                #[derive(serde_derive::Deserialize)]
+                #[serde(rename_all = "camelCase")]
+                enum Event {
+                    $($variant,)*
+                }
+
+                let mut message = serde_json::Value::deserialize(deserializer)?;
+                match $crate::unknown_event::<Event>(&mut message) {
+                    Some((event, payload)) => Ok(Message::Unknown { event, payload }),
+                    None => Known::deserialize(message).map_err(serde::de::Error::custom),
+                }
+            }
+        }
+    };
+}
@@ -1,31 +1,25 @@
-use super::{Color, DeviceSize, DeviceType};
+use super::version::{Feature, ParseVersionError, Version};
+use super::{Color, Device, SDK_VERSION};
 use failure::Fail;
-use serde::de;
+use serde::{de, ser};
 use serde_derive::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ffi::OsString;
 use std::fmt;
 use std::str::FromStr;
 
 /// Information about a connected device.
 ///
-/// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#info-parameter)
-#[derive(Deserialize)]
-pub struct RegistrationInfoDevice {
-    /// The ID of the specific device.
-    pub id: String,
-    /// The user-specified name of the device.
-    ///
-    /// Added in Stream Deck software version 4.3.
-    pub name: Option<String>,
-    /// The size of the device.
-    pub size: DeviceSize,
-    /// The type of the device.
-    #[serde(rename = "type")]
-    pub _type: Option<DeviceType>,
-}
+/// This is now the same type as [`Device`](../struct.Device.html), whose `id` is optional because
+/// it is not known for devices that connect later.
+#[deprecated(note = "use Device instead")]
+pub type RegistrationInfoDevice = Device;
 
 /// The language the Stream Deck software is running in.
 ///
 /// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#Info-parameter)
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Language {
     English,
     French,
@@ -34,10 +28,60 @@ pub enum Language {
     Japanese,
     /// Unlike the other lanuages which are not specifically localized to a country, Chinese is specifically zh-CN.
     ChineseChina,
-    /// A language that was not documented in the 4.0.0 SDK.
+    /// Traditional Chinese, as used in Taiwan (zh-TW).
+    ChineseTaiwan,
+    Italian,
+    Korean,
+    Portuguese,
+    /// A language not known to this crate.
     Unknown(String),
 }
 
+impl Language {
+    /// Gets the language for a tag sent by the Stream Deck software.
+    fn from_tag(tag: &str) -> Self {
+        match tag {
+            "en" => Language::English,
+            "fr" => Language::French,
+            "de" => Language::German,
+            "es" => Language::Spanish,
+            "ja" => Language::Japanese,
+            "zh_cn" | "zh_CN" => Language::ChineseChina,
+            "zh_tw" | "zh_TW" => Language::ChineseTaiwan,
+            "it" => Language::Italian,
+            "ko" => Language::Korean,
+            "pt" | "pt_BR" => Language::Portuguese,
+            tag => Language::Unknown(tag.to_string()),
+        }
+    }
+
+    /// Gets the tag the Stream Deck software sends for the language.
+    fn tag(&self) -> &str {
+        match self {
+            Language::English => "en",
+            Language::French => "fr",
+            Language::German => "de",
+            Language::Spanish => "es",
+            Language::Japanese => "ja",
+            Language::ChineseChina => "zh_CN",
+            Language::ChineseTaiwan => "zh_TW",
+            Language::Italian => "it",
+            Language::Korean => "ko",
+            Language::Portuguese => "pt_BR",
+            Language::Unknown(value) => value,
+        }
+    }
+}
+
+impl ser::Serialize for Language {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(self.tag())
+    }
+}
+
 impl<'de> de::Deserialize<'de> for Language {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -56,15 +100,7 @@ impl<'de> de::Deserialize<'de> for Language {
             where
                 E: de::Error,
             {
-                Ok(match value {
-                    "en" => Language::English,
-                    "fr" => Language::French,
-                    "de" => Language::German,
-                    "es" => Language::Spanish,
-                    "ja" => Language::Japanese,
-                    "zh_cn" => Language::ChineseChina,
-                    value => Language::Unknown(value.to_string()),
-                })
+                Ok(Language::from_tag(value))
             }
         }
 
@@ -73,6 +109,8 @@ impl<'de> de::Deserialize<'de> for Language {
 }
 
 /// The platform on which the Stream Deck software is running.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
 pub enum Platform {
     /// Mac OS X
     Mac,
@@ -82,7 +120,20 @@ pub enum Platform {
     Unknown(String),
 }
 
-impl<'de: 'a, 'a> de::Deserialize<'de> for Platform {
+impl ser::Serialize for Platform {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: ser::Serializer,
+    {
+        serializer.serialize_str(match self {
+            Platform::Mac => "mac",
+            Platform::Windows => "windows",
+            Platform::Unknown(value) => value,
+        })
+    }
+}
+
+impl<'de> de::Deserialize<'de> for Platform {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: de::Deserializer<'de>,
@@ -115,27 +166,93 @@ impl<'de: 'a, 'a> de::Deserialize<'de> for Platform {
 /// Information about the Stream Deck software.
 ///
 /// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#info-parameter)
-#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
 pub struct RegistrationInfoApplication {
     pub language: Language,
     pub platform: Platform,
+    /// The version of the operating system, such as `14.2.1` on macOS or `10.0.22631` on Windows.
+    ///
+    /// Only sent by newer versions of the Stream Deck software.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub platform_version: Option<String>,
     pub version: String,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
+}
+
+impl RegistrationInfoApplication {
+    /// Parses the version of the Stream Deck software.
+    pub fn parsed_version(&self) -> Result<Version, ParseVersionError> {
+        self.version.parse()
+    }
+
+    /// Checks whether the Stream Deck software supports a feature.
+    ///
+    /// Returns `false` if the version cannot be parsed.
+    pub fn supports(&self, feature: Feature) -> bool {
+        self.parsed_version()
+            .is_ok_and(|version| version.supports(feature))
+    }
+
+    /// Compares the version of the Stream Deck software with the version implemented by this crate.
+    ///
+    /// Returns `None` if the Stream Deck software is not newer than [`SDK_VERSION`](../constant.SDK_VERSION.html).
+    /// Otherwise, events and fields introduced by the newer version may not be understood, and
+    /// unrecognized events will be received as [`Message::Unknown`](../enum.Message.html#variant.Unknown).
+    ///
+    /// Only the major and minor versions are compared, and `None` is returned if the version
+    /// cannot be parsed.
+    pub fn version_mismatch(&self) -> Option<VersionMismatch> {
+        let host = self.parsed_version().ok()?;
+        if Version::new(host.major, host.minor) > Version::SDK {
+            Some(VersionMismatch {
+                host_version: self.version.clone(),
+                sdk_version: SDK_VERSION,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// The Stream Deck software is newer than the version implemented by this crate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionMismatch {
+    /// The version of the Stream Deck software.
+    pub host_version: String,
+    /// The version of the Stream Deck software implemented by this crate.
+    pub sdk_version: &'static str,
+}
+
+impl fmt::Display for VersionMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Stream Deck software version {} is newer than the supported version {}",
+            self.host_version, self.sdk_version
+        )
+    }
 }
 
 /// Information about the plugin
 ///
 /// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#info-parameter)
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RegistrationInfoPlugin {
     /// Version of the plugin as per the manifest
     pub version: String,
     /// Unique identifier of the plugin
-    pub uuid: String,
+    ///
+    /// Not sent by older versions of the Stream Deck software.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uuid: Option<String>,
 }
 
 /// The user's preferred colors
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct UserColors {
     button_pressed_background_color: Option<Color>,
@@ -146,34 +263,67 @@ pub struct UserColors {
     mouse_down_color: Option<Color>,
 }
 
+fn default_device_pixel_ratio() -> f64 {
+    1.0
+}
+
 /// Information about the environment the plugin is being loaded into.
 ///
+/// `platformVersion` is left out when it is not known, so store this in MessagePack with the
+/// named encoding (`rmp_serde::to_vec_named`) rather than the compact one.
+///
 /// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#info-parameter)
-#[derive(Deserialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RegistrationInfo {
     pub application: RegistrationInfoApplication,
     pub plugin: RegistrationInfoPlugin,
-    pub device_pixel_ratio: u8,
-    pub devices: Vec<RegistrationInfoDevice>,
+    /// The ratio of physical pixels to logical pixels of the main display, such as `2` on a
+    /// high DPI display.
+    ///
+    /// Not sent by older versions of the Stream Deck software, in which case this is `1`.
+    #[serde(default = "default_device_pixel_ratio")]
+    pub device_pixel_ratio: f64,
+    pub devices: Vec<Device>,
+    /// The user's preferred colors.
+    ///
+    /// Not sent by older versions of the Stream Deck software, in which case none of the colors
+    /// are set.
+    #[serde(default)]
     pub colors: UserColors,
+    /// Fields added by newer versions of the Stream Deck software.
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, Value>,
 }
 
 /// Registration parameters provided to the plugin on startup.
 ///
 /// [Official Documentation](https://developer.elgato.com/documentation/stream-deck/sdk/registration-procedure/#compiled-plugin-registration)
-#[derive(Deserialize)]
+///
+/// With the `clap` feature, this can be flattened into a clap parser with `#[command(flatten)]`.
+/// See [`clap_args`](#method.clap_args).
+#[derive(Clone, Debug, Deserialize)]
+#[cfg_attr(feature = "clap", derive(clap::Args))]
 pub struct RegistrationParams {
     /// The web socket port listening for the plugin.
+    #[cfg_attr(feature = "clap", arg(long = "port"))]
     pub port: u16,
     /// The uuid of the plugin.
+    #[cfg_attr(feature = "clap", arg(long = "pluginUUID"))]
     pub uuid: String,
     /// The event the plugin should send to register with the Stream Deck software.
+    #[cfg_attr(feature = "clap", arg(long = "registerEvent"))]
     pub event: String,
     /// Information about the environment the plugin is being loaded into.
+    #[cfg_attr(feature = "clap", arg(long = "info", value_parser = parse_info))]
     pub info: RegistrationInfo,
 }
 
+#[cfg(feature = "clap")]
+fn parse_info(info: &str) -> Result<RegistrationInfo, serde_json::Error> {
+    serde_json::from_str(info)
+}
+
 /// An error that occurred while collecting the registration parameters.
 #[derive(Debug, Fail)]
 pub enum RegistrationParamsError {
@@ -195,6 +345,9 @@ pub enum RegistrationParamsError {
     /// The registration environment info could not be parsed.
     #[fail(display = "info could not be parsed")]
     BadInfo(#[fail(cause)] serde_json::Error),
+    /// The value of an argument, such as `-info`, is not valid Unicode.
+    #[fail(display = "{} is not valid Unicode", _0)]
+    InvalidEncoding(&'static str),
 }
 
 impl RegistrationParams {
@@ -202,42 +355,398 @@ impl RegistrationParams {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use std::env;
     /// RegistrationParams::from_args(env::args())
+    /// # ;
     /// ```
     pub fn from_args<I: IntoIterator<Item = String>>(
         args: I,
     ) -> Result<RegistrationParams, RegistrationParamsError> {
+        Self::from_raw_args(RawArgs::scan(args))
+    }
+
+    /// Pull the registration parameters out of a command line which may not be valid Unicode.
+    ///
+    /// Only the values of the registration arguments need to be valid Unicode. Other arguments,
+    /// such as paths given to the plugin, are ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use std::env;
+    /// RegistrationParams::from_args_os(env::args_os())
+    /// # ;
+    /// ```
+    pub fn from_args_os<I: IntoIterator<Item = OsString>>(
+        args: I,
+    ) -> Result<RegistrationParams, RegistrationParamsError> {
+        Self::from_raw_args(RawArgs::scan_os(args)?)
+    }
+
+    fn from_raw_args(args: RawArgs) -> Result<RegistrationParams, RegistrationParamsError> {
+        let (port, uuid, event) = args.required()?;
+        let info = args.info.ok_or(RegistrationParamsError::NoInfo)?;
+        let info = serde_json::from_str(&info).map_err(RegistrationParamsError::BadInfo)?;
+
+        Ok(RegistrationParams {
+            port,
+            uuid,
+            event,
+            info,
+        })
+    }
+
+    /// Creates the command line arguments that pass these registration parameters to a plugin,
+    /// in the same form as the Stream Deck software.
+    ///
+    /// This is the inverse of [`from_args`](#method.from_args), and does not include the name of
+    /// the program.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use std::process::Command;
+    /// # fn example(params: &RegistrationParams) -> std::io::Result<()> {
+    /// Command::new("plugin").args(params.to_args()).spawn()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_args(&self) -> Vec<String> {
+        // The registration info only holds values which can be represented as JSON.
+        let info = serde_json::to_string(&self.info).expect("registration info is valid JSON");
+        vec![
+            "-port".to_string(),
+            self.port.to_string(),
+            "-pluginUUID".to_string(),
+            self.uuid.clone(),
+            "-registerEvent".to_string(),
+            self.event.clone(),
+            "-info".to_string(),
+            info,
+        ]
+    }
+
+    /// Pull the registration parameters out of a command line, even if the registration info
+    /// cannot be parsed.
+    ///
+    /// This allows a plugin to connect to the Stream Deck software and report the problem when
+    /// the registration info is not understood, such as when a newer version of the Stream Deck
+    /// software changes its format.
+    pub fn from_args_lenient<I: IntoIterator<Item = String>>(
+        args: I,
+    ) -> Result<LenientRegistrationParams, RegistrationParamsError> {
+        let args = RawArgs::scan(args);
+        let (port, uuid, event) = args.required()?;
+        let info = args.info.ok_or(RegistrationParamsError::NoInfo)?;
+        let info = match serde_json::from_str::<Value>(&info) {
+            Ok(raw) => {
+                serde_json::from_value(raw.clone()).map_err(|error| InvalidInfo { raw, error })
+            }
+            Err(error) => Err(InvalidInfo {
+                raw: Value::String(info),
+                error,
+            }),
+        };
+
+        Ok(LenientRegistrationParams {
+            port,
+            uuid,
+            event,
+            info,
+        })
+    }
+}
+
+/// Registration parameters where the registration info may not have been understood.
+///
+/// See [`RegistrationParams::from_args_lenient`](struct.RegistrationParams.html#method.from_args_lenient).
+pub struct LenientRegistrationParams {
+    /// The web socket port listening for the plugin.
+    pub port: u16,
+    /// The uuid of the plugin.
+    pub uuid: String,
+    /// The event the plugin should send to register with the Stream Deck software.
+    pub event: String,
+    /// Information about the environment the plugin is being loaded into, if it could be parsed.
+    pub info: Result<RegistrationInfo, InvalidInfo>,
+}
+
+#[cfg(feature = "clap")]
+impl RegistrationParams {
+    /// Prepares a command line for a clap parser which includes the registration parameters.
+    ///
+    /// The Stream Deck software passes the registration arguments with a single dash, like
+    /// `-port`, which clap does not accept for long arguments, so they are rewritten to `--port`.
+    /// Other arguments are passed through unchanged.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// use clap::Parser;
+    ///
+    /// #[derive(Parser)]
+    /// struct Cli {
+    ///     #[command(flatten)]
+    ///     registration: RegistrationParams,
+    ///     #[arg(long)]
+    ///     verbose: bool,
+    /// }
+    ///
+    /// let info = r#"{"application":{"language":"en","platform":"mac","version":"6.0"},"plugin":{"uuid":"com.example.plugin","version":"1.0"},"devicePixelRatio":2,"devices":[]}"#;
+    /// let args = ["plugin", "--verbose", "-port", "28196", "-pluginUUID", "ABCDEF", "-registerEvent", "registerPlugin", "-info", info];
+    /// let cli = Cli::try_parse_from(RegistrationParams::clap_args(args)).unwrap();
+    /// assert!(cli.verbose);
+    /// assert_eq!(28196, cli.registration.port);
+    /// ```
+    pub fn clap_args<I>(args: I) -> impl Iterator<Item = OsString>
+    where
+        I: IntoIterator,
+        I::Item: Into<OsString>,
+    {
+        args.into_iter().map(|arg| {
+            let arg = arg.into();
+            match arg.to_str() {
+                Some("-port") | Some("-pluginUUID") | Some("-registerEvent") | Some("-info") => {
+                    let mut long = OsString::from("-");
+                    long.push(&arg);
+                    long
+                }
+                _ => arg,
+            }
+        })
+    }
+}
+
+impl LenientRegistrationParams {
+    /// Converts into [`RegistrationParams`](struct.RegistrationParams.html) if the registration
+    /// info was understood.
+    pub fn into_strict(self) -> Result<RegistrationParams, InvalidInfo> {
+        Ok(RegistrationParams {
+            port: self.port,
+            uuid: self.uuid,
+            event: self.event,
+            info: self.info?,
+        })
+    }
+}
+
+/// Registration info that could not be parsed.
+#[derive(Debug)]
+pub struct InvalidInfo {
+    /// The registration info.
+    ///
+    /// If the registration info is not valid JSON, this is a string containing the original text.
+    pub raw: Value,
+    /// The reason the registration info could not be parsed.
+    pub error: serde_json::Error,
+}
+
+struct RawArgs {
+    port: Option<String>,
+    uuid: Option<String>,
+    event: Option<String>,
+    info: Option<String>,
+}
+
+impl RawArgs {
+    fn scan<I: IntoIterator<Item = String>>(args: I) -> Self {
         let mut iter = args.into_iter();
-        let mut port = None;
-        let mut uuid = None;
-        let mut event = None;
-        let mut info = None;
+        let mut raw = RawArgs {
+            port: None,
+            uuid: None,
+            event: None,
+            info: None,
+        };
 
         loop {
             match iter.next().as_deref() {
-                Some("-port") => port = iter.next().map(|a| u16::from_str(&a)),
-                Some("-pluginUUID") => uuid = iter.next(),
-                Some("-registerEvent") => event = iter.next(),
-                Some("-info") => info = iter.next().map(|a| serde_json::from_str(&a)),
+                Some("-port") => raw.port = iter.next(),
+                Some("-pluginUUID") => raw.uuid = iter.next(),
+                Some("-registerEvent") => raw.event = iter.next(),
+                Some("-info") => raw.info = iter.next(),
                 Some(_) => {}
                 None => break,
             }
         }
-        let port = port
-            .ok_or(RegistrationParamsError::NoPort)?
-            .map_err(RegistrationParamsError::BadPort)?;
-        let uuid = uuid.ok_or(RegistrationParamsError::NoUuid)?;
-        let event = event.ok_or(RegistrationParamsError::NoEvent)?;
-        let info = info
-            .ok_or(RegistrationParamsError::NoInfo)?
-            .map_err(RegistrationParamsError::BadInfo)?;
+        raw
+    }
 
-        Ok(RegistrationParams {
-            port,
-            uuid,
-            event,
+    fn scan_os<I: IntoIterator<Item = OsString>>(args: I) -> Result<Self, RegistrationParamsError> {
+        let mut iter = args.into_iter();
+        let mut raw = RawArgs {
+            port: None,
+            uuid: None,
+            event: None,
+            info: None,
+        };
+
+        while let Some(arg) = iter.next() {
+            let (name, slot) = match arg.to_str() {
+                Some("-port") => ("-port", &mut raw.port),
+                Some("-pluginUUID") => ("-pluginUUID", &mut raw.uuid),
+                Some("-registerEvent") => ("-registerEvent", &mut raw.event),
+                Some("-info") => ("-info", &mut raw.info),
+                _ => continue,
+            };
+            *slot = iter
+                .next()
+                .map(OsString::into_string)
+                .transpose()
+                .map_err(|_| RegistrationParamsError::InvalidEncoding(name))?;
+        }
+        Ok(raw)
+    }
+
+    fn required(&self) -> Result<(u16, String, String), RegistrationParamsError> {
+        let port = self.port.as_ref().ok_or(RegistrationParamsError::NoPort)?;
+        let port = u16::from_str(port).map_err(RegistrationParamsError::BadPort)?;
+        let uuid = self.uuid.clone().ok_or(RegistrationParamsError::NoUuid)?;
+        let event = self.event.clone().ok_or(RegistrationParamsError::NoEvent)?;
+        Ok((port, uuid, event))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        Language, Platform, RegistrationInfo, RegistrationInfoApplication, RegistrationParams,
+        UserColors,
+    };
+    use serde_json::Value;
+
+    #[test]
+    fn language() {
+        let languages: Vec<Language> =
+            serde_json::from_str(r#"["ko", "it", "pt", "zh_TW", "zh_CN", "xx"]"#).unwrap();
+        assert_eq!(
+            vec![
+                Language::Korean,
+                Language::Italian,
+                Language::Portuguese,
+                Language::ChineseTaiwan,
+                Language::ChineseChina,
+                Language::Unknown("xx".to_string()),
+            ],
+            languages
+        );
+    }
+
+    #[test]
+    fn language_tag() {
+        for tag in &["pt_BR", "zh_TW", "zh_CN", "en", "xx"] {
+            let json = format!(
+                r#"{{"language":"{}","platform":"mac","version":"6.0"}}"#,
+                tag
+            );
+            let application: RegistrationInfoApplication = serde_json::from_str(&json).unwrap();
+            assert_eq!(json, serde_json::to_string(&application).unwrap());
+        }
+    }
+
+    #[test]
+    fn version_mismatch() {
+        let application = |version: &str| RegistrationInfoApplication {
+            language: Language::English,
+            platform: Platform::Windows,
+            platform_version: None,
+            version: version.to_string(),
+            extra: Default::default(),
+        };
+
+        assert!(application("4.3.2.11299").version_mismatch().is_none());
+        assert!(application("6.0.0.17801").version_mismatch().is_none());
+        assert!(application("6.5.1.19900").version_mismatch().is_none());
+        assert!(application("6").version_mismatch().is_none());
+        assert!(application("6.6.0").version_mismatch().is_some());
+        assert!(application("7.0").version_mismatch().is_some());
+        assert!(application("6.6-beta").version_mismatch().is_some());
+        assert!(application("unknown").version_mismatch().is_none());
+    }
+
+    #[test]
+    fn older_info() {
+        let info: RegistrationInfo = serde_json::from_str(
+            r#"{
+                "application": {"language": "en", "platform": "windows", "version": "4.1.2.10234", "beta": true},
+                "plugin": {"version": "1.0"},
+                "devices": []
+            }"#,
+        )
+        .expect("registration info");
+        assert_eq!(UserColors::default(), info.colors);
+        assert_eq!(None, info.plugin.uuid);
+        assert_eq!(None, info.plugin_directory());
+        assert_eq!(1.0, info.device_pixel_ratio);
+        assert_eq!(Some(&Value::Bool(true)), info.application.extra.get("beta"));
+
+        let params = RegistrationParams {
+            port: 28196,
+            uuid: "ABCDEF".to_string(),
+            event: "registerPlugin".to_string(),
             info,
-        })
+        };
+        let args = params.to_args();
+        assert_eq!("-port", args[0]);
+        let parsed = RegistrationParams::from_args(args).expect("registration params");
+        assert_eq!(28196, parsed.port);
+        assert_eq!(params.info, parsed.info);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn os_args() {
+        use super::RegistrationParamsError;
+        use std::ffi::OsString;
+        use std::os::unix::ffi::OsStringExt;
+
+        let args = |uuid: OsString| {
+            vec![
+                OsString::from("plugin"),
+                OsString::from_vec(vec![0xff]),
+                OsString::from("-port"),
+                OsString::from("28196"),
+                OsString::from("-pluginUUID"),
+                uuid,
+                OsString::from("-registerEvent"),
+                OsString::from("registerPlugin"),
+                OsString::from("-info"),
+                OsString::from("{}"),
+            ]
+        };
+        assert!(matches!(
+            RegistrationParams::from_args_os(args(OsString::from("ABCDEF"))),
+            Err(RegistrationParamsError::BadInfo(_))
+        ));
+        assert!(matches!(
+            RegistrationParams::from_args_os(args(OsString::from_vec(vec![0xff]))),
+            Err(RegistrationParamsError::InvalidEncoding("-pluginUUID"))
+        ));
+    }
+
+    #[test]
+    fn lenient_args() {
+        let args = [
+            "plugin",
+            "-port",
+            "28196",
+            "-pluginUUID",
+            "ABCDEF",
+            "-registerEvent",
+            "registerPlugin",
+            "-info",
+            r#"{"application":{}}"#,
+        ];
+        let params = RegistrationParams::from_args_lenient(args.iter().map(|arg| arg.to_string()))
+            .expect("port, uuid, and event");
+        assert_eq!(28196, params.port);
+        assert_eq!("ABCDEF", params.uuid);
+        assert_eq!("registerPlugin", params.event);
+        let invalid = params.info.expect_err("invalid info");
+        assert!(invalid.raw["application"].is_object());
     }
 }
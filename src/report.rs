@@ -0,0 +1,185 @@
+//! Reporting errors to the user.
+
+use crate::{LogMessagePayload, MessageOut, Target, TitlePayload};
+use failure::AsFail;
+use futures::channel::mpsc;
+use std::fmt::Write;
+use std::future::Future;
+use std::time::Duration;
+
+/// Reports errors that occur while handling an action.
+///
+/// Reporting an error shows an alert on the key and writes the error, including its causes, to the
+/// Stream Deck log. Optionally, the title of the key can be replaced with a short message until a
+/// timeout passes.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use futures::channel::mpsc;
+/// # use serde_json::Value;
+/// # use std::time::Duration;
+/// # use streamdeck_rs::MessageOut;
+/// # use streamdeck_rs::report::ErrorReporter;
+/// # async fn example(sender: mpsc::UnboundedSender<MessageOut<Value, Value, Value>>, context: &str) {
+/// let reporter = ErrorReporter::new(sender).with_title("Error", Duration::from_secs(3));
+///
+/// if let Err(error) = "x".parse::<u32>() {
+///     reporter.report(context, &error).await;
+/// }
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ErrorReporter<G, S, M> {
+    sender: mpsc::UnboundedSender<MessageOut<G, S, M>>,
+    title: Option<(String, Duration)>,
+}
+
+impl<G, S, M> ErrorReporter<G, S, M> {
+    /// Creates a reporter that sends messages through a channel.
+    pub fn new(sender: mpsc::UnboundedSender<MessageOut<G, S, M>>) -> Self {
+        ErrorReporter {
+            sender,
+            title: None,
+        }
+    }
+
+    /// Also shows a title on the key, which is reverted to the title chosen by the user after the
+    /// timeout.
+    pub fn with_title<T: Into<String>>(mut self, title: T, timeout: Duration) -> Self {
+        self.title = Some((title.into(), timeout));
+        self
+    }
+
+    /// Reports an error for an instance of an action.
+    ///
+    /// The alert and log message are sent immediately. If a title was configured, it is also set
+    /// immediately, and the returned future completes after it has been reverted. The future does
+    /// not borrow the reporter or its arguments, so it can be spawned rather than awaited to avoid
+    /// waiting for the timeout.
+    pub fn report<E: AsFail + ?Sized>(&self, context: &str, error: &E) -> impl Future<Output = ()>
+    where
+        G: 'static,
+        S: 'static,
+        M: 'static,
+    {
+        let _ = self.sender.unbounded_send(MessageOut::LogMessage {
+            payload: LogMessagePayload {
+                message: describe(context, error),
+            },
+        });
+        let _ = self.sender.unbounded_send(MessageOut::ShowAlert {
+            context: context.into(),
+        });
+
+        let revert = self.title.as_ref().map(|(title, timeout)| {
+            self.set_title(context, Some(title.clone()));
+            (self.sender.clone(), context.to_string(), *timeout)
+        });
+        async move {
+            if let Some((sender, context, timeout)) = revert {
+                tokio::time::sleep(timeout).await;
+                set_title(&sender, &context, None);
+            }
+        }
+    }
+
+    fn set_title(&self, context: &str, title: Option<String>) {
+        set_title(&self.sender, context, title);
+    }
+}
+
+/// Sets the title of an instance of an action, or reverts it to the title chosen by the user.
+fn set_title<G, S, M>(
+    sender: &mpsc::UnboundedSender<MessageOut<G, S, M>>,
+    context: &str,
+    title: Option<String>,
+) {
+    let _ = sender.unbounded_send(MessageOut::SetTitle {
+        context: context.into(),
+        payload: TitlePayload {
+            title,
+            target: Target::Both,
+            state: None,
+        },
+    });
+}
+
+/// Formats an error and its causes on a single line.
+fn describe<E: AsFail + ?Sized>(context: &str, error: &E) -> String {
+    let mut message = format!("error in {}", context);
+    for (i, fail) in error.as_fail().iter_chain().enumerate() {
+        let _ = write!(
+            message,
+            "{}{}",
+            if i == 0 { ": " } else { ": caused by: " },
+            fail
+        );
+    }
+    message
+}
+
+#[cfg(test)]
+mod test {
+    use super::{describe, ErrorReporter};
+    use crate::MessageOut;
+    use failure::{Fail, ResultExt};
+    use futures::channel::mpsc;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    #[derive(Debug, Fail)]
+    #[fail(display = "could not refresh")]
+    struct Refresh;
+
+    #[test]
+    fn error_chain() {
+        let error = "x".parse::<u32>().context("not a number").unwrap_err();
+        assert_eq!(
+            "error in abc: not a number: caused by: invalid digit found in string",
+            describe("abc", &error)
+        );
+        assert_eq!("error in abc: could not refresh", describe("abc", &Refresh));
+    }
+
+    #[test]
+    fn report() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (sender, mut receiver) = mpsc::unbounded::<MessageOut<Value, Value, Value>>();
+            let reporter = ErrorReporter::new(sender).with_title("Error", Duration::from_secs(3));
+            let context = "abc".to_string();
+            let reverted = tokio::spawn(reporter.report(&context, &Refresh));
+            drop(context);
+            drop(reporter);
+
+            assert!(matches!(
+                receiver.try_recv().unwrap(),
+                MessageOut::LogMessage { .. }
+            ));
+            assert!(matches!(
+                receiver.try_recv().unwrap(),
+                MessageOut::ShowAlert { .. }
+            ));
+            match receiver.try_recv().unwrap() {
+                MessageOut::SetTitle { context, payload } => {
+                    assert_eq!("abc", context);
+                    assert_eq!(Some("Error"), payload.title.as_deref());
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(receiver.try_recv().is_err());
+
+            tokio::time::sleep(Duration::from_secs(3)).await;
+            reverted.await.unwrap();
+            match receiver.try_recv().unwrap() {
+                MessageOut::SetTitle { payload, .. } => assert_eq!(None, payload.title),
+                other => panic!("unexpected {:?}", other),
+            }
+        });
+    }
+}
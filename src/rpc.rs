@@ -0,0 +1,324 @@
+//! Requests and responses between a plugin and its property inspectors.
+//!
+//! [SendToPlugin](../enum.Message.html#variant.SendToPlugin) and
+//! [SendToPropertyInspector](../enum.MessageOut.html#variant.SendToPropertyInspector) carry any
+//! payload but have no way to match a reply to the message it answers. Using an
+//! [`Envelope`](enum.Envelope.html) as the payload on both sides adds an id to each request, and
+//! an [`Rpc`](struct.Rpc.html) remembers the requests that are waiting for a response.
+//!
+//! - `Q` represents the requests sent to the other side.
+//! - `A` represents the answers sent to the other side's requests.
+//! - `N` represents the notifications sent to the other side, which do not expect a response.
+//!
+//! # Examples
+//!
+//! ```
+//! # use futures::prelude::*;
+//! # use serde_json::Value;
+//! # use streamdeck_rs::rpc::{Envelope, Incoming, Rpc};
+//! # use streamdeck_rs::MessageOut;
+//! # async fn example<Si: Sink<MessageOut<Value, Value, Envelope<String, Value, Value>>> + Unpin>(
+//! #     socket: &mut Si,
+//! #     payload: Envelope<Value, u32, Value>,
+//! # ) {
+//! let rpc: Rpc<u32> = Rpc::new();
+//!
+//! // send a request to the property inspector for "context"
+//! let mut sink = socket.with(|envelope| {
+//!     future::ok::<_, Si::Error>(MessageOut::SendToPropertyInspector {
+//!         action: None,
+//!         context: "context".into(),
+//!         payload: envelope,
+//!     })
+//! });
+//! let count = rpc.call(&mut sink, "count".to_string()).await;
+//!
+//! // elsewhere, for each SendToPlugin payload received
+//! match rpc.receive(payload) {
+//!     Some(Incoming::Request { id, body }) => { /* respond with Envelope::response(id, ...) */ }
+//!     Some(Incoming::Notification { body }) => {}
+//!     // responses complete the calls waiting for them
+//!     None => {}
+//! }
+//! # }
+//! ```
+
+use failure::Fail;
+use futures::channel::oneshot;
+use futures::prelude::*;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+/// A payload sent between a plugin and a property inspector.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "rpc", rename_all = "camelCase")]
+pub enum Envelope<Q, A, N> {
+    /// A request expecting a response with the same id.
+    Request {
+        /// The id of the request.
+        id: u64,
+        /// The request.
+        body: Q,
+    },
+    /// The response to a request.
+    Response {
+        /// The id of the request being answered.
+        id: u64,
+        /// The response.
+        body: A,
+    },
+    /// A message which does not expect a response.
+    Notification {
+        /// The message.
+        body: N,
+    },
+}
+
+impl<Q, A, N> Envelope<Q, A, N> {
+    /// Creates the response to a request.
+    pub fn response(id: u64, body: A) -> Self {
+        Envelope::Response { id, body }
+    }
+
+    /// Creates a notification.
+    pub fn notification(body: N) -> Self {
+        Envelope::Notification { body }
+    }
+}
+
+/// A payload received from the other side which is not a response.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Incoming<Q, N> {
+    /// A request, which should be answered with
+    /// [`Envelope::response`](enum.Envelope.html#method.response) and the same id.
+    Request {
+        /// The id of the request.
+        id: u64,
+        /// The request.
+        body: Q,
+    },
+    /// A message which does not expect a response.
+    Notification {
+        /// The message.
+        body: N,
+    },
+}
+
+/// Tracks the requests waiting for responses of type `R`.
+///
+/// An `Rpc` can be cloned cheaply, and clones share the same requests.
+pub struct Rpc<R> {
+    state: Arc<Mutex<State<R>>>,
+}
+
+struct State<R> {
+    next_id: u64,
+    pending: HashMap<u64, oneshot::Sender<R>>,
+}
+
+impl<R> Clone for Rpc<R> {
+    fn clone(&self) -> Self {
+        Rpc {
+            state: self.state.clone(),
+        }
+    }
+}
+
+impl<R> Default for Rpc<R> {
+    fn default() -> Self {
+        Rpc::new()
+    }
+}
+
+impl<R> fmt::Debug for Rpc<R> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Rpc")
+            .field("pending", &self.pending())
+            .finish()
+    }
+}
+
+impl<R> Rpc<R> {
+    /// Creates a tracker with no requests.
+    pub fn new() -> Self {
+        Rpc {
+            state: Arc::new(Mutex::new(State {
+                next_id: 0,
+                pending: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Creates a request, returning the envelope to send and a future for its response.
+    pub fn request<Q, A, N>(&self, body: Q) -> (Envelope<Q, A, N>, Response<R>) {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id = state.next_id.wrapping_add(1);
+        let (sender, receiver) = oneshot::channel();
+        state.pending.insert(id, sender);
+        (
+            Envelope::Request { id, body },
+            Response {
+                id,
+                receiver,
+                rpc: self.clone(),
+            },
+        )
+    }
+
+    /// Sends a request through `sink` and waits for its response.
+    pub async fn call<Si, Q, A, N>(&self, sink: &mut Si, body: Q) -> Result<R, RpcError<Si::Error>>
+    where
+        Si: Sink<Envelope<Q, A, N>> + Unpin,
+    {
+        let (envelope, response) = self.request(body);
+        sink.send(envelope).await.map_err(RpcError::Send)?;
+        response.await.map_err(|Cancelled| RpcError::Cancelled)
+    }
+
+    /// Handles a payload received from the other side.
+    ///
+    /// A response completes the request waiting for it and returns `None`, as does a response to
+    /// a request that is no longer waiting. Requests and notifications are returned to be handled
+    /// by the caller.
+    pub fn receive<Q, N>(&self, envelope: Envelope<Q, R, N>) -> Option<Incoming<Q, N>> {
+        match envelope {
+            Envelope::Request { id, body } => Some(Incoming::Request { id, body }),
+            Envelope::Notification { body } => Some(Incoming::Notification { body }),
+            Envelope::Response { id, body } => {
+                let sender = self.state.lock().unwrap().pending.remove(&id);
+                if let Some(sender) = sender {
+                    let _ = sender.send(body);
+                }
+                None
+            }
+        }
+    }
+
+    /// The number of requests waiting for responses.
+    pub fn pending(&self) -> usize {
+        self.state.lock().unwrap().pending.len()
+    }
+
+    /// Stops waiting for all requests, such as when the property inspector has disappeared.
+    ///
+    /// The calls waiting for responses fail with [`RpcError::Cancelled`](enum.RpcError.html).
+    pub fn cancel_all(&self) {
+        self.state.lock().unwrap().pending.clear();
+    }
+}
+
+/// A future for the response to a request.
+///
+/// Dropping the future stops waiting for the response.
+pub struct Response<R> {
+    id: u64,
+    receiver: oneshot::Receiver<R>,
+    rpc: Rpc<R>,
+}
+
+impl<R> Response<R> {
+    /// The id of the request.
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+}
+
+impl<R> Future for Response<R> {
+    type Output = Result<R, Cancelled>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.receiver.poll_unpin(cx).map_err(|_| Cancelled)
+    }
+}
+
+impl<R> Drop for Response<R> {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.rpc.state.lock() {
+            state.pending.remove(&self.id);
+        }
+    }
+}
+
+/// The request was cancelled before a response was received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Fail)]
+#[fail(display = "the request was cancelled")]
+pub struct Cancelled;
+
+/// Represents an error that occurred while calling the other side.
+#[derive(Debug)]
+pub enum RpcError<E> {
+    /// The request could not be sent.
+    Send(E),
+    /// The request was cancelled before a response was received.
+    Cancelled,
+}
+
+impl<E: fmt::Display> fmt::Display for RpcError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RpcError::Send(error) => write!(f, "the request could not be sent: {}", error),
+            RpcError::Cancelled => f.write_str("the request was cancelled"),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for RpcError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RpcError::Send(error) => Some(error),
+            RpcError::Cancelled => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Envelope, Incoming, Rpc, RpcError};
+    use futures::channel::mpsc;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+
+    #[test]
+    fn call() {
+        let (mut sink, mut requests) = mpsc::unbounded::<Envelope<String, (), ()>>();
+        let rpc: Rpc<usize> = Rpc::new();
+
+        block_on(async {
+            let call = rpc.call(&mut sink, "hello".to_string());
+            let answer = async {
+                let request = requests.next().await.unwrap();
+                assert_eq!(1, rpc.pending());
+                // the other side sees a request, and answers it
+                let json = serde_json::to_value(&request).unwrap();
+                assert_eq!("request", json["rpc"]);
+                let other: Rpc<()> = Rpc::new();
+                let (id, body) = match other.receive::<String, ()>(request.clone()) {
+                    Some(Incoming::Request { id, body }) => (id, body),
+                    _ => unreachable!(),
+                };
+                let response: Envelope<(), usize, ()> = Envelope::response(id, body.len());
+                assert_eq!(None, rpc.receive(response));
+            };
+            let (result, ()) = futures::join!(call, answer);
+            assert_eq!(5, result.unwrap());
+        });
+        assert_eq!(0, rpc.pending());
+
+        let (_, response) = rpc.request::<(), (), ()>(());
+        rpc.cancel_all();
+        assert!(block_on(response).is_err());
+
+        drop(requests);
+        assert!(matches!(
+            block_on(rpc.call(&mut sink, String::new())),
+            Err(RpcError::Send(_))
+        ));
+        assert_eq!(0, rpc.pending());
+    }
+}
@@ -0,0 +1,81 @@
+//! Helpers for working with action and global settings.
+
+#[cfg(feature = "schemars")]
+pub mod schema;
+
+use serde::{de, ser};
+use serde_json::Value;
+
+/// Applies changes from a partial settings object to complete settings.
+///
+/// Property inspectors often send only the fields they display, so replacing the settings with
+/// what they send would lose the other fields. This merges using
+/// [JSON merge patch](https://tools.ietf.org/html/rfc7386) semantics instead: fields in `partial`
+/// replace fields in `current`, nested objects are merged, and `null` removes a field.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// # use serde_json::json;
+/// # use streamdeck_rs::settings::merge_settings;
+/// #[derive(Deserialize, Serialize)]
+/// struct Settings {
+///     text: String,
+///     count: u32,
+/// }
+///
+/// let current = Settings { text: "Hello".to_string(), count: 3 };
+/// let merged = merge_settings(&current, &json!({ "text": "Goodbye" })).unwrap();
+/// assert_eq!("Goodbye", merged.text);
+/// assert_eq!(3, merged.count);
+/// ```
+pub fn merge_settings<S>(current: &S, partial: &Value) -> Result<S, serde_json::Error>
+where
+    S: ser::Serialize + de::DeserializeOwned,
+{
+    let mut merged = serde_json::to_value(current)?;
+    merge_patch(&mut merged, partial);
+    serde_json::from_value(merged)
+}
+
+/// Applies a [JSON merge patch](https://tools.ietf.org/html/rfc7386) to a value.
+pub fn merge_patch(target: &mut Value, patch: &Value) {
+    let patch = match patch {
+        Value::Object(patch) => patch,
+        patch => {
+            *target = patch.clone();
+            return;
+        }
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    let target = target.as_object_mut().unwrap();
+    for (key, value) in patch {
+        if value.is_null() {
+            target.remove(key);
+        } else {
+            merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::merge_patch;
+    use serde_json::json;
+
+    #[test]
+    fn merge() {
+        let mut target = json!({ "a": "b", "c": { "d": "e", "f": "g" }, "h": [1, 2] });
+        merge_patch(
+            &mut target,
+            &json!({ "a": "z", "c": { "f": null, "x": 1 }, "h": [3] }),
+        );
+        assert_eq!(
+            json!({ "a": "z", "c": { "d": "e", "x": 1 }, "h": [3] }),
+            target
+        );
+    }
+}
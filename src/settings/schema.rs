@@ -0,0 +1,406 @@
+//! JSON Schema for settings types.
+//!
+//! Settings saved by an older version of a plugin may not match the current settings type. The
+//! schema of the settings type can be given to property inspectors and other tools, and
+//! [`from_value_checked`](fn.from_value_checked.html) checks settings against it before
+//! deserializing them, so the error names the setting that does not match.
+//!
+//! The checks cover the parts of JSON Schema generated by `schemars` for plain data types: types,
+//! required and additional properties, enums and constants, array items, string lengths, and
+//! number ranges.
+
+use failure::Fail;
+use schemars::{schema_for, JsonSchema};
+use serde::de;
+use serde_json::{Map, Value};
+use std::any::TypeId;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::rc::Rc;
+
+thread_local! {
+    static SCHEMAS: RefCell<HashMap<TypeId, Rc<Value>>> = RefCell::new(HashMap::new());
+}
+
+/// Generates the JSON Schema for the settings type `S`.
+///
+/// # Examples
+///
+/// ```
+/// # use schemars::JsonSchema;
+/// # use serde_derive::{Deserialize, Serialize};
+/// # use streamdeck_rs::settings::schema::settings_schema;
+/// #[derive(Deserialize, Serialize, JsonSchema)]
+/// struct Settings {
+///     text: String,
+/// }
+///
+/// let schema = settings_schema::<Settings>();
+/// assert_eq!("string", schema["properties"]["text"]["type"]);
+/// ```
+pub fn settings_schema<S: JsonSchema>() -> Value {
+    serde_json::to_value(schema_for!(S)).expect("JSON schema")
+}
+
+/// A value does not match a schema.
+#[derive(Debug)]
+pub struct SchemaViolation {
+    /// The path to the value that does not match, such as `/items/0`, or an empty string for the
+    /// whole value.
+    pub path: String,
+    /// Why the value does not match.
+    pub reason: String,
+}
+
+impl fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.path.is_empty() {
+            f.write_str(&self.reason)
+        } else {
+            write!(f, "{}: {}", self.path, self.reason)
+        }
+    }
+}
+
+impl Fail for SchemaViolation {}
+
+/// Represents an error that occurred reading settings.
+#[derive(Debug, Fail)]
+pub enum SettingsError {
+    /// The settings do not match the schema.
+    #[fail(display = "invalid settings")]
+    Invalid(#[fail(cause)] SchemaViolation),
+    /// The settings could not be deserialized.
+    #[fail(display = "bad settings")]
+    BadSettings(#[fail(cause)] serde_json::Error),
+}
+
+/// Checks settings against the schema of the settings type `S`, then deserializes them.
+///
+/// The schema is generated once for each settings type on each thread.
+///
+/// # Examples
+///
+/// ```
+/// # use schemars::JsonSchema;
+/// # use serde_derive::{Deserialize, Serialize};
+/// # use serde_json::json;
+/// # use streamdeck_rs::settings::schema::from_value_checked;
+/// #[derive(Debug, Deserialize, Serialize, JsonSchema)]
+/// struct Settings {
+///     count: u32,
+/// }
+///
+/// let error = from_value_checked::<Settings>(json!({ "count": "3" })).unwrap_err();
+/// assert_eq!("invalid settings", error.to_string());
+/// ```
+pub fn from_value_checked<S>(value: Value) -> Result<S, SettingsError>
+where
+    S: JsonSchema + de::DeserializeOwned + 'static,
+{
+    validate(&cached_schema::<S>(), &value).map_err(SettingsError::Invalid)?;
+    serde_json::from_value(value).map_err(SettingsError::BadSettings)
+}
+
+/// Gets the schema of the settings type `S`, generating it if this thread has not already.
+fn cached_schema<S: JsonSchema + 'static>() -> Rc<Value> {
+    SCHEMAS.with(|schemas| {
+        schemas
+            .borrow_mut()
+            .entry(TypeId::of::<S>())
+            .or_insert_with(|| Rc::new(settings_schema::<S>()))
+            .clone()
+    })
+}
+
+/// Checks a value against a JSON Schema.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), SchemaViolation> {
+    let definitions = schema.get("definitions").and_then(Value::as_object);
+    Validator { definitions }.check(schema, value, &mut String::new())
+}
+
+struct Validator<'a> {
+    definitions: Option<&'a Map<String, Value>>,
+}
+
+impl<'a> Validator<'a> {
+    fn check(
+        &self,
+        schema: &Value,
+        value: &Value,
+        path: &mut String,
+    ) -> Result<(), SchemaViolation> {
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return Err(violation(path, "no value is allowed")),
+            Value::Object(schema) => schema,
+            _ => return Ok(()),
+        };
+
+        if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+            let definition = reference
+                .strip_prefix("#/definitions/")
+                .and_then(|name| self.definitions?.get(name));
+            match definition {
+                Some(definition) => self.check(definition, value, path)?,
+                None => {
+                    return Err(violation(
+                        path,
+                        &format!("the schema has no definition for {}", reference),
+                    ))
+                }
+            }
+        }
+        if let Some(all) = schema.get("allOf").and_then(Value::as_array) {
+            for alternative in all {
+                self.check(alternative, value, path)?;
+            }
+        }
+        if let Some(alternatives) = schema.get("anyOf").and_then(Value::as_array) {
+            if !alternatives
+                .iter()
+                .any(|alternative| self.check(alternative, value, path).is_ok())
+            {
+                return Err(violation(path, "does not match any of the alternatives"));
+            }
+        }
+        if let Some(alternatives) = schema.get("oneOf").and_then(Value::as_array) {
+            let matches = alternatives
+                .iter()
+                .filter(|alternative| self.check(alternative, value, path).is_ok())
+                .count();
+            match matches {
+                0 => return Err(violation(path, "does not match any of the alternatives")),
+                1 => {}
+                _ => return Err(violation(path, "matches more than one of the alternatives")),
+            }
+        }
+
+        if let Some(types) = schema.get("type") {
+            let types: Vec<&str> = match types {
+                Value::String(t) => vec![t.as_str()],
+                Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !types.iter().any(|t| has_type(value, t)) {
+                return Err(violation(path, &format!("expected {}", types.join(" or "))));
+            }
+        }
+        if let Some(options) = schema.get("enum").and_then(Value::as_array) {
+            if !options.contains(value) {
+                return Err(violation(path, "is not one of the allowed values"));
+            }
+        }
+        if let Some(constant) = schema.get("const") {
+            if constant != value {
+                return Err(violation(path, &format!("expected {}", constant)));
+            }
+        }
+
+        match value {
+            Value::Object(object) => self.check_object(schema, object, path),
+            Value::Array(items) => self.check_array(schema, items, path),
+            Value::String(string) => check_length(schema, string, path),
+            Value::Number(_) => check_range(schema, value.as_f64().unwrap_or_default(), path),
+            _ => Ok(()),
+        }
+    }
+
+    fn check_object(
+        &self,
+        schema: &Map<String, Value>,
+        object: &Map<String, Value>,
+        path: &mut String,
+    ) -> Result<(), SchemaViolation> {
+        if let Some(required) = schema.get("required").and_then(Value::as_array) {
+            for name in required.iter().filter_map(Value::as_str) {
+                if !object.contains_key(name) {
+                    return Err(violation(path, &format!("missing {}", name)));
+                }
+            }
+        }
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for (name, value) in object {
+            let property = match properties.and_then(|p| p.get(name)) {
+                Some(property) => property,
+                None => match schema.get("additionalProperties") {
+                    Some(additional) => additional,
+                    None => continue,
+                },
+            };
+            let len = path.len();
+            path.push('/');
+            path.push_str(name);
+            let result = self.check(property, value, path);
+            path.truncate(len);
+            result?;
+        }
+        Ok(())
+    }
+
+    fn check_array(
+        &self,
+        schema: &Map<String, Value>,
+        items: &[Value],
+        path: &mut String,
+    ) -> Result<(), SchemaViolation> {
+        let item_schema = match schema.get("items") {
+            Some(item_schema) => item_schema,
+            None => return Ok(()),
+        };
+        for (i, item) in items.iter().enumerate() {
+            let item_schema = match item_schema {
+                Value::Array(tuple) => match tuple.get(i) {
+                    Some(item_schema) => item_schema,
+                    None => continue,
+                },
+                item_schema => item_schema,
+            };
+            let len = path.len();
+            path.push('/');
+            path.push_str(&i.to_string());
+            let result = self.check(item_schema, item, path);
+            path.truncate(len);
+            result?;
+        }
+        Ok(())
+    }
+}
+
+fn has_type(value: &Value, t: &str) -> bool {
+    match t {
+        "null" => value.is_null(),
+        "boolean" => value.is_boolean(),
+        "string" => value.is_string(),
+        "array" => value.is_array(),
+        "object" => value.is_object(),
+        "number" => value.is_number(),
+        "integer" => {
+            value.is_i64() || value.is_u64() || value.as_f64().is_some_and(|n| n.fract() == 0.0)
+        }
+        _ => true,
+    }
+}
+
+fn check_length(
+    schema: &Map<String, Value>,
+    string: &str,
+    path: &str,
+) -> Result<(), SchemaViolation> {
+    let length = string.chars().count() as u64;
+    if let Some(minimum) = schema.get("minLength").and_then(Value::as_u64) {
+        if length < minimum {
+            return Err(violation(path, &format!("shorter than {}", minimum)));
+        }
+    }
+    if let Some(maximum) = schema.get("maxLength").and_then(Value::as_u64) {
+        if length > maximum {
+            return Err(violation(path, &format!("longer than {}", maximum)));
+        }
+    }
+    Ok(())
+}
+
+fn check_range(
+    schema: &Map<String, Value>,
+    number: f64,
+    path: &str,
+) -> Result<(), SchemaViolation> {
+    if let Some(minimum) = schema.get("minimum").and_then(Value::as_f64) {
+        if number < minimum {
+            return Err(violation(path, &format!("less than {}", minimum)));
+        }
+    }
+    if let Some(maximum) = schema.get("maximum").and_then(Value::as_f64) {
+        if number > maximum {
+            return Err(violation(path, &format!("greater than {}", maximum)));
+        }
+    }
+    Ok(())
+}
+
+fn violation(path: &str, reason: &str) -> SchemaViolation {
+    SchemaViolation {
+        path: path.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_value_checked, validate, SettingsError, SCHEMAS};
+    use schemars::JsonSchema;
+    use serde_derive::{Deserialize, Serialize};
+    use serde_json::json;
+    use std::any::TypeId;
+
+    #[derive(Debug, Deserialize, Serialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    enum Mode {
+        Count,
+        Toggle,
+    }
+
+    #[derive(Debug, Deserialize, Serialize, JsonSchema)]
+    #[serde(rename_all = "camelCase")]
+    struct Settings {
+        label: Option<String>,
+        step_size: u8,
+        modes: Vec<Mode>,
+    }
+
+    #[test]
+    fn checked() {
+        let settings: Settings =
+            from_value_checked(json!({ "label": null, "stepSize": 2, "modes": ["toggle"] }))
+                .unwrap();
+        assert_eq!(2, settings.step_size);
+
+        let violation = |value| match from_value_checked::<Settings>(value) {
+            Err(SettingsError::Invalid(violation)) => violation.to_string(),
+            other => panic!("unexpected {:?}", other),
+        };
+        assert_eq!(
+            "/modes/1: is not one of the allowed values",
+            violation(json!({ "stepSize": 2, "modes": ["count", "other"] }))
+        );
+        assert_eq!(
+            "/stepSize: less than 0",
+            violation(json!({ "stepSize": -1, "modes": [] }))
+        );
+        assert_eq!("missing modes", violation(json!({ "stepSize": 1 })));
+
+        // the schema is only generated once
+        SCHEMAS.with(|schemas| {
+            let schemas = schemas.borrow();
+            assert_eq!(1, schemas.len());
+            assert!(schemas.contains_key(&TypeId::of::<Settings>()));
+        });
+    }
+
+    #[test]
+    fn keywords() {
+        let schema = json!({ "oneOf": [{ "type": "integer" }, { "type": "number" }] });
+        assert!(validate(&schema, &json!(1.5)).is_ok());
+        assert_eq!(
+            "matches more than one of the alternatives",
+            validate(&schema, &json!(1)).unwrap_err().to_string()
+        );
+        assert_eq!(
+            "does not match any of the alternatives",
+            validate(&schema, &json!("1")).unwrap_err().to_string()
+        );
+
+        let schema = json!({
+            "properties": { "mode": { "$ref": "#/definitions/Mode" } },
+            "definitions": {}
+        });
+        assert_eq!(
+            "/mode: the schema has no definition for #/definitions/Mode",
+            validate(&schema, &json!({ "mode": "count" }))
+                .unwrap_err()
+                .to_string()
+        );
+    }
+}
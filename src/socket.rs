@@ -1,28 +1,108 @@
-use super::{Message, MessageOut};
+use super::devices::DeviceRegistry;
+use super::metrics::Metrics;
+use super::registration::{RegistrationInfo, RegistrationInfoApplication, RegistrationParams};
+use super::version::{UnsupportedMessage, Version};
+use super::{Device, Message, MessageOut};
 use failure::Fail;
 use futures::prelude::*;
+use futures::stream::FusedStream;
 use serde::{de, ser};
 use serde_derive::Serialize;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
 use tokio_tungstenite::{self, MaybeTlsStream, WebSocketStream};
 use url::Url;
 
+mod intercept;
+mod pump;
+mod queue;
+mod raw;
+mod reconnect;
+mod shutdown;
+mod split;
+mod supervised;
+
+pub use self::intercept::Interceptor;
+pub use self::pump::{CommandSender, Disconnected, EventStream};
+pub use self::queue::{Priority, QueuedSocket};
+pub use self::raw::{RawMessage, RawSocket};
+pub use self::reconnect::{Backoff, ConnectionEvent, ReconnectingSocket};
+pub use self::shutdown::Shutdown;
+pub use self::split::{ReadHalf, ReuniteError, WriteHalf};
+pub use self::supervised::{SentMessage, SocketEvent, SupervisedSocket};
+pub use tungstenite::protocol::frame::coding::CloseCode;
+pub use tungstenite::protocol::{CloseFrame, WebSocketConfig};
+
+type Inner = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A connection carrying web socket messages, which a
+/// [`StreamDeckSocket`](struct.StreamDeckSocket.html) encodes and decodes messages over.
+///
+/// This is normally the `WebSocketStream` opened by connecting, but any stream and sink of
+/// web socket messages can be used, such as a `WebSocketStream` over `tokio::io::duplex` in tests.
+pub trait Transport:
+    Stream<Item = Result<tungstenite::Message, tungstenite::Error>>
+    + Sink<tungstenite::Message, Error = tungstenite::Error>
+    + Unpin
+{
+}
+
+impl<T> Transport for T where
+    T: Stream<Item = Result<tungstenite::Message, tungstenite::Error>>
+        + Sink<tungstenite::Message, Error = tungstenite::Error>
+        + Unpin
+{
+}
+
+/// The number of times a transient failure to send is retried before it is reported.
+const SEND_RETRIES: u32 = 5;
+/// The delay before retrying a transient failure to send, which doubles with each retry.
+const SEND_RETRY_DELAY: Duration = Duration::from_millis(10);
+
 /// Provides encoding and decoding for messages sent to/from the Stream Deck software.
 ///
 /// - `S` represents settings persisted within the Stream Deck software.
 /// - `MI` represents messages received from the property inspector.
 /// - `MO` represents messages sent to the property inspector.
-pub struct StreamDeckSocket<G, S, MI, MO> {
-    inner: WebSocketStream<MaybeTlsStream<TcpStream>>,
+/// - `T` represents the connection, which is a web socket unless created by
+///   [`from_transport`](#method.from_transport).
+///
+/// Sending a message retries failures that are expected to pass, such as the send queue being
+/// full, a few times with increasing delays before reporting an error.
+///
+/// The socket is a `FusedStream` and is `Unpin`, so it can be used in `futures::select!`, and
+/// `&mut` references to it can be given to combinators that take a stream or sink.
+pub struct StreamDeckSocket<G, S, MI, MO, T = Inner> {
+    inner: T,
+    metrics: Metrics,
+    pending_ping: Option<(u64, Instant)>,
+    keep_alive: Option<KeepAlive>,
+    terminated: bool,
+    close_frame: Option<CloseFrame<'static>>,
+    shutdown: Vec<futures::channel::oneshot::Sender<Option<CloseFrame<'static>>>>,
+    retry: SendRetry,
+    info: Option<RegistrationInfo>,
+    devices: DeviceRegistry,
+    cache: Option<MessageCache>,
+    strict: bool,
+    target_version: Option<Version>,
+    encode_sizes: EncodeSizes,
+    interceptors: intercept::Interceptors<G, S, MI, MO>,
     _g: PhantomData<G>,
     _s: PhantomData<S>,
     _mi: PhantomData<MI>,
     _mo: PhantomData<MO>,
 }
 
+// The type parameters only describe the messages, which are never pinned.
+impl<G, S, MI, MO, T: Unpin> Unpin for StreamDeckSocket<G, S, MI, MO, T> {}
+
 impl<G, S, MI, MO> StreamDeckSocket<G, S, MI, MO> {
     /// Begins connecting to the Stream Deck software.
     ///
@@ -30,37 +110,239 @@ impl<G, S, MI, MO> StreamDeckSocket<G, S, MI, MO> {
     ///
     /// # Examples
     ///
-    /// ```
+    /// ```no_run
+    /// # use futures::prelude::*;
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # use serde_json::Value;
+    /// # use std::env;
+    /// # type GlobalSettings = Value;
+    /// # type ActionSettings = Value;
+    /// # type PiMessage = Value;
+    /// # type PiMessageOut = Value;
+    /// # async fn run() {
     /// let params = RegistrationParams::from_args(env::args()).unwrap();
-    /// let connect = StreamDeckSocket::<GlobalSettings, ActionSettings, PiMessage, PiMessageOut>::connect(params.port, params.event, params.uuid);
-    /// tokio::run(connect
-    ///     .map_err(|e| println!("connection failed: {:?}", e))
-    ///     .and_then(|socket| socket.for_each(|message| println!("received: {:?}", message))
-    ///         .map_err(|e| println!("read error: {:?}", e))));
+    /// let mut socket = StreamDeckSocket::<GlobalSettings, ActionSettings, PiMessage, PiMessageOut>::connect(params.port, params.event, params.uuid)
+    ///     .await
+    ///     .expect("connection failed");
+    /// while let Some(message) = socket.next().await {
+    ///     println!("received: {:?}", message);
+    /// }
+    /// # }
     /// ```
     pub async fn connect<A: Into<Address>>(
         address: A,
         event: String,
         uuid: String,
+    ) -> Result<Self, ConnectError> {
+        Self::connect_with_registration(address, Registration::new(&event, &uuid)).await
+    }
+
+    /// Begins connecting to the Stream Deck software, giving up if the connection is not
+    /// established and the registration sent within `timeout`.
+    ///
+    /// This avoids waiting forever when the port is wrong or the Stream Deck software is not
+    /// responding. Like the other ways of connecting, the returned future can also be cancelled
+    /// at any point by dropping it, which closes the partially opened connection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # use serde_json::Value;
+    /// # use std::env;
+    /// # use std::time::Duration;
+    /// # async fn run() {
+    /// let params = RegistrationParams::from_args(env::args()).unwrap();
+    /// let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_timeout(params.port, params.event, params.uuid, Duration::from_secs(5))
+    ///     .await
+    ///     .expect("connection failed");
+    /// # }
+    /// ```
+    pub async fn connect_timeout<A: Into<Address>>(
+        address: A,
+        event: String,
+        uuid: String,
+        timeout: Duration,
+    ) -> Result<Self, ConnectError> {
+        tokio::time::timeout(timeout, Self::connect(address, event, uuid))
+            .await
+            .map_err(|_| ConnectError::Timeout)?
+    }
+
+    /// Begins connecting to the Stream Deck software using the parameters given to the plugin.
+    ///
+    /// The registration info is kept by the socket and is available from [`info`](#method.info).
+    /// If the Stream Deck software is newer than [`SDK_VERSION`](../constant.SDK_VERSION.html), a
+    /// warning is written to the plugin log after registering, to explain events received as
+    /// [`Message::Unknown`](../enum.Message.html#variant.Unknown).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use streamdeck_rs::registration::RegistrationParams;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # use serde_json::Value;
+    /// # use std::env;
+    /// # async fn run() {
+    /// let params = RegistrationParams::from_args(env::args()).unwrap();
+    /// let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_with(params)
+    ///     .await
+    ///     .expect("connection failed");
+    /// println!("running as {}", socket.plugin_uuid().unwrap());
+    /// # }
+    /// ```
+    pub async fn connect_with(params: RegistrationParams) -> Result<Self, ConnectError> {
+        let mut socket = Self::connect(params.port, params.event, params.uuid).await?;
+        for warning in version_warnings(&params.info.application) {
+            let message = encode(&mut socket.encode_sizes, &warning)
+                .map_err(ConnectError::BadRegistration)?;
+            socket
+                .inner
+                .send(tungstenite::Message::Text(message))
+                .await
+                .map_err(ConnectError::SendError)?;
+        }
+        socket.devices = DeviceRegistry::from_info(&params.info);
+        socket.info = Some(params.info);
+        Ok(socket)
+    }
+
+    /// Begins connecting to the Stream Deck software, sending a customized registration message.
+    ///
+    /// This is only needed when connecting to something other than the Stream Deck software, such
+    /// as a debugging proxy, which accepts additional registration data.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use serde_derive::Serialize;
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::socket::Registration;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// #[derive(Serialize)]
+    /// struct Extra {
+    ///     session: &'static str,
+    /// }
+    ///
+    /// # async fn run() {
+    /// let registration = Registration::new("registerPlugin", "ABCDEF").with_extra(Extra { session: "debug" });
+    /// let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_with_registration(28196, registration)
+    ///     .await
+    ///     .expect("connection failed");
+    /// # }
+    /// ```
+    pub async fn connect_with_registration<A: Into<Address>, E: ser::Serialize>(
+        address: A,
+        registration: Registration<'_, E>,
+    ) -> Result<Self, ConnectError> {
+        Self::connect_with_config(address, registration, None).await
+    }
+
+    /// Begins connecting to the Stream Deck software, using custom limits for the web socket.
+    ///
+    /// The default limits suit most plugins, but the maximum message and frame sizes may need to
+    /// be raised for plugins that send large images with
+    /// [SetImage](../enum.MessageOut.html#variant.SetImage).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::socket::{Registration, WebSocketConfig};
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # async fn run() {
+    /// let config = WebSocketConfig {
+    ///     max_message_size: Some(256 << 20),
+    ///     max_frame_size: Some(64 << 20),
+    ///     ..WebSocketConfig::default()
+    /// };
+    /// let registration = Registration::new("registerPlugin", "ABCDEF");
+    /// let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_with_config(28196, registration, Some(config))
+    ///     .await
+    ///     .expect("connection failed");
+    /// # }
+    /// ```
+    pub async fn connect_with_config<A: Into<Address>, E: ser::Serialize>(
+        address: A,
+        registration: Registration<'_, E>,
+        config: Option<WebSocketConfig>,
     ) -> Result<Self, ConnectError> {
         let address = address.into();
 
-        let (mut stream, _) = tokio_tungstenite::connect_async(address.url)
+        let message =
+            serde_json::to_string(&registration).map_err(ConnectError::BadRegistration)?;
+
+        let (stream, _) = tokio_tungstenite::connect_async_with_config(address.url, config)
             .await
             .map_err(ConnectError::ConnectionError)?;
 
-        let message = serde_json::to_string(&Registration {
-            event: &event,
-            uuid: &uuid,
-        })
-        .unwrap();
-        stream
+        Self::register(stream, message).await
+    }
+}
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Registers with the Stream Deck software over a connection that is already open.
+    ///
+    /// This allows using a connection other than a web socket opened by this crate, such as an
+    /// in-memory connection in tests.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use futures::prelude::*;
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::socket::Registration;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # use tokio_tungstenite::WebSocketStream;
+    /// # use tungstenite::protocol::Role;
+    /// # tokio::runtime::Builder::new_current_thread().build().unwrap().block_on(async {
+    /// let (plugin, stream_deck) = tokio::io::duplex(4096);
+    /// let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+    /// let socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+    ///     transport,
+    ///     Registration::new("registerPlugin", "ABCDEF"),
+    /// )
+    /// .await
+    /// .expect("registration failed");
+    ///
+    /// let mut stream_deck = WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+    /// let registration = stream_deck.next().await.unwrap().unwrap();
+    /// assert!(registration.to_text().unwrap().contains("registerPlugin"));
+    /// # });
+    /// ```
+    pub async fn from_transport<E: ser::Serialize>(
+        transport: T,
+        registration: Registration<'_, E>,
+    ) -> Result<Self, ConnectError> {
+        let message =
+            serde_json::to_string(&registration).map_err(ConnectError::BadRegistration)?;
+        Self::register(transport, message).await
+    }
+
+    async fn register(mut transport: T, message: String) -> Result<Self, ConnectError> {
+        transport
             .send(tungstenite::Message::Text(message))
             .await
             .map_err(ConnectError::SendError)?;
 
         Ok(StreamDeckSocket {
-            inner: stream,
+            inner: transport,
+            metrics: Metrics::default(),
+            pending_ping: None,
+            keep_alive: None,
+            terminated: false,
+            close_frame: None,
+            shutdown: Vec::new(),
+            retry: SendRetry::default(),
+            info: None,
+            devices: DeviceRegistry::new(),
+            cache: None,
+            strict: false,
+            target_version: None,
+            encode_sizes: EncodeSizes::new(),
+            interceptors: Vec::new(),
             _g: PhantomData,
             _s: PhantomData,
             _mi: PhantomData,
@@ -68,24 +350,485 @@ impl<G, S, MI, MO> StreamDeckSocket<G, S, MI, MO> {
         })
     }
 
-    fn pin_get_inner(self: Pin<&mut Self>) -> Pin<&mut WebSocketStream<MaybeTlsStream<TcpStream>>> {
-        unsafe { self.map_unchecked_mut(|s| &mut s.inner) }
+    /// Sends a ping to the Stream Deck software to measure the round trip time.
+    ///
+    /// The pong is received while the socket is being polled for messages, after which the round
+    /// trip time is available from [`metrics`](#method.metrics). A slow round trip time indicates
+    /// that the Stream Deck software is overloaded.
+    ///
+    /// If a previous ping has not been answered, it is forgotten.
+    pub async fn send_ping(&mut self) -> Result<(), StreamDeckSocketError> {
+        let id = self.metrics.pings_sent;
+        self.inner
+            .send(tungstenite::Message::Ping(id.to_be_bytes().to_vec()))
+            .await
+            .map_err(StreamDeckSocketError::from)?;
+        self.metrics.pings_sent += 1;
+        self.pending_ping = Some((id, Instant::now()));
+        Ok(())
+    }
+
+    /// Gets the registration info, if the socket was created by
+    /// [`connect_with`](#method.connect_with).
+    pub fn info(&self) -> Option<&RegistrationInfo> {
+        self.info.as_ref()
+    }
+
+    /// Gets the UUID of the plugin, if the socket was created by
+    /// [`connect_with`](#method.connect_with) and the Stream Deck software sent it.
+    pub fn plugin_uuid(&self) -> Option<&str> {
+        self.info.as_ref()?.plugin.uuid.as_deref()
+    }
+
+    /// Gets the devices that are currently connected.
+    ///
+    /// This starts with the devices from the registration info and is updated as devices connect
+    /// and disconnect.
+    pub fn devices(&self) -> &DeviceRegistry {
+        &self.devices
+    }
+
+    /// Gets a connected device by ID.
+    pub fn device(&self, id: &str) -> Option<&Device> {
+        self.devices.get(id)
+    }
+
+    /// Reuses the encoded form of messages that are always the same for a context.
+    ///
+    /// [ShowOk](../enum.MessageOut.html#variant.ShowOk),
+    /// [ShowAlert](../enum.MessageOut.html#variant.ShowAlert),
+    /// [GetSettings](../enum.MessageOut.html#variant.GetSettings),
+    /// [GetGlobalSettings](../enum.MessageOut.html#variant.GetGlobalSettings), and
+    /// [SetState](../enum.MessageOut.html#variant.SetState) are encoded once per context (and state)
+    /// and then reused, which helps plugins that send these messages frequently. Encoded messages
+    /// are discarded when their context disappears.
+    pub fn with_message_cache(mut self) -> Self {
+        self.cache = Some(MessageCache::default());
+        self
+    }
+
+    /// Reports fields sent by the Stream Deck software that are not understood by this crate.
+    ///
+    /// When strict parsing is enabled, a message containing fields that would be ignored is
+    /// returned as [`StreamDeckSocketError::UnknownFields`](enum.StreamDeckSocketError.html#variant.UnknownFields)
+    /// instead. This is intended for catching changes to the protocol during development, as
+    /// plugins normally work with newer versions of the Stream Deck software by ignoring the new
+    /// fields. Settings and messages from the property inspector are not checked, and neither are
+    /// events that are received as [`Message::Unknown`](../enum.Message.html#variant.Unknown).
+    pub fn with_strict_parsing(mut self) -> Self {
+        self.strict = true;
+        self
+    }
+
+    /// Pings the Stream Deck software while the socket is being polled, to detect a connection that
+    /// has stopped working.
+    ///
+    /// A ping is sent every `interval`, and if it is not answered within `timeout`, the socket
+    /// returns [`StreamDeckSocketError::Unresponsive`](enum.StreamDeckSocketError.html#variant.Unresponsive)
+    /// and then ends. Otherwise, a plugin that rarely receives messages cannot tell whether it is
+    /// idle or disconnected.
+    pub fn with_keep_alive(mut self, interval: Duration, timeout: Duration) -> Self {
+        self.keep_alive = Some(KeepAlive {
+            interval,
+            timeout,
+            timer: None,
+        });
+        self
+    }
+
+    /// Adapts messages for a version of the Stream Deck software before sending them.
+    ///
+    /// Fields the version does not support are removed, and events it does not support are
+    /// returned as [`StreamDeckSocketError::Unsupported`](enum.StreamDeckSocketError.html#variant.Unsupported)
+    /// instead of being ignored by the Stream Deck software.
+    pub fn with_target_version(mut self, version: Version) -> Self {
+        self.target_version = Some(version);
+        self
+    }
+
+    /// Adapts messages for the version of the Stream Deck software from the registration info, as
+    /// [`with_target_version`](#method.with_target_version) does.
+    ///
+    /// This does nothing if the socket was not created by [`connect_with`](#method.connect_with)
+    /// or the version cannot be parsed.
+    pub fn with_registered_version(mut self) -> Self {
+        if let Some(Ok(version)) = self
+            .info
+            .as_ref()
+            .map(|info| info.application.parsed_version())
+        {
+            self.target_version = Some(version);
+        }
+        self
+    }
+
+    /// Gets the version of the Stream Deck software that messages are adapted for, if any.
+    pub fn target_version(&self) -> Option<Version> {
+        self.target_version
+    }
+
+    /// Gets measurements of the connection.
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Gets the underlying connection.
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+
+    /// Gets the underlying connection.
+    ///
+    /// Reading from or writing to the connection directly bypasses the socket, so messages read
+    /// this way do not update the devices, and pongs are not recorded in the metrics.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying connection.
+    ///
+    /// A message waiting to be retried is discarded.
+    pub fn into_inner(self) -> T {
+        self.inner
+    }
+
+    fn receive_pong(&mut self, payload: &[u8]) {
+        if let Some((id, sent)) = self.pending_ping {
+            if payload == id.to_be_bytes() {
+                self.metrics.ping_rtt = Some(sent.elapsed());
+                self.metrics.pongs_received += 1;
+                self.pending_ping = None;
+                if let Some(keep_alive) = &mut self.keep_alive {
+                    keep_alive.schedule(sent + keep_alive.interval);
+                }
+            }
+        }
+    }
+
+    /// Sends the keep-alive pings, returning an error if one was not answered in time.
+    fn poll_keep_alive(&mut self, cx: &mut Context) -> Option<StreamDeckSocketError> {
+        let keep_alive = self.keep_alive.as_mut()?;
+        let interval = keep_alive.interval;
+        let timer = keep_alive
+            .timer
+            .get_or_insert_with(|| Box::pin(tokio::time::sleep(interval)));
+        if timer.as_mut().poll(cx).is_pending() {
+            return None;
+        }
+        let error = match self.pending_ping {
+            Some((_, sent)) if sent.elapsed() >= keep_alive.timeout => {
+                self.terminate();
+                Some(StreamDeckSocketError::Unresponsive)
+            }
+            Some((_, sent)) => {
+                keep_alive.schedule(sent + keep_alive.timeout);
+                None
+            }
+            None => {
+                // if the ping cannot be queued yet, the timer stays expired and it is tried again
+                match Pin::new(&mut self.inner).poll_ready(cx) {
+                    Poll::Ready(Ok(())) => {}
+                    Poll::Ready(Err(error)) => return Some(StreamDeckSocketError::from(error)),
+                    Poll::Pending => return None,
+                }
+                let id = self.metrics.pings_sent;
+                if let Err(error) = Pin::new(&mut self.inner)
+                    .start_send(tungstenite::Message::Ping(id.to_be_bytes().to_vec()))
+                {
+                    return Some(StreamDeckSocketError::from(error));
+                }
+                // the ping is written by later sends if the socket is busy
+                let _ = Pin::new(&mut self.inner).poll_flush(cx);
+                let now = Instant::now();
+                self.metrics.pings_sent += 1;
+                self.pending_ping = Some((id, now));
+                keep_alive.schedule(now + keep_alive.timeout);
+                None
+            }
+        };
+        // a new deadline must be polled to be woken for it
+        error.or_else(|| self.poll_keep_alive(cx))
+    }
+
+    /// Receives the next frame, sending keep-alive pings and recording pongs along the way.
+    fn poll_frame(
+        &mut self,
+        cx: &mut Context,
+    ) -> Poll<Option<Result<tungstenite::Message, StreamDeckSocketError>>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+        if let Some(error) = self.poll_keep_alive(cx) {
+            return Poll::Ready(Some(Err(error)));
+        }
+        let frame = futures::ready!(Pin::new(&mut self.inner).poll_next(cx));
+        match &frame {
+            Some(Ok(tungstenite::Message::Pong(payload))) => self.receive_pong(payload),
+            Some(Ok(tungstenite::Message::Text(text))) => {
+                self.metrics.bytes_received += text.len() as u64
+            }
+            Some(Ok(tungstenite::Message::Close(frame))) => {
+                self.close_frame = frame.clone().map(CloseFrame::into_owned)
+            }
+            Some(_) => {}
+            None => self.terminate(),
+        }
+        Poll::Ready(frame.map(|frame| frame.map_err(StreamDeckSocketError::from)))
+    }
+
+    /// Decodes a text frame, updating the devices and cached messages it affects.
+    ///
+    /// Owned text is parsed in place when the `simd-json` feature is enabled.
+    fn decode(&mut self, text: Cow<str>) -> Result<Message<G, S, MI>, StreamDeckSocketError>
+    where
+        G: de::DeserializeOwned,
+        S: de::DeserializeOwned,
+        MI: de::DeserializeOwned,
+    {
+        if self.strict {
+            if let Some(error) = check_strict(&text) {
+                return Err(error);
+            }
+        }
+        #[cfg(feature = "simd-json")]
+        let message = Message::from_json_simd(text.into_owned());
+        #[cfg(not(feature = "simd-json"))]
+        let message = Message::from_json(&text);
+        let mut message = message.map_err(|error| {
+            self.metrics.decode_failures += 1;
+            StreamDeckSocketError::BadMessage(error)
+        })?;
+        self.metrics.receive(message.event());
+        for interceptor in &mut self.interceptors {
+            interceptor.received(&mut message);
+        }
+        self.devices.observe(&message);
+        if let (Some(cache), Message::WillDisappear { context, .. }) = (&mut self.cache, &message) {
+            cache.remove(context);
+        }
+        Ok(message)
+    }
+
+    /// Sends a message that could not be queued earlier, waiting out any backoff first.
+    fn poll_send_retry(&mut self, cx: &mut Context) -> Poll<Result<(), tungstenite::Error>> {
+        loop {
+            futures::ready!(self.retry.poll_delay(cx));
+            let message = match self.retry.message.take() {
+                Some(message) => message,
+                None => return Poll::Ready(Ok(())),
+            };
+            match Pin::new(&mut self.inner).poll_ready(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(error)) if is_transient(&error) && self.retry.backoff() => {
+                    self.retry.message = Some(message);
+                    continue;
+                }
+                Poll::Ready(Err(error)) => return Poll::Ready(Err(error)),
+                Poll::Pending => {
+                    self.retry.message = Some(message);
+                    return Poll::Pending;
+                }
+            }
+            let len = message.len();
+            match Pin::new(&mut self.inner).start_send(message) {
+                Ok(()) => {
+                    self.retry.attempts = 0;
+                    self.metrics.messages_sent += 1;
+                    self.metrics.bytes_sent += len as u64;
+                    return Poll::Ready(Ok(()));
+                }
+                Err(tungstenite::Error::SendQueueFull(message)) if self.retry.backoff() => {
+                    self.retry.message = Some(message);
+                }
+                Err(error) => return Poll::Ready(Err(error)),
+            }
+        }
+    }
+
+    /// Runs an operation on the web socket after any retried message has been sent, retrying the
+    /// operation with backoff if it fails in a way that is expected to pass.
+    fn poll_with_retry<F>(
+        &mut self,
+        cx: &mut Context,
+        operation: F,
+    ) -> Poll<Result<(), StreamDeckSocketError>>
+    where
+        F: Fn(Pin<&mut T>, &mut Context) -> Poll<Result<(), tungstenite::Error>>,
+    {
+        loop {
+            if let Err(error) = futures::ready!(self.poll_send_retry(cx)) {
+                return Poll::Ready(Err(StreamDeckSocketError::from(error)));
+            }
+            match operation(Pin::new(&mut self.inner), cx) {
+                Poll::Ready(Err(error)) if is_transient(&error) && self.retry.backoff() => {}
+                Poll::Ready(Err(error)) => {
+                    return Poll::Ready(Err(StreamDeckSocketError::from(error)))
+                }
+                Poll::Ready(Ok(())) => {
+                    self.retry.attempts = 0;
+                    return Poll::Ready(Ok(()));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+/// Gets the log messages warning that the Stream Deck software is newer than this crate.
+#[cfg(feature = "logging")]
+fn version_warnings(application: &RegistrationInfoApplication) -> Vec<MessageOut<(), (), ()>> {
+    use slog::Drain;
+
+    let (sender, mut receiver) = futures::channel::mpsc::unbounded();
+    let drain = super::logging::StreamDeckDrain::new(sender).ignore_res();
+    let logger = slog::Logger::root(drain, slog::o!());
+    super::logging::warn_version_mismatch(&logger, application);
+    drop(logger);
+    std::iter::from_fn(|| receiver.try_recv().ok()).collect()
+}
+
+/// Gets the log messages warning that the Stream Deck software is newer than this crate.
+#[cfg(not(feature = "logging"))]
+fn version_warnings(application: &RegistrationInfoApplication) -> Vec<MessageOut<(), (), ()>> {
+    application
+        .version_mismatch()
+        .map(|mismatch| MessageOut::log_message(mismatch.to_string()))
+        .into_iter()
+        .collect()
+}
+
+/// Encoded messages keyed by context and then by event and state.
+type MessageCache = HashMap<super::Context, HashMap<(&'static str, Option<u8>), String>>;
+
+/// The length of the last message encoded for each event, used to size the buffer for the next.
+type EncodeSizes = HashMap<&'static str, usize>;
+
+/// Encodes a message as JSON.
+///
+/// The message is written to a buffer with room for the last message of the same event, so that
+/// large messages such as images are not copied each time the buffer grows, and the buffer then
+/// becomes the text of the message without being copied again.
+fn encode<G, S, MO>(
+    sizes: &mut EncodeSizes,
+    item: &MessageOut<G, S, MO>,
+) -> Result<String, serde_json::Error>
+where
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    let size = sizes.entry(item.event()).or_default();
+    let mut buffer = Vec::with_capacity(*size);
+    serde_json::to_writer(&mut buffer, item)?;
+    *size = buffer.len();
+    // serde_json only writes valid UTF-8
+    String::from_utf8(buffer).map_err(ser::Error::custom)
+}
+
+/// Gets the key for a message whose encoding depends only on its context, if it is one.
+fn cache_key<G, S, MO>(
+    message: &MessageOut<G, S, MO>,
+) -> Option<(&super::Context, (&'static str, Option<u8>))> {
+    match message {
+        MessageOut::ShowOk { context }
+        | MessageOut::ShowAlert { context }
+        | MessageOut::GetSettings { context }
+        | MessageOut::GetGlobalSettings { context } => Some((context, (message.event(), None))),
+        MessageOut::SetState { context, payload } => {
+            Some((context, (message.event(), Some(payload.state))))
+        }
+        _ => None,
+    }
+}
+
+/// A message waiting to be sent again after a transient failure.
+#[derive(Default)]
+struct SendRetry {
+    message: Option<tungstenite::Message>,
+    attempts: u32,
+    delay: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl SendRetry {
+    /// Schedules another attempt, or returns false if there have been too many attempts.
+    fn backoff(&mut self) -> bool {
+        if self.attempts >= SEND_RETRIES {
+            self.attempts = 0;
+            return false;
+        }
+        self.delay = Some(Box::pin(tokio::time::sleep(
+            SEND_RETRY_DELAY * 2u32.pow(self.attempts),
+        )));
+        self.attempts += 1;
+        true
+    }
+
+    fn poll_delay(&mut self, cx: &mut Context) -> Poll<()> {
+        if let Some(delay) = &mut self.delay {
+            futures::ready!(delay.as_mut().poll(cx));
+            self.delay = None;
+        }
+        Poll::Ready(())
+    }
+}
+
+/// Settings and state for [`with_keep_alive`](struct.StreamDeckSocket.html#method.with_keep_alive).
+struct KeepAlive {
+    interval: Duration,
+    timeout: Duration,
+    timer: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl KeepAlive {
+    fn schedule(&mut self, deadline: Instant) {
+        self.timer = Some(Box::pin(tokio::time::sleep_until(deadline.into())));
     }
 }
 
+/// Checks whether an error is likely to pass if the operation is retried.
+///
+/// tokio-tungstenite already waits out I/O that would block, so only a full send queue is retried.
+fn is_transient(error: &tungstenite::Error) -> bool {
+    matches!(error, tungstenite::Error::SendQueueFull(_))
+}
+
 /// Represents an error that occurred reading or writing the web socket.
 #[derive(Debug, Fail)]
 pub enum StreamDeckSocketError {
     /// The web socket reported an error.
     #[fail(display = "WebSocket error")]
-    WebSocketError(#[fail(cause)] tungstenite::error::Error),
+    WebSocketError(#[fail(cause)] Box<tungstenite::error::Error>),
     /// The message could not be encoded/decoded.
     #[fail(display = "Bad message")]
     BadMessage(#[fail(cause)] serde_json::Error),
+    /// The message contained fields that are not understood, and strict parsing is enabled.
+    #[fail(display = "Unknown fields in {} message: {:?}", event, fields)]
+    UnknownFields {
+        /// The name of the event.
+        event: String,
+        /// The paths of the unknown fields, such as `payload.newField`.
+        fields: Vec<String>,
+    },
+    /// The message is not supported by the target version of the Stream Deck software.
+    #[fail(display = "Unsupported message")]
+    Unsupported(#[fail(cause)] UnsupportedMessage),
+    /// A keep-alive ping was not answered in time, so the connection is assumed to be lost.
+    #[fail(display = "Stream Deck software is not responding")]
+    Unresponsive,
+    /// The connection was lost and has not been reestablished yet.
+    #[fail(display = "Not connected")]
+    NotConnected,
 }
 
-impl<G, S, MI, MO> Stream for StreamDeckSocket<G, S, MI, MO>
+impl From<tungstenite::error::Error> for StreamDeckSocketError {
+    fn from(error: tungstenite::error::Error) -> Self {
+        StreamDeckSocketError::WebSocketError(Box::new(error))
+    }
+}
+
+impl<G, S, MI, MO, T> Stream for StreamDeckSocket<G, S, MI, MO, T>
 where
+    T: Transport,
     G: de::DeserializeOwned,
     S: de::DeserializeOwned,
     MI: de::DeserializeOwned,
@@ -93,30 +836,120 @@ where
     type Item = Result<Message<G, S, MI>, StreamDeckSocketError>;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
-        let mut inner = self.pin_get_inner();
+        let this = self.get_mut();
         loop {
-            match inner.as_mut().poll_next(cx) {
-                Poll::Ready(Some(Ok(tungstenite::Message::Text(message)))) => {
-                    break match serde_json::from_str(&message) {
-                        Ok(message) => Poll::Ready(Some(Ok(message))),
-                        Err(error) => {
-                            Poll::Ready(Some(Err(StreamDeckSocketError::BadMessage(error))))
-                        }
-                    };
+            match futures::ready!(this.poll_frame(cx)) {
+                Some(Ok(tungstenite::Message::Text(text))) => {
+                    return Poll::Ready(Some(this.decode(Cow::Owned(text))))
                 }
-                Poll::Ready(Some(Ok(_))) => {}
-                Poll::Ready(Some(Err(error))) => {
-                    break Poll::Ready(Some(Err(StreamDeckSocketError::WebSocketError(error))))
+                Some(Ok(_)) => {}
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<G, S, MI, MO, T> FusedStream for StreamDeckSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+{
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+/// Finds the fields of a message that are lost by parsing it, ignoring settings and messages from
+/// the property inspector.
+fn check_strict(message: &str) -> Option<StreamDeckSocketError> {
+    let original: Value = serde_json::from_str(message).ok()?;
+    let mut parsed: Message<Value, Value, Value> = Message::from_json(message).ok()?;
+    if let Message::Unknown { .. } = parsed {
+        return None;
+    }
+    // Fields that are only kept as extra fields are not understood either.
+    parsed.clear_extra();
+    let parsed = serde_json::to_value(&parsed).ok()?;
+
+    let mut fields = Vec::new();
+    unknown_fields(&original, &parsed, "", &mut fields);
+    if fields.is_empty() {
+        None
+    } else {
+        Some(StreamDeckSocketError::UnknownFields {
+            event: original["event"].as_str().unwrap_or_default().to_string(),
+            fields,
+        })
+    }
+}
+
+fn unknown_fields(original: &Value, parsed: &Value, path: &str, fields: &mut Vec<String>) {
+    match (original, parsed) {
+        (Value::Object(original), Value::Object(parsed)) => {
+            for (key, value) in original {
+                let field = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match parsed.get(key) {
+                    Some(parsed) => unknown_fields(value, parsed, &field, fields),
+                    None if value.is_null() => {}
+                    None => fields.push(field),
                 }
-                Poll::Ready(None) => break Poll::Ready(None),
-                Poll::Pending => break Poll::Pending,
             }
         }
+        (Value::Array(original), Value::Array(parsed)) => {
+            for (i, (value, parsed)) in original.iter().zip(parsed).enumerate() {
+                unknown_fields(value, parsed, &format!("{}[{}]", path, i), fields);
+            }
+        }
+        _ => {}
     }
 }
 
-impl<G, S, MI, MO> Sink<MessageOut<G, S, MO>> for StreamDeckSocket<G, S, MI, MO>
+impl<G, S, MI, MO, T> StreamDeckSocket<G, S, MI, MO, T>
 where
+    T: Transport,
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    /// Sends several messages, flushing the socket once after all of them have been queued.
+    ///
+    /// This is faster than sending the messages one at a time when updating many actions at once,
+    /// such as when a page of a device is shown. If a message cannot be sent, the messages after
+    /// it are not sent.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::{MessageOut, StreamDeckSocket};
+    /// # async fn run(mut socket: StreamDeckSocket<Value, Value, Value, Value>, contexts: Vec<String>) {
+    /// let titles = contexts
+    ///     .iter()
+    ///     .map(|context| MessageOut::set_title(context.as_str()).text("Ready").build());
+    /// socket.send_batch(titles).await.expect("failed to send");
+    /// # }
+    /// ```
+    pub async fn send_batch<I>(&mut self, messages: I) -> Result<(), StreamDeckSocketError>
+    where
+        I: IntoIterator<Item = MessageOut<G, S, MO>>,
+    {
+        for message in messages {
+            self.feed(message).await?;
+        }
+        self.flush().await
+    }
+}
+
+impl<G, S, MI, MO, T> Sink<MessageOut<G, S, MO>> for StreamDeckSocket<G, S, MI, MO, T>
+where
+    T: Transport,
     G: ser::Serialize,
     S: ser::Serialize,
     MO: ser::Serialize,
@@ -124,28 +957,59 @@ where
     type Error = StreamDeckSocketError;
 
     fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.pin_get_inner()
-            .poll_ready(cx)
-            .map_err(StreamDeckSocketError::WebSocketError)
+        self.get_mut().poll_with_retry(cx, Sink::poll_ready)
     }
 
-    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
-        let message = serde_json::to_string(&item).map_err(StreamDeckSocketError::BadMessage)?;
-        self.pin_get_inner()
-            .start_send(tungstenite::Message::Text(message))
-            .map_err(StreamDeckSocketError::WebSocketError)
+    fn start_send(self: Pin<&mut Self>, mut item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        for interceptor in &mut this.interceptors {
+            interceptor.sending(&mut item);
+        }
+        let item = match this.target_version() {
+            Some(version) => item
+                .for_version(version)
+                .map_err(StreamDeckSocketError::Unsupported)?,
+            None => item,
+        };
+        let message = match (&mut this.cache, cache_key(&item)) {
+            (Some(cache), Some((context, key))) => {
+                match cache.get(context).and_then(|messages| messages.get(&key)) {
+                    Some(message) => Ok(message.clone()),
+                    None => encode(&mut this.encode_sizes, &item).inspect(|message| {
+                        cache
+                            .entry(context.clone())
+                            .or_default()
+                            .insert(key, message.clone());
+                    }),
+                }
+            }
+            _ => encode(&mut this.encode_sizes, &item),
+        };
+        let message = message.map_err(|error| {
+            this.metrics.encode_failures += 1;
+            StreamDeckSocketError::BadMessage(error)
+        })?;
+        let len = message.len();
+        match Pin::new(&mut this.inner).start_send(tungstenite::Message::Text(message)) {
+            Ok(()) => {
+                this.metrics.messages_sent += 1;
+                this.metrics.bytes_sent += len as u64;
+            }
+            // sent by the next poll_ready or poll_flush, which counts it
+            Err(tungstenite::Error::SendQueueFull(message)) if this.retry.backoff() => {
+                this.retry.message = Some(message);
+            }
+            Err(error) => return Err(StreamDeckSocketError::from(error)),
+        }
+        Ok(())
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.pin_get_inner()
-            .poll_flush(cx)
-            .map_err(StreamDeckSocketError::WebSocketError)
+        self.get_mut().poll_with_retry(cx, Sink::poll_flush)
     }
 
     fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
-        self.pin_get_inner()
-            .poll_close(cx)
-            .map_err(StreamDeckSocketError::WebSocketError)
+        self.get_mut().poll_with_retry(cx, Sink::poll_close)
     }
 }
 
@@ -177,10 +1041,585 @@ pub enum ConnectError {
     /// The registration information could not be sent.
     #[fail(display = "Send error")]
     SendError(#[fail(cause)] tungstenite::error::Error),
+    /// The registration information could not be encoded.
+    #[fail(display = "Bad registration")]
+    BadRegistration(#[fail(cause)] serde_json::Error),
+    /// The connection was not established in time.
+    #[fail(display = "Connection timed out")]
+    Timeout,
 }
 
+/// The message sent to register with the Stream Deck software.
+///
+/// `E` represents additional fields to include in the message.
 #[derive(Serialize)]
-struct Registration<'a> {
-    event: &'a str,
-    uuid: &'a str,
+pub struct Registration<'a, E = ()> {
+    /// The event the plugin should send to register with the Stream Deck software.
+    pub event: &'a str,
+    /// The uuid of the plugin.
+    pub uuid: &'a str,
+    /// Additional fields to include in the message.
+    #[serde(flatten)]
+    pub extra: E,
+}
+
+impl<'a> Registration<'a> {
+    /// Creates the standard registration message.
+    pub fn new(event: &'a str, uuid: &'a str) -> Self {
+        Registration {
+            event,
+            uuid,
+            extra: (),
+        }
+    }
+}
+
+impl<'a, E> Registration<'a, E> {
+    /// Replaces the additional fields to include in the message.
+    pub fn with_extra<X: ser::Serialize>(self, extra: X) -> Registration<'a, X> {
+        Registration {
+            event: self.event,
+            uuid: self.uuid,
+            extra,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        check_strict, encode, ConnectError, EncodeSizes, Registration, StreamDeckSocket,
+        StreamDeckSocketError, WebSocketConfig,
+    };
+    use crate::registration::{RegistrationInfo, RegistrationParams};
+    use crate::version::Version;
+    use crate::MessageOut;
+    use futures::prelude::*;
+    use futures::stream::FusedStream;
+    use serde_json::Value;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::task::{Context, Poll};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn strict() {
+        let key_down = r#"{
+            "action": "com.elgato.example.action1",
+            "event": "keyDown",
+            "context": "opaqueValue",
+            "device": "opaqueValue",
+            "payload": {
+                "settings": { "anything": [1, 2] },
+                "coordinates": { "column": 3, "row": 1 },
+                "state": 0,
+                "userDesiredState": 1,
+                "isInMultiAction": false
+            }
+        }"#;
+        assert!(check_strict(key_down).is_none());
+
+        let changed = key_down.replace("\"state\": 0", "\"state\": 0, \"pressure\": 0.5");
+        match check_strict(&changed) {
+            Some(StreamDeckSocketError::UnknownFields { event, fields }) => {
+                assert_eq!("keyDown", event);
+                assert_eq!(vec!["payload.pressure"], fields);
+            }
+            _ => panic!("expected unknown fields"),
+        }
+
+        let dial_rotate = r#"{"event":"dialRotate","action":"com.example.action","context":"b","device":"c","payload":{"settings":{},"coordinates":{"column":0,"row":0},"ticks":1,"pressed":false}}"#;
+        assert!(check_strict(dial_rotate).is_none());
+        let changed = dial_rotate.replace("\"ticks\"", "\"acceleration\":2,\"ticks\"");
+        match check_strict(&changed) {
+            Some(StreamDeckSocketError::UnknownFields { event, fields }) => {
+                assert_eq!("dialRotate", event);
+                assert_eq!(vec!["payload.acceleration"], fields);
+            }
+            _ => panic!("expected unknown fields"),
+        }
+    }
+
+    #[test]
+    fn encode_sizes() {
+        let mut sizes = EncodeSizes::new();
+        let message: MessageOut<Value, Value, Value> = MessageOut::show_ok("abc");
+        let json = encode(&mut sizes, &message).unwrap();
+        assert_eq!(r#"{"event":"showOk","context":"abc"}"#, json);
+        assert_eq!(Some(&json.len()), sizes.get("showOk"));
+        let again = encode(&mut sizes, &message).unwrap();
+        assert_eq!(json, again);
+        assert_eq!(again.len(), again.capacity());
+    }
+
+    #[test]
+    fn keep_alive() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let (done, stop) = futures::channel::oneshot::channel::<()>();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                stream.next().await.unwrap().unwrap();
+                // the first ping is answered, then the server stops reading
+                stream.next().await.unwrap().unwrap();
+                stream.flush().await.unwrap();
+                let _ = stop.await;
+            });
+
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value>::connect(
+                port,
+                "registerPlugin".to_string(),
+                "uuid".to_string(),
+            )
+            .await
+            .unwrap()
+            .with_keep_alive(Duration::from_millis(10), Duration::from_millis(50));
+            assert!(matches!(
+                socket.next().await,
+                Some(Err(StreamDeckSocketError::Unresponsive))
+            ));
+            assert!(socket.next().await.is_none());
+            assert_eq!(2, socket.metrics().pings_sent());
+            assert_eq!(1, socket.metrics().pongs_received());
+            drop(done);
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn connect_timeout() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            // the connection is accepted by the operating system, but the handshake never happens
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let result = StreamDeckSocket::<Value, Value, Value, Value>::connect_timeout(
+                port,
+                "registerPlugin".to_string(),
+                "uuid".to_string(),
+                Duration::from_millis(50),
+            )
+            .await;
+            assert!(matches!(result, Err(ConnectError::Timeout)));
+        });
+    }
+
+    #[test]
+    fn config() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                stream.next().await.unwrap().unwrap();
+                let title = "x".repeat(1024);
+                let message = format!(
+                    r#"{{"event":"titleParametersDidChange","title":"{}"}}"#,
+                    title
+                );
+                let _ = stream.send(tungstenite::Message::Text(message)).await;
+            });
+
+            let config = WebSocketConfig {
+                max_message_size: Some(512),
+                ..WebSocketConfig::default()
+            };
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_with_config(
+                port,
+                Registration::new("registerPlugin", "uuid"),
+                Some(config),
+            )
+            .await
+            .unwrap();
+            match socket.next().await {
+                Some(Err(StreamDeckSocketError::WebSocketError(error))) => {
+                    assert!(matches!(*error, tungstenite::Error::Capacity(_)))
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            server.await.unwrap();
+        });
+    }
+
+    #[test]
+    fn fused() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            // the socket is lent to the combinator, so it is still available afterwards
+            stream::iter(vec![Ok(MessageOut::show_ok("context"))])
+                .forward(&mut socket)
+                .await
+                .unwrap();
+            assert!(stream_deck.next().await.unwrap().is_ok());
+            // a client waits for the server to end the connection after closing
+            stream_deck.close(None).await.unwrap();
+            drop(stream_deck);
+
+            let mut never = future::pending::<()>();
+            let mut received = 0;
+            loop {
+                futures::select! {
+                    message = socket.next() => match message {
+                        Some(_) => received += 1,
+                        None => break,
+                    },
+                    () = never => unreachable!(),
+                }
+            }
+            assert_eq!(0, received);
+            assert!(socket.is_terminated());
+            assert!(socket.next().await.is_none());
+        });
+    }
+
+    #[test]
+    fn send_batch() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            let contexts = ["a", "b", "c"];
+            socket
+                .send_batch(contexts.iter().map(|&context| MessageOut::show_ok(context)))
+                .await
+                .unwrap();
+            for context in &contexts {
+                let message = stream_deck.next().await.unwrap().unwrap();
+                assert_eq!(
+                    format!(r#"{{"event":"showOk","context":"{}"}}"#, context),
+                    message.to_text().unwrap()
+                );
+            }
+        });
+    }
+
+    #[test]
+    fn counters() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            socket.send(MessageOut::show_ok("a")).await.unwrap();
+            let sent = stream_deck.next().await.unwrap().unwrap();
+            let received = [
+                r#"{"event":"systemDidWakeUp"}"#,
+                r#"{"event":"systemDidWakeUp"}"#,
+                r#"{"event":"keyDown"}"#,
+            ];
+            for text in &received {
+                stream_deck
+                    .send(tungstenite::Message::Text(text.to_string()))
+                    .await
+                    .unwrap();
+            }
+            for _ in &received {
+                socket.next().await.unwrap().ok();
+            }
+
+            let metrics = socket.metrics();
+            assert_eq!(1, metrics.messages_sent());
+            assert_eq!(sent.len() as u64, metrics.bytes_sent());
+            assert_eq!(2, metrics.messages_received());
+            assert_eq!(2, metrics.events_received("systemDidWakeUp"));
+            assert_eq!(0, metrics.events_received("keyUp"));
+            assert_eq!(1, metrics.decode_failures());
+            assert_eq!(
+                received.iter().map(|text| text.len() as u64).sum::<u64>(),
+                metrics.bytes_received()
+            );
+        });
+    }
+
+    #[test]
+    fn version_warning() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                stream.next().await.unwrap().unwrap();
+                let warning = stream.next().await.unwrap().unwrap();
+                let warning: Value = serde_json::from_str(warning.to_text().unwrap()).unwrap();
+                assert_eq!("logMessage", warning["event"]);
+                let message = warning["payload"]["message"].as_str().unwrap();
+                assert!(message.contains("99.0"), "{}", message);
+            });
+
+            let info: RegistrationInfo = serde_json::from_str(
+                r#"{
+                    "application": {"language": "en", "platform": "mac", "version": "99.0"},
+                    "plugin": {"uuid": "com.example.plugin", "version": "1.0"},
+                    "devicePixelRatio": 2,
+                    "devices": []
+                }"#,
+            )
+            .unwrap();
+            let params = RegistrationParams {
+                port,
+                uuid: "uuid".to_string(),
+                event: "registerPlugin".to_string(),
+                info,
+            };
+            let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect_with(params)
+                .await
+                .unwrap();
+            server.await.unwrap();
+
+            // adapting messages to the version is opt-in
+            assert_eq!(None, socket.target_version());
+            let socket = socket.with_registered_version();
+            assert_eq!(Some(Version::new(99, 0)), socket.target_version());
+        });
+    }
+
+    #[test]
+    fn message_cache() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .with_message_cache();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            socket.send(MessageOut::show_ok("a")).await.unwrap();
+            socket.send(MessageOut::set_state("a", 1)).await.unwrap();
+            socket.send(MessageOut::set_state("a", 0)).await.unwrap();
+            socket.send(MessageOut::show_ok("a")).await.unwrap();
+            socket.send(MessageOut::show_ok("b")).await.unwrap();
+            let mut sent = Vec::new();
+            for _ in 0..5 {
+                let message = stream_deck.next().await.unwrap().unwrap();
+                sent.push(serde_json::from_str::<Value>(message.to_text().unwrap()).unwrap());
+            }
+            assert_eq!(sent[0], sent[3]);
+            assert_eq!(1, sent[1]["payload"]["state"]);
+            assert_eq!(0, sent[2]["payload"]["state"]);
+            assert_eq!("b", sent[4]["context"]);
+
+            let cache = socket.cache.as_ref().unwrap();
+            assert_eq!(3, cache["a"].len());
+            assert_eq!(1, cache["b"].len());
+
+            stream_deck
+                .send(tungstenite::Message::Text(
+                    r#"{
+                        "action": "com.example.plugin.action",
+                        "event": "willDisappear",
+                        "context": "a",
+                        "device": "device",
+                        "payload": {
+                            "settings": {},
+                            "coordinates": {"column": 0, "row": 0},
+                            "isInMultiAction": false
+                        }
+                    }"#
+                    .to_string(),
+                ))
+                .await
+                .unwrap();
+            socket.next().await.unwrap().unwrap();
+            let cache = socket.cache.as_ref().unwrap();
+            assert!(!cache.contains_key("a"));
+            assert!(cache.contains_key("b"));
+        });
+    }
+
+    /// A transport that reports a full send queue a number of times before accepting messages.
+    struct FullQueue {
+        failures: Arc<AtomicUsize>,
+        sent: Arc<Mutex<Vec<tungstenite::Message>>>,
+    }
+
+    impl Stream for FullQueue {
+        type Item = Result<tungstenite::Message, tungstenite::Error>;
+
+        fn poll_next(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Option<Self::Item>> {
+            Poll::Pending
+        }
+    }
+
+    impl Sink<tungstenite::Message> for FullQueue {
+        type Error = tungstenite::Error;
+
+        fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn start_send(self: Pin<&mut Self>, item: tungstenite::Message) -> Result<(), Self::Error> {
+            let failures = self.failures.load(Ordering::SeqCst);
+            if failures > 0 {
+                self.failures.store(failures - 1, Ordering::SeqCst);
+                return Err(tungstenite::Error::SendQueueFull(item));
+            }
+            self.sent.lock().unwrap().push(item);
+            Ok(())
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn send_retry() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .start_paused(true)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let failures = Arc::new(AtomicUsize::new(0));
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            let transport = FullQueue {
+                failures: failures.clone(),
+                sent: sent.clone(),
+            };
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            assert_eq!(1, sent.lock().unwrap().len());
+
+            failures.store(3, Ordering::SeqCst);
+            socket.feed(MessageOut::show_ok("a")).await.unwrap();
+            // the message is only counted once the web socket accepts it
+            assert_eq!(0, socket.metrics().messages_sent());
+            assert_eq!(0, socket.metrics().bytes_sent());
+            socket.flush().await.unwrap();
+            assert_eq!(0, failures.load(Ordering::SeqCst));
+            let message = sent.lock().unwrap().pop().unwrap();
+            assert!(message.to_text().unwrap().contains("showOk"));
+            assert_eq!(1, socket.metrics().messages_sent());
+            assert_eq!(message.len() as u64, socket.metrics().bytes_sent());
+
+            failures.store(usize::MAX, Ordering::SeqCst);
+            match socket.send(MessageOut::show_ok("a")).await {
+                Err(StreamDeckSocketError::WebSocketError(error)) => {
+                    assert!(matches!(*error, tungstenite::Error::SendQueueFull(_)))
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            assert_eq!(1, sent.lock().unwrap().len());
+            assert_eq!(1, socket.metrics().messages_sent());
+        });
+    }
+
+    #[test]
+    fn ping_rtt() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+            assert_eq!(None, socket.metrics().ping_rtt());
+
+            socket.send_ping().await.unwrap();
+            assert!(stream_deck.next().await.unwrap().unwrap().is_ping());
+            // the answer to the ping is sent with the next frame, and a pong that does not answer
+            // the ping is ignored
+            stream_deck
+                .send(tungstenite::Message::Pong(vec![1, 2, 3]))
+                .await
+                .unwrap();
+            stream_deck
+                .send(tungstenite::Message::Text(
+                    r#"{"event":"systemDidWakeUp"}"#.to_string(),
+                ))
+                .await
+                .unwrap();
+            socket.next().await.unwrap().unwrap();
+
+            let metrics = socket.metrics();
+            assert_eq!(1, metrics.pings_sent());
+            assert_eq!(1, metrics.pongs_received());
+            assert!(metrics.ping_rtt().is_some());
+        });
+    }
 }
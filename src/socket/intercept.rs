@@ -0,0 +1,120 @@
+use super::{StreamDeckSocket, Transport};
+use crate::{Message, MessageOut};
+
+/// Observes or changes every message passing through a
+/// [`StreamDeckSocket`](struct.StreamDeckSocket.html).
+///
+/// Interceptors are added with
+/// [`with_interceptor`](struct.StreamDeckSocket.html#method.with_interceptor), and are useful for
+/// concerns that apply to all messages, such as logging, counting, or removing private data from
+/// payloads. Both methods do nothing by default.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_json::Value;
+/// # use streamdeck_rs::socket::Interceptor;
+/// # use streamdeck_rs::{Message, MessageOut};
+/// #[derive(Default)]
+/// struct CountKeyPresses(u64);
+///
+/// impl Interceptor<Value, Value, Value, Value> for CountKeyPresses {
+///     fn received(&mut self, message: &mut Message<Value, Value, Value>) {
+///         if let Message::KeyDown { .. } = message {
+///             self.0 += 1;
+///         }
+///     }
+/// }
+/// ```
+pub trait Interceptor<G, S, MI, MO> {
+    /// Called for each message received, after it has been decoded.
+    fn received(&mut self, message: &mut Message<G, S, MI>) {
+        let _ = message;
+    }
+
+    /// Called for each message before it is sent.
+    fn sending(&mut self, message: &mut MessageOut<G, S, MO>) {
+        let _ = message;
+    }
+}
+
+/// The interceptors of a socket, in the order they were added.
+pub(super) type Interceptors<G, S, MI, MO> = Vec<Box<dyn Interceptor<G, S, MI, MO> + Send>>;
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Adds an interceptor, which is called for every message received and sent.
+    ///
+    /// Interceptors are called in the order they were added.
+    pub fn with_interceptor<I>(mut self, interceptor: I) -> Self
+    where
+        I: Interceptor<G, S, MI, MO> + Send + 'static,
+    {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Interceptor;
+    use crate::socket::Registration;
+    use crate::{Message, MessageOut, StreamDeckSocket};
+    use futures::prelude::*;
+    use serde_json::{json, Value};
+    use std::sync::{Arc, Mutex};
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    struct Redact(Arc<Mutex<Vec<&'static str>>>);
+
+    impl Interceptor<Value, Value, Value, Value> for Redact {
+        fn received(&mut self, message: &mut Message<Value, Value, Value>) {
+            if let Message::DidReceiveGlobalSettings { payload } = message {
+                payload.settings["token"] = json!("***");
+            }
+        }
+
+        fn sending(&mut self, message: &mut MessageOut<Value, Value, Value>) {
+            self.0.lock().unwrap().push(message.event());
+        }
+    }
+
+    #[test]
+    fn intercept() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let sent = Arc::new(Mutex::new(Vec::new()));
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .with_interceptor(Redact(sent.clone()));
+
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+            stream_deck
+                .send(tungstenite::Message::Text(
+                    r#"{"event":"didReceiveGlobalSettings","payload":{"settings":{"token":"secret"}}}"#
+                        .to_string(),
+                ))
+                .await
+                .unwrap();
+            match socket.next().await {
+                Some(Ok(Message::DidReceiveGlobalSettings { payload })) => {
+                    assert_eq!(json!({"token": "***"}), payload.settings)
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+
+            socket.send(MessageOut::show_ok("context")).await.unwrap();
+            assert_eq!(vec!["showOk"], *sent.lock().unwrap());
+        });
+    }
+}
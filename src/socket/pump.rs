@@ -0,0 +1,231 @@
+use super::{StreamDeckSocket, StreamDeckSocketError};
+use crate::{Message, MessageOut};
+use failure::Fail;
+use futures::channel::mpsc;
+use futures::prelude::*;
+use serde::{de, ser};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Sends messages through a socket driven by a background task, created by
+/// [`StreamDeckSocket::spawn`](struct.StreamDeckSocket.html#method.spawn).
+///
+/// The sender can be cloned cheaply, and clones send through the same socket.
+pub struct CommandSender<G, S, MO> {
+    sender: mpsc::UnboundedSender<MessageOut<G, S, MO>>,
+}
+
+/// The messages received by a socket driven by a background task, created by
+/// [`StreamDeckSocket::spawn`](struct.StreamDeckSocket.html#method.spawn).
+///
+/// Errors sending messages are reported here as well. The stream ends when the connection is
+/// closed.
+pub struct EventStream<G, S, MI> {
+    receiver: mpsc::UnboundedReceiver<Result<Message<G, S, MI>, StreamDeckSocketError>>,
+}
+
+/// The background task has stopped because the connection was closed.
+#[derive(Clone, Copy, Debug, Fail)]
+#[fail(display = "the socket is disconnected")]
+pub struct Disconnected;
+
+impl<G, S, MI, MO> StreamDeckSocket<G, S, MI, MO>
+where
+    G: ser::Serialize + de::DeserializeOwned + Send + 'static,
+    S: ser::Serialize + de::DeserializeOwned + Send + 'static,
+    MI: de::DeserializeOwned + Send + 'static,
+    MO: ser::Serialize + Send + 'static,
+{
+    /// Drives the socket on a background task, returning a sender for messages and the stream of
+    /// received messages.
+    ///
+    /// The sender can be shared between the handlers, timers, and logging of a plugin without
+    /// locking. Messages that are queued together are sent with a single flush.
+    ///
+    /// The task runs until the connection is closed. This must be called within a Tokio runtime.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use futures::prelude::*;
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::{MessageOut, StreamDeckSocket};
+    /// # async fn run(socket: StreamDeckSocket<Value, Value, Value, Value>) {
+    /// let (sender, mut events) = socket.spawn();
+    /// while let Some(message) = events.next().await {
+    ///     sender.send(MessageOut::log_message("received a message")).unwrap();
+    /// }
+    /// # }
+    /// ```
+    pub fn spawn(self) -> (CommandSender<G, S, MO>, EventStream<G, S, MI>) {
+        let (command_sender, commands) = mpsc::unbounded();
+        let (event_sender, events) = mpsc::unbounded();
+        tokio::spawn(pump(self, commands, event_sender));
+        (
+            CommandSender {
+                sender: command_sender,
+            },
+            EventStream { receiver: events },
+        )
+    }
+}
+
+/// Moves messages between the socket and the channels until the connection is closed.
+async fn pump<G, S, MI, MO>(
+    mut socket: StreamDeckSocket<G, S, MI, MO>,
+    mut commands: mpsc::UnboundedReceiver<MessageOut<G, S, MO>>,
+    events: mpsc::UnboundedSender<Result<Message<G, S, MI>, StreamDeckSocketError>>,
+) where
+    G: ser::Serialize + de::DeserializeOwned,
+    S: ser::Serialize + de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+    MO: ser::Serialize,
+{
+    enum Next<T, U> {
+        Received(Option<T>),
+        Command(Option<U>),
+    }
+
+    let mut commands_done = false;
+    loop {
+        let next = future::poll_fn(|cx| {
+            if let Poll::Ready(item) = socket.poll_next_unpin(cx) {
+                return Poll::Ready(Next::Received(item));
+            }
+            if !commands_done {
+                if let Poll::Ready(command) = commands.poll_next_unpin(cx) {
+                    return Poll::Ready(Next::Command(command));
+                }
+            }
+            Poll::Pending
+        })
+        .await;
+
+        match next {
+            // nobody may be listening, but the messages still need to be read
+            Next::Received(Some(item)) => {
+                let _ = events.unbounded_send(item);
+            }
+            Next::Received(None) => break,
+            Next::Command(Some(message)) => {
+                let mut result = socket.feed(message).await;
+                while result.is_ok() {
+                    match commands.try_recv() {
+                        Ok(message) => result = socket.feed(message).await,
+                        Err(_) => break,
+                    }
+                }
+                if let Err(error) = result.and(socket.flush().await) {
+                    let _ = events.unbounded_send(Err(error));
+                }
+            }
+            Next::Command(None) => commands_done = true,
+        }
+    }
+}
+
+impl<G, S, MO> Clone for CommandSender<G, S, MO> {
+    fn clone(&self) -> Self {
+        CommandSender {
+            sender: self.sender.clone(),
+        }
+    }
+}
+
+impl<G, S, MO> CommandSender<G, S, MO> {
+    /// Queues a message to be sent.
+    pub fn send(&self, message: MessageOut<G, S, MO>) -> Result<(), Disconnected> {
+        self.sender
+            .unbounded_send(message)
+            .map_err(|_| Disconnected)
+    }
+
+    /// Checks whether the background task has stopped.
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+impl<G, S, MO> Sink<MessageOut<G, S, MO>> for CommandSender<G, S, MO> {
+    type Error = Disconnected;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.sender.poll_ready(cx).map_err(|_| Disconnected)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        CommandSender::send(&self, item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<G, S, MI> Stream for EventStream<G, S, MI> {
+    type Item = Result<Message<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Message, MessageOut, StreamDeckSocket};
+    use futures::prelude::*;
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn spawn() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                let registration = stream.next().await.unwrap().unwrap();
+                assert!(registration.to_text().unwrap().contains("registerPlugin"));
+                stream
+                    .send(tungstenite::Message::Text(
+                        r#"{"event":"systemDidWakeUp"}"#.to_string(),
+                    ))
+                    .await
+                    .unwrap();
+                let command = stream.next().await.unwrap().unwrap();
+                assert!(command.to_text().unwrap().contains("showOk"));
+                stream.close(None).await.unwrap();
+            });
+
+            let socket = StreamDeckSocket::<Value, Value, Value, Value>::connect(
+                port,
+                "registerPlugin".to_string(),
+                "uuid".to_string(),
+            )
+            .await
+            .unwrap();
+            let (sender, mut events) = socket.spawn();
+            assert!(matches!(
+                events.next().await,
+                Some(Ok(Message::SystemDidWakeUp))
+            ));
+            sender
+                .clone()
+                .send(MessageOut::ShowOk {
+                    context: "context".into(),
+                })
+                .unwrap();
+            server.await.unwrap();
+            assert!(events.next().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,584 @@
+use super::{Inner, StreamDeckSocket, StreamDeckSocketError, Transport};
+use crate::{Message, MessageOut, Target};
+use futures::prelude::*;
+use serde::{de, ser};
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+/// How soon a message queued by a [`QueuedSocket`](struct.QueuedSocket.html) is sent, compared
+/// to the other queued messages.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Small messages the user is waiting to see, such as alerts and state changes.
+    High,
+    /// Messages that are neither urgent nor large.
+    Normal,
+    /// Large messages, such as images, which can wait for the others.
+    Low,
+}
+
+impl Priority {
+    /// The number of priorities.
+    const COUNT: usize = 3;
+
+    /// Gets the priority of a message.
+    ///
+    /// [ShowAlert](../enum.MessageOut.html#variant.ShowAlert),
+    /// [ShowOk](../enum.MessageOut.html#variant.ShowOk),
+    /// [SetState](../enum.MessageOut.html#variant.SetState), and
+    /// [LogMessage](../enum.MessageOut.html#variant.LogMessage) have a high priority, and
+    /// [SetImage](../enum.MessageOut.html#variant.SetImage) and
+    /// [SetFeedback](../enum.MessageOut.html#variant.SetFeedback) have a low priority.
+    pub fn of<G, S, M>(message: &MessageOut<G, S, M>) -> Self {
+        match message {
+            MessageOut::ShowAlert { .. }
+            | MessageOut::ShowOk { .. }
+            | MessageOut::SetState { .. }
+            | MessageOut::LogMessage { .. } => Priority::High,
+            MessageOut::SetImage { .. } | MessageOut::SetFeedback { .. } => Priority::Low,
+            _ => Priority::Normal,
+        }
+    }
+}
+
+/// A [`StreamDeckSocket`](struct.StreamDeckSocket.html) that queues messages and sends them in
+/// order of [`Priority`](enum.Priority.html).
+///
+/// Messages are queued when they are sent and written to the socket when it is flushed, so a
+/// burst of images does not hold up an alert that was sent after them. Messages with the same
+/// priority are sent in the order they were queued, and a message never goes ahead of an earlier
+/// message for the same action instance. The queue is not limited in size.
+///
+/// Images and feedback can also be limited to one update per action in an interval with
+/// [`with_rate_limit`](#method.with_rate_limit), and updates that are replaced before they are
+/// sent can be dropped with [`with_coalescing`](#method.with_coalescing).
+///
+/// Created by [`StreamDeckSocket::queued`](struct.StreamDeckSocket.html#method.queued).
+pub struct QueuedSocket<G, S, MI, MO, T = Inner> {
+    inner: StreamDeckSocket<G, S, MI, MO, T>,
+    queues: [VecDeque<MessageOut<G, S, MO>>; Priority::COUNT],
+    rate_limit: Option<RateLimit<G, S, MO>>,
+    coalesce: bool,
+}
+
+/// Identifies the messages that replace each other.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct UpdateKey {
+    context: crate::Context,
+    event: &'static str,
+    target: Option<Target>,
+    state: Option<u8>,
+}
+
+impl UpdateKey {
+    /// Gets the key of a message that replaces the earlier messages with the same key.
+    fn of<G, S, M>(message: &MessageOut<G, S, M>) -> Option<Self> {
+        let (context, target, state) = match message {
+            MessageOut::SetTitle { context, payload } => {
+                (context, Some(payload.target), payload.state)
+            }
+            MessageOut::SetImage { context, payload } => {
+                (context, Some(payload.target), payload.state)
+            }
+            MessageOut::SetState { context, .. }
+            | MessageOut::SetFeedback { context, .. }
+            | MessageOut::SetFeedbackLayout { context, .. }
+            | MessageOut::SetTriggerDescription { context, .. } => (context, None, None),
+            _ => return None,
+        };
+        Some(UpdateKey {
+            context: context.clone(),
+            event: message.event(),
+            target,
+            state,
+        })
+    }
+}
+
+/// Replaces an update with a later one with the same key.
+///
+/// Feedback only updates the items it includes, so later feedback is merged instead.
+fn supersede<G, S, M>(update: &mut MessageOut<G, S, M>, later: MessageOut<G, S, M>) {
+    match (update, later) {
+        (
+            MessageOut::SetFeedback { payload, .. },
+            MessageOut::SetFeedback { payload: later, .. },
+        ) => payload.merge(later),
+        (update, later) => *update = later,
+    }
+}
+
+/// Settings and state for [`with_rate_limit`](struct.QueuedSocket.html#method.with_rate_limit).
+struct RateLimit<G, S, MO> {
+    interval: Duration,
+    last_sent: HashMap<UpdateKey, Instant>,
+    held: HashMap<UpdateKey, MessageOut<G, S, MO>>,
+    timer: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl<G, S, MO> RateLimit<G, S, MO> {
+    /// When the next update for the key may be sent, if it has been sent before.
+    fn due(&self, key: &UpdateKey) -> Option<Instant> {
+        self.last_sent.get(key).map(|sent| *sent + self.interval)
+    }
+
+    /// Forgets when updates were sent if the next update would be allowed anyway.
+    fn prune(&mut self, now: Instant) {
+        let interval = self.interval;
+        self.last_sent.retain(|_, sent| *sent + interval > now);
+    }
+
+    /// Forgets the updates for an action instance that has disappeared.
+    fn forget(&mut self, context: &crate::Context) {
+        self.last_sent.retain(|key, _| key.context != *context);
+        self.held.retain(|key, _| key.context != *context);
+        self.schedule();
+    }
+
+    /// Sets the timer for the next held message to become due.
+    fn schedule(&mut self) {
+        self.timer = self
+            .held
+            .keys()
+            .filter_map(|key| self.due(key))
+            .min()
+            .map(|due| Box::pin(tokio::time::sleep_until(due.into())));
+    }
+}
+
+/// Gets the key of a message that is subject to the rate limit.
+fn rate_limited<G, S, M>(message: &MessageOut<G, S, M>) -> Option<UpdateKey> {
+    match message {
+        MessageOut::SetImage { .. } | MessageOut::SetFeedback { .. } => UpdateKey::of(message),
+        _ => None,
+    }
+}
+
+// Queued messages are only moved, and are never pinned.
+impl<G, S, MI, MO, T: Unpin> Unpin for QueuedSocket<G, S, MI, MO, T> {}
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Wraps the socket so that it sends messages in order of priority.
+    pub fn queued(self) -> QueuedSocket<G, S, MI, MO, T> {
+        QueuedSocket {
+            inner: self,
+            queues: Default::default(),
+            rate_limit: None,
+            coalesce: false,
+        }
+    }
+}
+
+impl<G, S, MI, MO, T: Transport> QueuedSocket<G, S, MI, MO, T> {
+    /// Gets the underlying socket.
+    pub fn get_ref(&self) -> &StreamDeckSocket<G, S, MI, MO, T> {
+        &self.inner
+    }
+
+    /// Gets the underlying socket.
+    pub fn get_mut(&mut self) -> &mut StreamDeckSocket<G, S, MI, MO, T> {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying socket, discarding any queued messages.
+    pub fn into_inner(self) -> StreamDeckSocket<G, S, MI, MO, T> {
+        self.inner
+    }
+
+    /// Limits [SetImage](../enum.MessageOut.html#variant.SetImage) and
+    /// [SetFeedback](../enum.MessageOut.html#variant.SetFeedback) messages to one of each for an
+    /// action in every `interval`.
+    ///
+    /// The Stream Deck software can fall behind when images are sent faster than it can show them.
+    /// An update that is sent too soon is held until the interval has passed, and is dropped if
+    /// another update for the same action replaces it in the meantime. Held updates are sent while
+    /// the socket is being polled for messages or flushed. Updates held for an action are dropped
+    /// when a [WillDisappear](../enum.Message.html#variant.WillDisappear) message is received for
+    /// it.
+    pub fn with_rate_limit(mut self, interval: Duration) -> Self {
+        self.rate_limit = Some(RateLimit {
+            interval,
+            last_sent: HashMap::new(),
+            held: HashMap::new(),
+            timer: None,
+        });
+        self
+    }
+
+    /// Drops updates that are replaced before they are sent.
+    ///
+    /// When an update is queued while an earlier update of the same kind for the same action is
+    /// still waiting, such as a second [SetTitle](../enum.MessageOut.html#variant.SetTitle), the
+    /// later update takes the place of the earlier one in the queue. Titles and images are only
+    /// replaced by updates for the same state and target, and
+    /// [SetFeedback](../enum.MessageOut.html#variant.SetFeedback) messages are combined, because
+    /// each only updates the items it includes.
+    pub fn with_coalescing(mut self) -> Self {
+        self.coalesce = true;
+        self
+    }
+
+    /// Queues a message, replacing an earlier update if coalescing.
+    ///
+    /// A message waits behind the earlier messages for the same action instance, even if they
+    /// have a lower priority.
+    fn push(&mut self, message: MessageOut<G, S, MO>) {
+        if self.coalesce {
+            if let Some(key) = UpdateKey::of(&message) {
+                let earlier = self
+                    .queues
+                    .iter_mut()
+                    .flatten()
+                    .find(|queued| UpdateKey::of(queued).as_ref() == Some(&key));
+                if let Some(earlier) = earlier {
+                    supersede(earlier, message);
+                    return;
+                }
+            }
+        }
+        let context = message.context();
+        let waiting = self
+            .queues
+            .iter()
+            .rposition(|queue| queue.iter().any(|queued| queued.context() == context));
+        let priority = waiting
+            .map_or(0, |waiting| waiting)
+            .max(Priority::of(&message) as usize);
+        self.queues[priority].push_back(message);
+    }
+
+    /// Gets the number of messages waiting to be sent, including updates held by the rate limit.
+    pub fn queued_len(&self) -> usize {
+        let held = self.rate_limit.as_ref().map_or(0, |limit| limit.held.len());
+        self.queues.iter().map(VecDeque::len).sum::<usize>() + held
+    }
+
+    /// Takes the next message that can be sent now, holding back updates that are too soon.
+    fn pop(&mut self) -> Option<MessageOut<G, S, MO>> {
+        loop {
+            let message = self.queues.iter_mut().find_map(VecDeque::pop_front)?;
+            let (limit, key) = match (&mut self.rate_limit, rate_limited(&message)) {
+                (Some(limit), Some(key)) => (limit, key),
+                _ => return Some(message),
+            };
+            let now = Instant::now();
+            limit.prune(now);
+            let message = match limit.held.remove(&key) {
+                Some(mut held) => {
+                    supersede(&mut held, message);
+                    held
+                }
+                None => message,
+            };
+            if limit.due(&key).is_some_and(|due| due > now) {
+                limit.held.insert(key, message);
+                limit.schedule();
+                continue;
+            }
+            limit.schedule();
+            limit.last_sent.insert(key, now);
+            return Some(message);
+        }
+    }
+
+    /// Queues the held updates that have become due, returning whether there were any.
+    fn poll_release(&mut self, cx: &mut Context) -> bool {
+        let limit = match &mut self.rate_limit {
+            Some(limit) => limit,
+            None => return false,
+        };
+        let fired = match &mut limit.timer {
+            Some(timer) => timer.as_mut().poll(cx).is_ready(),
+            None => false,
+        };
+        if !fired {
+            return false;
+        }
+        let now = Instant::now();
+        let due: Vec<UpdateKey> = limit
+            .held
+            .keys()
+            .filter(|key| limit.due(key).is_some_and(|due| due <= now))
+            .cloned()
+            .collect();
+        let released: Vec<_> = due
+            .iter()
+            .filter_map(|key| limit.held.remove(key))
+            .collect();
+        limit.schedule();
+        for message in released {
+            self.push(message);
+        }
+        // the new timer must be polled to be woken for it
+        self.poll_release(cx);
+        true
+    }
+
+    /// Records the number of waiting messages in the metrics of the socket.
+    fn record_depth(&mut self) {
+        self.inner.metrics.queue_depth = self.queued_len();
+    }
+
+    fn has_ready(&self) -> bool {
+        self.queues.iter().any(|queue| !queue.is_empty())
+    }
+}
+
+impl<G, S, MI, MO, T> Stream for QueuedSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: ser::Serialize + de::DeserializeOwned,
+    S: ser::Serialize + de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+    MO: ser::Serialize,
+{
+    type Item = Result<Message<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.poll_release(cx) {
+            // errors sending held updates have nowhere else to go
+            if let Poll::Ready(Err(error)) = this.poll_flush_unpin(cx) {
+                return Poll::Ready(Some(Err(error)));
+            }
+        }
+        let message = futures::ready!(this.inner.poll_next_unpin(cx));
+        if let (Some(limit), Some(Ok(Message::WillDisappear { context, .. }))) =
+            (&mut this.rate_limit, &message)
+        {
+            limit.forget(context);
+            this.record_depth();
+        }
+        Poll::Ready(message)
+    }
+}
+
+impl<G, S, MI, MO, T> Sink<MessageOut<G, S, MO>> for QueuedSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, _cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.push(item);
+        this.record_depth();
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        loop {
+            while this.has_ready() {
+                futures::ready!(this.inner.poll_ready_unpin(cx))?;
+                if let Some(message) = this.pop() {
+                    this.record_depth();
+                    this.inner.start_send_unpin(message)?;
+                }
+            }
+            if !this.poll_release(cx) {
+                break;
+            }
+        }
+        this.record_depth();
+        this.inner.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        futures::ready!(self.as_mut().poll_flush(cx))?;
+        self.get_mut().inner.poll_close_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::socket::Registration;
+    use crate::{MessageOut, StreamDeckSocket};
+    use futures::prelude::*;
+    use serde_json::{json, Value};
+    use std::time::Duration;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn priority() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .queued();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            socket
+                .feed(MessageOut::set_image("a").build())
+                .await
+                .unwrap();
+            socket.feed(MessageOut::get_settings("b")).await.unwrap();
+            socket.feed(MessageOut::show_alert("c")).await.unwrap();
+            socket.feed(MessageOut::set_state("a", 1)).await.unwrap();
+            socket.feed(MessageOut::show_ok("d")).await.unwrap();
+            assert_eq!(5, socket.queued_len());
+            assert_eq!(5, socket.get_ref().metrics().queue_depth());
+            socket.flush().await.unwrap();
+            assert_eq!(0, socket.queued_len());
+            assert_eq!(0, socket.get_ref().metrics().queue_depth());
+
+            let mut events = Vec::new();
+            for _ in 0..5 {
+                let message = stream_deck.next().await.unwrap().unwrap();
+                let message: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+                events.push(format!(
+                    "{} {}",
+                    message["event"].as_str().unwrap(),
+                    message["context"].as_str().unwrap()
+                ));
+            }
+            // the state of a waits for its image, but not for the other actions
+            assert_eq!(
+                vec![
+                    "showAlert c",
+                    "showOk d",
+                    "getSettings b",
+                    "setImage a",
+                    "setState a"
+                ],
+                events
+            );
+        });
+    }
+
+    #[test]
+    fn rate_limit() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .queued()
+            .with_rate_limit(Duration::from_millis(50));
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            let image = |context, image| MessageOut::set_image(context).image(image).build();
+            socket.send(image("a", "1")).await.unwrap();
+            socket.send(image("a", "2")).await.unwrap();
+            socket.send(image("a", "3")).await.unwrap();
+            socket.send(image("b", "1")).await.unwrap();
+            assert_eq!(1, socket.queued_len());
+
+            tokio::time::sleep(Duration::from_millis(60)).await;
+            socket.flush().await.unwrap();
+            assert_eq!(0, socket.queued_len());
+
+            let mut images = Vec::new();
+            for _ in 0..3 {
+                let message = stream_deck.next().await.unwrap().unwrap();
+                let message: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+                images.push(format!(
+                    "{}{}",
+                    message["context"].as_str().unwrap(),
+                    message["payload"]["image"].as_str().unwrap()
+                ));
+            }
+            assert_eq!(vec!["a1", "b1", "a3"], images);
+
+            // only updates that could still be held back are remembered
+            let limit = socket.rate_limit.as_ref().unwrap();
+            assert_eq!(1, limit.last_sent.len());
+            let disappear = r#"{"event":"willDisappear","action":"com.example.action","context":"a","device":"d","payload":{"settings":{}}}"#;
+            stream_deck
+                .send(tungstenite::Message::Text(disappear.to_string()))
+                .await
+                .unwrap();
+            socket.next().await.unwrap().unwrap();
+            assert!(socket.rate_limit.as_ref().unwrap().last_sent.is_empty());
+        });
+    }
+
+    #[test]
+    fn coalesce() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .queued()
+            .with_coalescing();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            let title = |text| MessageOut::set_title("a").text(text).build();
+            let feedback = |json| MessageOut::SetFeedback {
+                context: "a".into(),
+                payload: serde_json::from_str(json).unwrap(),
+            };
+            socket.feed(title("1")).await.unwrap();
+            socket
+                .feed(feedback(
+                    r##"{"title":"CPU","value":{"value":"10%","color":"#fff"}}"##,
+                ))
+                .await
+                .unwrap();
+            socket
+                .feed(MessageOut::set_title("a").text("x").state(1).build())
+                .await
+                .unwrap();
+            socket.feed(title("2")).await.unwrap();
+            socket
+                .feed(feedback(r#"{"value":{"value":"20%"}}"#))
+                .await
+                .unwrap();
+            assert_eq!(3, socket.queued_len());
+            socket.flush().await.unwrap();
+
+            let mut messages = Vec::new();
+            for _ in 0..3 {
+                let message = stream_deck.next().await.unwrap().unwrap();
+                let message: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+                messages.push(message["payload"].clone());
+            }
+            assert_eq!("2", messages[0]["title"]);
+            assert_eq!(
+                json!({"title": "CPU", "value": {"value": "20%", "color": "#fff"}}),
+                messages[1]
+            );
+            assert_eq!("x", messages[2]["title"]);
+        });
+    }
+}
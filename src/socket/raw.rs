@@ -0,0 +1,197 @@
+use super::{Inner, StreamDeckSocket, StreamDeckSocketError, Transport};
+use crate::{Message, MessageOut};
+use futures::prelude::*;
+use serde::{de, ser};
+use std::borrow::Cow;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// A frame received by a [`RawSocket`](struct.RawSocket.html).
+#[derive(Debug)]
+pub enum RawMessage<G, S, MI> {
+    /// A text frame, which carries the messages of the Stream Deck protocol.
+    Text {
+        /// The text of the frame, before it was decoded.
+        text: String,
+        /// The message decoded from the text, or the reason it could not be decoded.
+        message: Result<Message<G, S, MI>, StreamDeckSocketError>,
+    },
+    /// Any other frame, such as a close frame or a pong.
+    Frame(tungstenite::Message),
+}
+
+/// A [`StreamDeckSocket`](struct.StreamDeckSocket.html) that receives every web socket frame,
+/// along with the text of each message.
+///
+/// This is for handling parts of the protocol this crate does not understand, such as close
+/// frames or extensions to the messages. Messages are still decoded, so the devices and other
+/// state of the socket are kept up to date. Frames can be sent as well as messages.
+///
+/// Created by [`StreamDeckSocket::into_raw`](struct.StreamDeckSocket.html#method.into_raw).
+pub struct RawSocket<G, S, MI, MO, T = Inner> {
+    inner: StreamDeckSocket<G, S, MI, MO, T>,
+}
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Wraps the socket so that it receives every frame.
+    pub fn into_raw(self) -> RawSocket<G, S, MI, MO, T> {
+        RawSocket { inner: self }
+    }
+}
+
+impl<G, S, MI, MO, T: Transport> RawSocket<G, S, MI, MO, T> {
+    /// Gets the underlying socket.
+    pub fn get_ref(&self) -> &StreamDeckSocket<G, S, MI, MO, T> {
+        &self.inner
+    }
+
+    /// Gets the underlying socket.
+    pub fn get_mut(&mut self) -> &mut StreamDeckSocket<G, S, MI, MO, T> {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying socket.
+    pub fn into_inner(self) -> StreamDeckSocket<G, S, MI, MO, T> {
+        self.inner
+    }
+}
+
+impl<G, S, MI, MO, T> Stream for RawSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+{
+    type Item = Result<RawMessage<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let inner = &mut self.get_mut().inner;
+        let frame = match futures::ready!(inner.poll_frame(cx)) {
+            Some(Ok(tungstenite::Message::Text(text))) => {
+                let message = inner.decode(Cow::Borrowed(&text));
+                Ok(RawMessage::Text { text, message })
+            }
+            Some(Ok(frame)) => Ok(RawMessage::Frame(frame)),
+            Some(Err(error)) => Err(error),
+            None => return Poll::Ready(None),
+        };
+        Poll::Ready(Some(frame))
+    }
+}
+
+impl<G, S, MI, MO, T> Sink<MessageOut<G, S, MO>> for RawSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Sink::<MessageOut<G, S, MO>>::poll_ready(Pin::new(&mut self.get_mut().inner), cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        Pin::new(&mut self.get_mut().inner).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Sink::<MessageOut<G, S, MO>>::poll_flush(Pin::new(&mut self.get_mut().inner), cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        Sink::<MessageOut<G, S, MO>>::poll_close(Pin::new(&mut self.get_mut().inner), cx)
+    }
+}
+
+impl<G, S, MI, MO, T> Sink<tungstenite::Message> for RawSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_with_retry(cx, Sink::poll_ready)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: tungstenite::Message) -> Result<(), Self::Error> {
+        Pin::new(&mut self.get_mut().inner.inner)
+            .start_send(item)
+            .map_err(StreamDeckSocketError::from)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_with_retry(cx, Sink::poll_flush)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_with_retry(cx, Sink::poll_close)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::RawMessage;
+    use crate::socket::Registration;
+    use crate::{Message, StreamDeckSocket};
+    use futures::prelude::*;
+    use serde_json::Value;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn raw() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .into_raw();
+
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+            socket
+                .send(tungstenite::Message::Text("custom".to_string()))
+                .await
+                .unwrap();
+            assert_eq!(
+                "custom",
+                stream_deck
+                    .next()
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .to_text()
+                    .unwrap()
+            );
+
+            let text = r#"{"event":"systemDidWakeUp","extension":true}"#;
+            stream_deck
+                .send(tungstenite::Message::Text(text.to_string()))
+                .await
+                .unwrap();
+            stream_deck.close(None).await.unwrap();
+            match socket.next().await {
+                Some(Ok(RawMessage::Text {
+                    text: received,
+                    message: Ok(Message::SystemDidWakeUp),
+                })) => assert_eq!(text, received),
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(matches!(
+                socket.next().await,
+                Some(Ok(RawMessage::Frame(tungstenite::Message::Close(_))))
+            ));
+        });
+    }
+}
@@ -0,0 +1,468 @@
+use super::{ConnectError, StreamDeckSocket, StreamDeckSocketError};
+use crate::registration::RegistrationParams;
+use crate::{Message, MessageOut};
+use futures::future::BoxFuture;
+use futures::prelude::*;
+use serde::{de, ser};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// How long to wait between attempts to reconnect.
+///
+/// The first attempt is made after `initial`, and each following attempt waits `factor` times
+/// longer than the one before, up to `max`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Backoff {
+    /// The delay before the first attempt.
+    pub initial: Duration,
+    /// The longest delay between attempts.
+    pub max: Duration,
+    /// How much the delay grows after each attempt.
+    pub factor: u32,
+    /// The number of attempts to make before giving up, or None to keep trying.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_secs(30),
+            factor: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// The delay before an attempt, counting from 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self.factor.saturating_pow(attempt.saturating_sub(1));
+        self.initial
+            .checked_mul(factor)
+            .map_or(self.max, |delay| delay.min(self.max))
+    }
+}
+
+/// Something that happened on a [`ReconnectingSocket`](struct.ReconnectingSocket.html).
+#[derive(Debug)]
+pub enum ConnectionEvent<G, S, MI> {
+    /// A message was received from the Stream Deck software.
+    Received(Message<G, S, MI>),
+    /// The connection was lost. Messages cannot be sent until it is reconnected.
+    Disconnected,
+    /// An attempt to reconnect will be made after a delay.
+    Reconnecting {
+        /// The number of the attempt, counting from 1.
+        attempt: u32,
+        /// How long until the attempt.
+        delay: Duration,
+    },
+    /// An attempt to reconnect failed.
+    ReconnectFailed {
+        /// The number of the attempt, counting from 1.
+        attempt: u32,
+        /// Why the attempt failed.
+        error: ConnectError,
+    },
+    /// The connection was reestablished and the plugin registered again.
+    Reconnected,
+}
+
+enum State<G, S, MI, MO> {
+    Connected(Box<StreamDeckSocket<G, S, MI, MO>>),
+    Retrying,
+    Waiting(Pin<Box<tokio::time::Sleep>>),
+    Connecting(BoxFuture<'static, Result<StreamDeckSocket<G, S, MI, MO>, ConnectError>>),
+    Closed,
+}
+
+/// A [`StreamDeckSocket`](struct.StreamDeckSocket.html) that reconnects when the connection is
+/// lost.
+///
+/// When the connection is lost, the socket reconnects with increasing delays, registering with
+/// the same parameters as the first time, and reports each step as a
+/// [`ConnectionEvent`](enum.ConnectionEvent.html). Sending while disconnected fails with
+/// [`StreamDeckSocketError::NotConnected`](enum.StreamDeckSocketError.html#variant.NotConnected).
+///
+/// The stream ends if the [`Backoff`](struct.Backoff.html) runs out of attempts.
+///
+/// Each connection is a new `StreamDeckSocket`, so settings such as keep-alive pings must be
+/// applied with [`with_configure`](#method.with_configure) to last after reconnecting.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use futures::prelude::*;
+/// # use serde_json::Value;
+/// # use std::env;
+/// # use streamdeck_rs::registration::RegistrationParams;
+/// # use streamdeck_rs::socket::{Backoff, ConnectionEvent, ReconnectingSocket};
+/// # async fn run() {
+/// let params = RegistrationParams::from_args(env::args()).unwrap();
+/// let mut socket = ReconnectingSocket::<Value, Value, Value, Value>::connect(params, Backoff::default())
+///     .await
+///     .expect("connection failed")
+///     .with_configure(|socket| socket.with_message_cache());
+/// while let Some(event) = socket.next().await {
+///     match event {
+///         Ok(ConnectionEvent::Received(message)) => println!("received: {:?}", message),
+///         Ok(event) => println!("connection: {:?}", event),
+///         Err(error) => println!("error: {}", error),
+///     }
+/// }
+/// # }
+/// ```
+pub struct ReconnectingSocket<G, S, MI, MO> {
+    params: RegistrationParams,
+    backoff: Backoff,
+    attempt: u32,
+    state: State<G, S, MI, MO>,
+    configure: Option<Configure<G, S, MI, MO>>,
+}
+
+type Configure<G, S, MI, MO> =
+    Box<dyn FnMut(StreamDeckSocket<G, S, MI, MO>) -> StreamDeckSocket<G, S, MI, MO> + Send>;
+
+impl<G, S, MI, MO> ReconnectingSocket<G, S, MI, MO>
+where
+    G: Send + 'static,
+    S: Send + 'static,
+    MI: Send + 'static,
+    MO: Send + 'static,
+{
+    /// Connects to the Stream Deck software using the parameters given to the plugin.
+    ///
+    /// Failing to make the first connection is returned as an error rather than retried.
+    pub async fn connect(
+        params: RegistrationParams,
+        backoff: Backoff,
+    ) -> Result<Self, ConnectError> {
+        let socket = StreamDeckSocket::connect_with(params.clone()).await?;
+        Ok(ReconnectingSocket {
+            params,
+            backoff,
+            attempt: 0,
+            state: State::Connected(Box::new(socket)),
+            configure: None,
+        })
+    }
+
+    /// Sets up each connection, such as with
+    /// [`with_keep_alive`](struct.StreamDeckSocket.html#method.with_keep_alive).
+    ///
+    /// This is applied to the current connection immediately and to every connection made after
+    /// reconnecting.
+    pub fn with_configure<F>(mut self, configure: F) -> Self
+    where
+        F: FnMut(StreamDeckSocket<G, S, MI, MO>) -> StreamDeckSocket<G, S, MI, MO> + Send + 'static,
+    {
+        self.configure = Some(Box::new(configure));
+        match std::mem::replace(&mut self.state, State::Closed) {
+            State::Connected(socket) => self.connected(*socket),
+            state => self.state = state,
+        }
+        self
+    }
+
+    /// Gets the current connection, if connected.
+    pub fn get_ref(&self) -> Option<&StreamDeckSocket<G, S, MI, MO>> {
+        match &self.state {
+            State::Connected(socket) => Some(socket),
+            _ => None,
+        }
+    }
+
+    /// Gets the current connection, if connected.
+    pub fn get_mut(&mut self) -> Option<&mut StreamDeckSocket<G, S, MI, MO>> {
+        match &mut self.state {
+            State::Connected(socket) => Some(socket),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the socket is currently connected.
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected(_))
+    }
+
+    /// Uses a new connection, after setting it up.
+    fn connected(&mut self, socket: StreamDeckSocket<G, S, MI, MO>) {
+        let socket = match &mut self.configure {
+            Some(configure) => configure(socket),
+            None => socket,
+        };
+        self.state = State::Connected(Box::new(socket));
+    }
+
+    /// Schedules the next attempt, or closes the socket if there are no attempts left.
+    fn retry(&mut self) -> Option<ConnectionEvent<G, S, MI>> {
+        self.attempt += 1;
+        if self
+            .backoff
+            .max_attempts
+            .is_some_and(|max_attempts| self.attempt > max_attempts)
+        {
+            self.state = State::Closed;
+            return None;
+        }
+        let delay = self.backoff.delay(self.attempt);
+        self.state = State::Waiting(Box::pin(tokio::time::sleep(delay)));
+        Some(ConnectionEvent::Reconnecting {
+            attempt: self.attempt,
+            delay,
+        })
+    }
+}
+
+// The state only holds the socket and boxed futures.
+impl<G, S, MI, MO> Unpin for ReconnectingSocket<G, S, MI, MO> {}
+
+impl<G, S, MI, MO> Stream for ReconnectingSocket<G, S, MI, MO>
+where
+    G: de::DeserializeOwned + Send + 'static,
+    S: de::DeserializeOwned + Send + 'static,
+    MI: de::DeserializeOwned + Send + 'static,
+    MO: Send + 'static,
+{
+    type Item = Result<ConnectionEvent<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match &mut this.state {
+                State::Connected(socket) => {
+                    return match futures::ready!(socket.poll_next_unpin(cx)) {
+                        Some(Ok(message)) => {
+                            Poll::Ready(Some(Ok(ConnectionEvent::Received(message))))
+                        }
+                        Some(Err(error)) => Poll::Ready(Some(Err(error))),
+                        None => {
+                            this.attempt = 0;
+                            this.state = State::Retrying;
+                            Poll::Ready(Some(Ok(ConnectionEvent::Disconnected)))
+                        }
+                    };
+                }
+                State::Retrying => return Poll::Ready(this.retry().map(Ok)),
+                State::Waiting(delay) => {
+                    futures::ready!(delay.as_mut().poll(cx));
+                    let params = this.params.clone();
+                    this.state = State::Connecting(StreamDeckSocket::connect_with(params).boxed());
+                }
+                State::Connecting(connecting) => {
+                    return match futures::ready!(connecting.as_mut().poll(cx)) {
+                        Ok(socket) => {
+                            this.connected(socket);
+                            Poll::Ready(Some(Ok(ConnectionEvent::Reconnected)))
+                        }
+                        Err(error) => {
+                            this.state = State::Retrying;
+                            Poll::Ready(Some(Ok(ConnectionEvent::ReconnectFailed {
+                                attempt: this.attempt,
+                                error,
+                            })))
+                        }
+                    };
+                }
+                State::Closed => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+impl<G, S, MI, MO> Sink<MessageOut<G, S, MO>> for ReconnectingSocket<G, S, MI, MO>
+where
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().state {
+            State::Connected(socket) => socket.poll_ready_unpin(cx),
+            _ => Poll::Ready(Err(StreamDeckSocketError::NotConnected)),
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        match &mut self.get_mut().state {
+            State::Connected(socket) => socket.start_send_unpin(item),
+            _ => Err(StreamDeckSocketError::NotConnected),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        match &mut self.get_mut().state {
+            State::Connected(socket) => socket.poll_flush_unpin(cx),
+            _ => Poll::Ready(Err(StreamDeckSocketError::NotConnected)),
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if let State::Connected(socket) = &mut this.state {
+            futures::ready!(socket.poll_close_unpin(cx))?;
+        }
+        // closing on purpose should not start reconnecting
+        this.state = State::Closed;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Backoff, ConnectionEvent, ReconnectingSocket};
+    use crate::registration::{RegistrationInfo, RegistrationParams};
+    use crate::{Message, MessageOut};
+    use futures::prelude::*;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    fn params(port: u16) -> RegistrationParams {
+        let info: RegistrationInfo = serde_json::from_str(
+            r#"{
+                "application": {"language": "en", "platform": "mac", "version": "4.1.0"},
+                "plugin": {"uuid": "com.example.plugin", "version": "1.0"},
+                "devicePixelRatio": 2,
+                "devices": []
+            }"#,
+        )
+        .unwrap();
+        RegistrationParams {
+            port,
+            uuid: "uuid".to_string(),
+            event: "registerPlugin".to_string(),
+            info,
+        }
+    }
+
+    #[test]
+    fn backoff() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            ..Backoff::default()
+        };
+        assert_eq!(Duration::from_millis(100), backoff.delay(1));
+        assert_eq!(Duration::from_millis(400), backoff.delay(3));
+        assert_eq!(Duration::from_millis(500), backoff.delay(4));
+        assert_eq!(Duration::from_millis(500), backoff.delay(100));
+    }
+
+    #[test]
+    fn reconnect() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                for _ in 0..2 {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    let registration = stream.next().await.unwrap().unwrap();
+                    assert!(registration.to_text().unwrap().contains("registerPlugin"));
+                    stream
+                        .send(tungstenite::Message::Text(
+                            r#"{"event":"systemDidWakeUp"}"#.to_string(),
+                        ))
+                        .await
+                        .unwrap();
+                    stream.close(None).await.unwrap();
+                }
+            });
+
+            let params = params(port);
+            let backoff = Backoff {
+                initial: Duration::from_millis(1),
+                max_attempts: Some(1),
+                ..Backoff::default()
+            };
+            let mut socket =
+                ReconnectingSocket::<Value, Value, Value, Value>::connect(params, backoff)
+                    .await
+                    .unwrap();
+            let mut events = Vec::new();
+            while let Some(event) = socket.next().await {
+                events.push(event.unwrap());
+                if let Some(ConnectionEvent::Disconnected) = events.last() {
+                    assert!(!socket.is_connected());
+                    assert!(socket.send(MessageOut::log_message("lost")).await.is_err());
+                }
+            }
+            server.await.unwrap();
+
+            assert!(matches!(
+                events.as_slice(),
+                [
+                    ConnectionEvent::Received(Message::SystemDidWakeUp),
+                    ConnectionEvent::Disconnected,
+                    ConnectionEvent::Reconnecting { attempt: 1, .. },
+                    ConnectionEvent::Reconnected,
+                    ConnectionEvent::Received(Message::SystemDidWakeUp),
+                    ConnectionEvent::Disconnected,
+                    ConnectionEvent::Reconnecting { attempt: 1, .. },
+                    ConnectionEvent::ReconnectFailed { attempt: 1, .. },
+                ]
+            ));
+        });
+    }
+
+    #[test]
+    fn configure() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::spawn(async move {
+                for _ in 0..2 {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    stream.next().await.unwrap().unwrap();
+                    // keep-alive pings are sent on every connection
+                    let ping = stream.next().await.unwrap().unwrap();
+                    assert!(ping.is_ping());
+                    stream.close(None).await.unwrap();
+                }
+            });
+
+            let backoff = Backoff {
+                initial: Duration::from_millis(1),
+                max_attempts: Some(1),
+                ..Backoff::default()
+            };
+            let configured = Arc::new(AtomicUsize::new(0));
+            let counter = configured.clone();
+            let mut socket =
+                ReconnectingSocket::<Value, Value, Value, Value>::connect(params(port), backoff)
+                    .await
+                    .unwrap()
+                    .with_configure(move |socket| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        socket.with_keep_alive(Duration::from_millis(10), Duration::from_millis(50))
+                    });
+            assert_eq!(1, configured.load(Ordering::SeqCst));
+            let mut reconnected = false;
+            while let Some(event) = socket.next().await {
+                // a ping may fail to send while the server is closing the connection
+                if let Ok(ConnectionEvent::Reconnected) = event {
+                    reconnected = true;
+                    assert_eq!(2, configured.load(Ordering::SeqCst));
+                }
+            }
+            assert!(reconnected);
+            server.await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,191 @@
+use super::{StreamDeckSocket, StreamDeckSocketError, Transport};
+use futures::channel::oneshot;
+use futures::prelude::*;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tungstenite::error::ProtocolError;
+use tungstenite::protocol::frame::coding::CloseCode;
+use tungstenite::protocol::CloseFrame;
+
+/// Resolves when the connection of a [`StreamDeckSocket`](struct.StreamDeckSocket.html) ends,
+/// created by [`StreamDeckSocket::shutdown`](struct.StreamDeckSocket.html#method.shutdown).
+///
+/// The output is the close frame sent by the Stream Deck software, if it sent one. The future
+/// also resolves if the socket is dropped.
+pub struct Shutdown {
+    receiver: oneshot::Receiver<Option<CloseFrame<'static>>>,
+}
+
+impl Future for Shutdown {
+    type Output = Option<CloseFrame<'static>>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        self.receiver
+            .poll_unpin(cx)
+            .map(|frame| frame.unwrap_or_default())
+    }
+}
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Gets the close frame sent by the Stream Deck software, which gives the code and reason for
+    /// closing the connection.
+    ///
+    /// This is only available after the socket has received it, and not if the connection was
+    /// lost without one.
+    pub fn close_frame(&self) -> Option<&CloseFrame<'static>> {
+        self.close_frame.as_ref()
+    }
+
+    /// Creates a future that resolves when the connection ends.
+    ///
+    /// The socket must still be polled for the end of the connection to be noticed, so this is
+    /// for other parts of a plugin, such as background tasks, that need to exit when the Stream
+    /// Deck software closes the connection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use futures::prelude::*;
+    /// # use serde_json::Value;
+    /// # use streamdeck_rs::StreamDeckSocket;
+    /// # async fn run(mut socket: StreamDeckSocket<Value, Value, Value, Value>) {
+    /// let shutdown = socket.shutdown();
+    /// tokio::spawn(async move {
+    ///     if let Some(frame) = shutdown.await {
+    ///         eprintln!("closed by the Stream Deck software: {}", frame);
+    ///     }
+    /// });
+    /// while let Some(message) = socket.next().await {
+    ///     println!("received: {:?}", message);
+    /// }
+    /// # }
+    /// ```
+    pub fn shutdown(&mut self) -> Shutdown {
+        let (sender, receiver) = oneshot::channel();
+        if self.terminated {
+            let _ = sender.send(self.close_frame.clone());
+        } else {
+            self.shutdown.push(sender);
+        }
+        Shutdown { receiver }
+    }
+
+    /// Closes the connection, waiting for the Stream Deck software to acknowledge it.
+    ///
+    /// Unlike closing the socket as a `Sink`, this sends a normal close code, and messages
+    /// received while waiting for the Stream Deck software are discarded. Keep-alive pings are
+    /// stopped.
+    pub async fn close(&mut self) -> Result<(), StreamDeckSocketError> {
+        if self.terminated {
+            return Ok(());
+        }
+        self.keep_alive = None;
+        future::poll_fn(|cx| self.poll_with_retry(cx, Sink::poll_ready)).await?;
+        let frame = CloseFrame {
+            code: CloseCode::Normal,
+            reason: "".into(),
+        };
+        match Pin::new(&mut self.inner).start_send(tungstenite::Message::Close(Some(frame))) {
+            // the Stream Deck software closed the connection first, and has already been answered
+            Ok(()) | Err(tungstenite::Error::Protocol(ProtocolError::SendAfterClosing)) => {}
+            Err(error) => return Err(StreamDeckSocketError::from(error)),
+        }
+        future::poll_fn(|cx| self.poll_with_retry(cx, Sink::poll_flush)).await?;
+        while let Some(frame) = future::poll_fn(|cx| self.poll_frame(cx)).await {
+            frame?;
+        }
+        Ok(())
+    }
+
+    /// Marks the socket as ended, resolving the shutdown futures.
+    pub(super) fn terminate(&mut self) {
+        self.terminated = true;
+        for sender in self.shutdown.drain(..) {
+            let _ = sender.send(self.close_frame.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::socket::Registration;
+    use crate::StreamDeckSocket;
+    use futures::prelude::*;
+    use serde_json::Value;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::frame::coding::CloseCode;
+    use tungstenite::protocol::{CloseFrame, Role};
+
+    #[test]
+    fn shutdown() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            let shutdown = socket.shutdown();
+            let frame = CloseFrame {
+                code: CloseCode::Away,
+                reason: "quitting".into(),
+            };
+            stream_deck.close(Some(frame.clone())).await.unwrap();
+            let stream_deck = async move {
+                // the connection ends once the reply is received
+                while stream_deck.next().await.is_some() {}
+            };
+            let (message, ()) = future::join(socket.next(), stream_deck).await;
+            assert!(message.is_none());
+            assert_eq!(Some(&frame), socket.close_frame());
+            assert_eq!(Some(frame.clone()), shutdown.await);
+            assert_eq!(Some(frame), socket.shutdown().await);
+            socket.close().await.unwrap();
+        });
+    }
+
+    #[test]
+    fn close() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            let stream_deck = async move {
+                let frame = stream_deck.next().await.unwrap().unwrap();
+                // the reply is sent by the next write, which then ends the connection
+                let _ = stream_deck.flush().await;
+                frame
+            };
+            let (result, frame) = future::join(socket.close(), stream_deck).await;
+            result.unwrap();
+            match frame {
+                tungstenite::Message::Close(Some(frame)) => {
+                    assert_eq!(CloseCode::Normal, frame.code)
+                }
+                other => panic!("unexpected {:?}", other),
+            }
+            assert!(socket.next().await.is_none());
+        });
+    }
+}
@@ -0,0 +1,195 @@
+use super::{StreamDeckSocket, StreamDeckSocketError};
+use crate::{Message, MessageOut};
+use futures::prelude::*;
+use futures::stream::{SplitSink, SplitStream};
+use serde::{de, ser};
+use std::error::Error;
+use std::fmt;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// The receiving half of a [`StreamDeckSocket`](struct.StreamDeckSocket.html), created by
+/// [`into_split`](struct.StreamDeckSocket.html#method.into_split).
+pub struct ReadHalf<G, S, MI, MO> {
+    inner: SplitStream<StreamDeckSocket<G, S, MI, MO>>,
+}
+
+/// The sending half of a [`StreamDeckSocket`](struct.StreamDeckSocket.html), created by
+/// [`into_split`](struct.StreamDeckSocket.html#method.into_split).
+pub struct WriteHalf<G, S, MI, MO> {
+    inner: SharedSink<G, S, MI, MO>,
+}
+
+type SharedSink<G, S, MI, MO> = SplitSink<StreamDeckSocket<G, S, MI, MO>, MessageOut<G, S, MO>>;
+type Halves<G, S, MI, MO> = (ReadHalf<G, S, MI, MO>, WriteHalf<G, S, MI, MO>);
+type Reunited<G, S, MI, MO> = Result<StreamDeckSocket<G, S, MI, MO>, ReuniteError<G, S, MI, MO>>;
+
+/// The halves given to [`ReadHalf::reunite`](struct.ReadHalf.html#method.reunite) when they are
+/// from different sockets.
+pub struct ReuniteError<G, S, MI, MO> {
+    halves: Box<Halves<G, S, MI, MO>>,
+}
+
+impl<G, S, MI, MO> ReuniteError<G, S, MI, MO> {
+    /// Gets the halves back.
+    pub fn into_inner(self) -> Halves<G, S, MI, MO> {
+        *self.halves
+    }
+}
+
+impl<G, S, MI, MO> fmt::Debug for ReuniteError<G, S, MI, MO> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("ReuniteError").finish()
+    }
+}
+
+impl<G, S, MI, MO> fmt::Display for ReuniteError<G, S, MI, MO> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "tried to reunite halves of different sockets")
+    }
+}
+
+impl<G, S, MI, MO> Error for ReuniteError<G, S, MI, MO> {}
+
+impl<G, S, MI, MO> StreamDeckSocket<G, S, MI, MO>
+where
+    G: ser::Serialize + de::DeserializeOwned,
+    S: ser::Serialize + de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+    MO: ser::Serialize,
+{
+    /// Splits the socket into halves for receiving and sending, which can be used from different
+    /// tasks.
+    ///
+    /// The halves share the socket, and each waits briefly while the other is using it. Use
+    /// [`ReadHalf::reunite`](struct.ReadHalf.html#method.reunite) to get the socket back, such as
+    /// to read the devices it has seen.
+    pub fn into_split(self) -> Halves<G, S, MI, MO> {
+        let (sink, stream) = self.split();
+        (ReadHalf { inner: stream }, WriteHalf { inner: sink })
+    }
+}
+
+impl<G, S, MI, MO> ReadHalf<G, S, MI, MO>
+where
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    /// Joins the halves back into the socket, or returns them if they are from different sockets.
+    pub fn reunite(self, other: WriteHalf<G, S, MI, MO>) -> Reunited<G, S, MI, MO> {
+        self.inner
+            .reunite(other.inner)
+            .map_err(|error| ReuniteError {
+                halves: Box::new((ReadHalf { inner: error.1 }, WriteHalf { inner: error.0 })),
+            })
+    }
+}
+
+impl<G, S, MI, MO> Stream for ReadHalf<G, S, MI, MO>
+where
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+{
+    type Item = Result<Message<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        self.inner.poll_next_unpin(cx)
+    }
+}
+
+impl<G, S, MI, MO> Sink<MessageOut<G, S, MO>> for WriteHalf<G, S, MI, MO>
+where
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready_unpin(cx)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        self.inner.start_send_unpin(item)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_flush_unpin(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_close_unpin(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::socket::StreamDeckSocket;
+    use crate::{Message, MessageOut};
+    use futures::prelude::*;
+    use serde_json::Value;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn tasks() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let local = tokio::task::LocalSet::new();
+        local.block_on(&runtime, async {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let server = tokio::task::spawn_local(async move {
+                let mut streams = Vec::new();
+                for _ in 0..2 {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    let mut stream = tokio_tungstenite::accept_async(stream).await.unwrap();
+                    stream.next().await.unwrap().unwrap();
+                    streams.push(stream);
+                }
+                let stream = &mut streams[0];
+                let message = stream.next().await.unwrap().unwrap();
+                let message: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+                assert_eq!("showOk", message["event"]);
+                let reply = r#"{"event":"systemDidWakeUp"}"#.to_string();
+                stream
+                    .send(tungstenite::Message::Text(reply))
+                    .await
+                    .unwrap();
+                streams
+            });
+
+            let connect = || {
+                StreamDeckSocket::<Value, Value, Value, Value>::connect(
+                    port,
+                    "registerPlugin".to_string(),
+                    "uuid".to_string(),
+                )
+            };
+            let (mut read_a, mut write_a) = connect().await.unwrap().into_split();
+            let (read_b, write_b) = connect().await.unwrap().into_split();
+
+            let reader = tokio::task::spawn_local(async move {
+                let message = read_a.next().await.unwrap().unwrap();
+                assert_eq!(Message::SystemDidWakeUp, message);
+                read_a
+            });
+            let writer = tokio::task::spawn_local(async move {
+                write_a.send(MessageOut::show_ok("abc")).await.unwrap();
+                write_a
+            });
+            let (read_a, write_a) = (reader.await.unwrap(), writer.await.unwrap());
+
+            let (read_a, write_b) = match read_a.reunite(write_b) {
+                Ok(_) => panic!("reunited halves of different sockets"),
+                Err(error) => error.into_inner(),
+            };
+            assert!(read_a.reunite(write_a).is_ok());
+            assert!(read_b.reunite(write_b).is_ok());
+            server.await.unwrap();
+        });
+    }
+}
@@ -0,0 +1,251 @@
+use super::{Inner, StreamDeckSocket, StreamDeckSocketError, Transport};
+use crate::{Message, MessageOut};
+use futures::prelude::*;
+use serde::{de, ser};
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+/// Something that happened on a [`SupervisedSocket`](struct.SupervisedSocket.html).
+#[derive(Debug)]
+pub enum SocketEvent<G, S, MI> {
+    /// A message was received from the Stream Deck software.
+    Received(Message<G, S, MI>),
+    /// A message was sent to the Stream Deck software.
+    Sent(SentMessage),
+    /// A message could not be sent to the Stream Deck software.
+    ///
+    /// The error is returned to the sender, so only its description is included here.
+    SendFailed(SentMessage, String),
+}
+
+/// Identifies a message sent through a [`SupervisedSocket`](struct.SupervisedSocket.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SentMessage {
+    /// The name of the event, e.g. `setTitle`.
+    pub event: &'static str,
+    /// The instance of the action the message was for, if any.
+    pub context: Option<crate::Context>,
+}
+
+impl SentMessage {
+    fn new<G, S, MO>(message: &MessageOut<G, S, MO>) -> Self {
+        SentMessage {
+            event: message.event(),
+            context: message.context().cloned(),
+        }
+    }
+}
+
+/// A [`StreamDeckSocket`](struct.StreamDeckSocket.html) that reports the outcome of sending
+/// messages alongside the messages it receives.
+///
+/// This allows a single loop to observe everything happening on the connection. Messages count
+/// as sent once they have been flushed.
+///
+/// Created by [`StreamDeckSocket::supervised`](struct.StreamDeckSocket.html#method.supervised).
+pub struct SupervisedSocket<G, S, MI, MO, T = Inner> {
+    inner: StreamDeckSocket<G, S, MI, MO, T>,
+    unflushed: Vec<SentMessage>,
+    outcomes: VecDeque<SocketEvent<G, S, MI>>,
+    waker: Option<Waker>,
+}
+
+impl<G, S, MI, MO, T: Unpin> Unpin for SupervisedSocket<G, S, MI, MO, T> {}
+
+impl<G, S, MI, MO, T: Transport> StreamDeckSocket<G, S, MI, MO, T> {
+    /// Wraps the socket so that it reports the outcome of sending messages.
+    pub fn supervised(self) -> SupervisedSocket<G, S, MI, MO, T> {
+        SupervisedSocket {
+            inner: self,
+            unflushed: Vec::new(),
+            outcomes: VecDeque::new(),
+            waker: None,
+        }
+    }
+}
+
+impl<G, S, MI, MO, T: Transport> SupervisedSocket<G, S, MI, MO, T> {
+    /// Gets the underlying socket.
+    pub fn get_ref(&self) -> &StreamDeckSocket<G, S, MI, MO, T> {
+        &self.inner
+    }
+
+    /// Gets the underlying socket.
+    pub fn get_mut(&mut self) -> &mut StreamDeckSocket<G, S, MI, MO, T> {
+        &mut self.inner
+    }
+
+    /// Unwraps the underlying socket, discarding any unreported outcomes.
+    pub fn into_inner(self) -> StreamDeckSocket<G, S, MI, MO, T> {
+        self.inner
+    }
+
+    fn report(&mut self, event: SocketEvent<G, S, MI>) {
+        self.outcomes.push_back(event);
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn report_flush(&mut self, result: &Result<(), StreamDeckSocketError>) {
+        if self.unflushed.is_empty() {
+            return;
+        }
+        match result {
+            Ok(()) => {
+                for sent in std::mem::take(&mut self.unflushed) {
+                    self.report(SocketEvent::Sent(sent));
+                }
+            }
+            Err(error) => {
+                let error = error.to_string();
+                for sent in std::mem::take(&mut self.unflushed) {
+                    self.report(SocketEvent::SendFailed(sent, error.clone()));
+                }
+            }
+        }
+    }
+}
+
+impl<G, S, MI, MO, T> Stream for SupervisedSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: de::DeserializeOwned,
+    S: de::DeserializeOwned,
+    MI: de::DeserializeOwned,
+{
+    type Item = Result<SocketEvent<G, S, MI>, StreamDeckSocketError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(event) = this.outcomes.pop_front() {
+            return Poll::Ready(Some(Ok(event)));
+        }
+        match this.inner.poll_next_unpin(cx) {
+            Poll::Ready(Some(Ok(message))) => Poll::Ready(Some(Ok(SocketEvent::Received(message)))),
+            Poll::Ready(Some(Err(error))) => Poll::Ready(Some(Err(error))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => {
+                this.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<G, S, MI, MO, T> Sink<MessageOut<G, S, MO>> for SupervisedSocket<G, S, MI, MO, T>
+where
+    T: Transport,
+    G: ser::Serialize,
+    S: ser::Serialize,
+    MO: ser::Serialize,
+{
+    type Error = StreamDeckSocketError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().inner.poll_ready_unpin(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: MessageOut<G, S, MO>) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        let sent = SentMessage::new(&item);
+        match this.inner.start_send_unpin(item) {
+            Ok(()) => {
+                this.unflushed.push(sent);
+                Ok(())
+            }
+            Err(error) => {
+                this.report(SocketEvent::SendFailed(sent, error.to_string()));
+                Err(error)
+            }
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let result = futures::ready!(this.inner.poll_flush_unpin(cx));
+        this.report_flush(&result);
+        Poll::Ready(result)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let result = futures::ready!(this.inner.poll_close_unpin(cx));
+        this.report_flush(&result);
+        Poll::Ready(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SentMessage, SocketEvent};
+    use crate::socket::Registration;
+    use crate::version::Version;
+    use crate::{Message, MessageOut, StreamDeckSocket};
+    use futures::prelude::*;
+    use serde_json::Value;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    #[test]
+    fn outcomes() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let (plugin, stream_deck) = tokio::io::duplex(4096);
+            let transport = WebSocketStream::from_raw_socket(plugin, Role::Client, None).await;
+            let mut socket = StreamDeckSocket::<Value, Value, Value, Value, _>::from_transport(
+                transport,
+                Registration::new("registerPlugin", "uuid"),
+            )
+            .await
+            .unwrap()
+            .with_target_version(Version::new(6, 0))
+            .supervised();
+            let mut stream_deck =
+                WebSocketStream::from_raw_socket(stream_deck, Role::Server, None).await;
+            stream_deck.next().await.unwrap().unwrap();
+
+            socket.send(MessageOut::show_ok("a")).await.unwrap();
+            match socket.next().await.unwrap().unwrap() {
+                SocketEvent::Sent(sent) => assert_eq!(
+                    SentMessage {
+                        event: "showOk",
+                        context: Some("a".into()),
+                    },
+                    sent
+                ),
+                other => panic!("unexpected {:?}", other),
+            }
+            let message = stream_deck.next().await.unwrap().unwrap();
+            assert!(message.to_text().unwrap().contains("showOk"));
+
+            // setTriggerDescription needs a newer version than the target
+            let message = MessageOut::set_trigger_description("b").touch("go");
+            assert!(socket.send(message.build()).await.is_err());
+            match socket.next().await.unwrap().unwrap() {
+                SocketEvent::SendFailed(sent, _) => assert_eq!(
+                    SentMessage {
+                        event: "setTriggerDescription",
+                        context: Some("b".into()),
+                    },
+                    sent
+                ),
+                other => panic!("unexpected {:?}", other),
+            }
+
+            stream_deck
+                .send(tungstenite::Message::Text(
+                    r#"{"event":"systemDidWakeUp"}"#.to_string(),
+                ))
+                .await
+                .unwrap();
+            match socket.next().await.unwrap().unwrap() {
+                SocketEvent::Received(Message::SystemDidWakeUp) => {}
+                other => panic!("unexpected {:?}", other),
+            }
+        });
+    }
+}
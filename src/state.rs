@@ -0,0 +1,161 @@
+//! Typed states for actions with more than one state.
+//!
+//! The Stream Deck software identifies states by their index in the plugin manifest. Define an
+//! enum with the [`action_state!`](../macro.action_state.html) macro, or implement
+//! [`ActionState`](trait.ActionState.html) by hand, to use names for the states instead.
+
+use super::{
+    Context, KeyPayload, MessageOut, StatePayload, TitleParametersPayload, VisibilityPayload,
+};
+use failure::Fail;
+use std::convert::TryFrom;
+
+/// A state of an action, converted to and from the index of the state in the plugin manifest.
+pub trait ActionState: Copy + Into<u8> + TryFrom<u8> {}
+
+/// An error indicating a state index does not correspond to a known state.
+#[derive(Debug, Fail)]
+#[fail(display = "invalid state: {}", _0)]
+pub struct InvalidState(pub u8);
+
+/// Defines an enum of the states of an action, in the order they appear in the plugin manifest.
+///
+/// The enum implements [`ActionState`](state/trait.ActionState.html), so it can be converted to
+/// and from the state index.
+///
+/// # Examples
+///
+/// ```
+/// # use serde_json::Value;
+/// # use std::convert::TryFrom;
+/// # use streamdeck_rs::{action_state, MessageOut};
+/// action_state! {
+///     /// Whether the light is on.
+///     pub enum Light {
+///         Off,
+///         On,
+///     }
+/// }
+///
+/// let message: MessageOut<Value, Value, Value> =
+///     MessageOut::set_action_state("context", Light::On);
+/// assert_eq!(Light::Off, Light::try_from(0).unwrap());
+/// ```
+#[macro_export]
+macro_rules! action_state {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        #[repr(u8)]
+        $vis enum $name {
+            $($(#[$variant_attr])* $variant),+
+        }
+
+        impl ::std::convert::From<$name> for u8 {
+            fn from(state: $name) -> u8 {
+                state as u8
+            }
+        }
+
+        impl ::std::convert::TryFrom<u8> for $name {
+            type Error = $crate::state::InvalidState;
+
+            fn try_from(index: u8) -> ::std::result::Result<Self, Self::Error> {
+                $(
+                    if index == $name::$variant as u8 {
+                        return Ok($name::$variant);
+                    }
+                )+
+                Err($crate::state::InvalidState(index))
+            }
+        }
+
+        impl $crate::state::ActionState for $name {}
+    };
+}
+
+impl StatePayload {
+    /// Creates a payload setting a typed state.
+    pub fn from_state<T: ActionState>(state: T) -> Self {
+        StatePayload::new(state.into())
+    }
+
+    /// Gets the state as a typed state, if it is one of its states.
+    pub fn state_as<T: ActionState>(&self) -> Option<T> {
+        T::try_from(self.state).ok()
+    }
+}
+
+impl<S> KeyPayload<S> {
+    /// Gets the state as a typed state, if there is one and it is one of its states.
+    pub fn state_as<T: ActionState>(&self) -> Option<T> {
+        self.state.and_then(|state| T::try_from(state).ok())
+    }
+
+    /// Gets the state the user wanted to switch to in a multi-action as a typed state, if there
+    /// is one and it is one of its states.
+    pub fn user_desired_state_as<T: ActionState>(&self) -> Option<T> {
+        self.user_desired_state
+            .and_then(|state| T::try_from(state).ok())
+    }
+}
+
+impl<S> VisibilityPayload<S> {
+    /// Gets the state as a typed state, if there is one and it is one of its states.
+    pub fn state_as<T: ActionState>(&self) -> Option<T> {
+        self.state.and_then(|state| T::try_from(state).ok())
+    }
+}
+
+impl<S> TitleParametersPayload<S> {
+    /// Gets the state as a typed state, if there is one and it is one of its states.
+    pub fn state_as<T: ActionState>(&self) -> Option<T> {
+        self.state.and_then(|state| T::try_from(state).ok())
+    }
+}
+
+impl<G, S, M> MessageOut<G, S, M> {
+    /// Creates a [SetState](enum.MessageOut.html#variant.SetState) message with a typed state.
+    pub fn set_action_state<C: Into<Context>, T: ActionState>(context: C, state: T) -> Self {
+        MessageOut::SetState {
+            context: context.into(),
+            payload: StatePayload::from_state(state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Location, VisibilityPayload};
+    use serde_json::Value;
+    use std::convert::TryFrom;
+
+    action_state! {
+        enum Mode {
+            Idle,
+            Recording,
+            Paused,
+        }
+    }
+
+    #[test]
+    fn action_state() {
+        assert_eq!(2, u8::from(Mode::Paused));
+        assert_eq!(Mode::Recording, Mode::try_from(1).unwrap());
+        assert!(Mode::try_from(3).is_err());
+
+        let payload = VisibilityPayload {
+            settings: Value::Null,
+            location: Location::MultiAction,
+            state: Some(2),
+            controller: None,
+            extra: Default::default(),
+        };
+        assert_eq!(Some(Mode::Paused), payload.state_as());
+    }
+}
@@ -0,0 +1,247 @@
+//! Versions of the Stream Deck software.
+//!
+//! Messages sent to an older version of the Stream Deck software than the one a field or event
+//! was added in are ignored without an error, so plugins supporting old versions can use
+//! [`MessageOut::for_version`](../enum.MessageOut.html#method.for_version) to find out before
+//! sending them.
+
+use super::MessageOut;
+use failure::Fail;
+use std::fmt;
+use std::num::ParseIntError;
+use std::str::FromStr;
+
+/// A version of the Stream Deck software, such as `6.5.1.19900`.
+///
+/// Missing components are treated as zero, so `6.5` is the same as `6.5.0.0`. When parsing, a
+/// suffix starting with `-` or `+`, such as `-beta`, is ignored.
+///
+/// # Examples
+///
+/// ```
+/// # use streamdeck_rs::version::{Feature, Version};
+/// let version: Version = "6.4.1.19500".parse().unwrap();
+/// assert!(version.supports(Feature::SetTriggerDescription));
+/// assert!(!version.supports(Feature::DeepLinks));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+    /// The major version.
+    pub major: u32,
+    /// The minor version.
+    pub minor: u32,
+    /// The patch version.
+    pub patch: u32,
+    /// The build number.
+    pub build: u32,
+}
+
+impl Version {
+    /// The version of the Stream Deck software implemented by this crate,
+    /// [`SDK_VERSION`](../constant.SDK_VERSION.html).
+    pub const SDK: Version = Version::new(6, 5);
+
+    /// Creates a version from its major and minor components.
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Version {
+            major,
+            minor,
+            patch: 0,
+            build: 0,
+        }
+    }
+
+    /// Checks whether this version of the Stream Deck software supports a feature.
+    pub fn supports(&self, feature: Feature) -> bool {
+        *self >= feature.required_version()
+    }
+}
+
+/// A feature added in a version of the Stream Deck software after 4.0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// The user-provided names of devices.
+    DeviceNames,
+    /// The [SystemDidWakeUp](../enum.Message.html#variant.SystemDidWakeUp) event.
+    SystemDidWakeUp,
+    /// Dials and the touch strip of the Stream Deck +, including the
+    /// [SetFeedback](../enum.MessageOut.html#variant.SetFeedback) and
+    /// [SetFeedbackLayout](../enum.MessageOut.html#variant.SetFeedbackLayout) events.
+    Encoders,
+    /// The [SetTriggerDescription](../enum.MessageOut.html#variant.SetTriggerDescription) event.
+    SetTriggerDescription,
+    /// The [DidReceiveDeepLink](../enum.Message.html#variant.DidReceiveDeepLink) event.
+    DeepLinks,
+    /// Choosing the page to show with
+    /// [SwitchToProfile](../enum.MessageOut.html#variant.SwitchToProfile).
+    ProfilePages,
+}
+
+impl Feature {
+    /// The first version of the Stream Deck software that supports the feature.
+    pub fn required_version(self) -> Version {
+        match self {
+            Feature::DeviceNames | Feature::SystemDidWakeUp => Version::new(4, 3),
+            Feature::Encoders => Version::new(6, 0),
+            Feature::SetTriggerDescription => Version::new(6, 1),
+            Feature::DeepLinks | Feature::ProfilePages => Version::new(6, 5),
+        }
+    }
+}
+
+/// An error parsing a [`Version`](struct.Version.html).
+#[derive(Debug, Fail)]
+pub enum ParseVersionError {
+    /// The version is empty.
+    #[fail(display = "the version is empty")]
+    Empty,
+    /// The version has more than four components.
+    #[fail(display = "the version has too many components")]
+    TooLong,
+    /// A component is not a number.
+    #[fail(display = "invalid version component")]
+    InvalidComponent(#[fail(cause)] ParseIntError),
+}
+
+impl FromStr for Version {
+    type Err = ParseVersionError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        let value = value.split(['-', '+']).next().unwrap_or_default();
+        if value.is_empty() {
+            return Err(ParseVersionError::Empty);
+        }
+        let mut components = [0; 4];
+        for (i, component) in value.split('.').enumerate() {
+            let slot = components.get_mut(i).ok_or(ParseVersionError::TooLong)?;
+            *slot = component
+                .parse()
+                .map_err(ParseVersionError::InvalidComponent)?;
+        }
+        let [major, minor, patch, build] = components;
+        Ok(Version {
+            major,
+            minor,
+            patch,
+            build,
+        })
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)?;
+        if self.patch != 0 || self.build != 0 {
+            write!(f, ".{}", self.patch)?;
+        }
+        if self.build != 0 {
+            write!(f, ".{}", self.build)?;
+        }
+        Ok(())
+    }
+}
+
+/// A message cannot be sent to a version of the Stream Deck software.
+#[derive(Debug, Fail)]
+#[fail(
+    display = "{} requires Stream Deck software version {} but the target is {}",
+    event, required, target
+)]
+pub struct UnsupportedMessage {
+    /// The name of the event.
+    pub event: &'static str,
+    /// The first version of the Stream Deck software that supports the event.
+    pub required: Version,
+    /// The version of the Stream Deck software the message was for.
+    pub target: Version,
+}
+
+impl<G, S, M> MessageOut<G, S, M> {
+    /// The first version of the Stream Deck software that supports the event.
+    pub fn required_version(&self) -> Version {
+        match self {
+            MessageOut::SetFeedback { .. } | MessageOut::SetFeedbackLayout { .. } => {
+                Feature::Encoders.required_version()
+            }
+            MessageOut::SetTriggerDescription { .. } => {
+                Feature::SetTriggerDescription.required_version()
+            }
+            _ => Version::new(4, 0),
+        }
+    }
+
+    /// Adapts the message for a version of the Stream Deck software.
+    ///
+    /// Fields that the version does not support are removed, which for example makes
+    /// [SwitchToProfile](../enum.MessageOut.html#variant.SwitchToProfile) show the first page of
+    /// the profile. Events that the version does not support are an error.
+    ///
+    /// The `state` of [SetTitle](../enum.MessageOut.html#variant.SetTitle) and
+    /// [SetImage](../enum.MessageOut.html#variant.SetImage) is always kept, because it is supported
+    /// by every version of the Stream Deck software since 4.0, the oldest version known to this
+    /// crate.
+    pub fn for_version(mut self, target: Version) -> Result<Self, UnsupportedMessage> {
+        let required = self.required_version();
+        if target < required {
+            return Err(UnsupportedMessage {
+                event: self.event(),
+                required,
+                target,
+            });
+        }
+        if let MessageOut::SwitchToProfile { payload, .. } = &mut self {
+            if !target.supports(Feature::ProfilePages) {
+                payload.page = None;
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Version;
+    use crate::MessageOut;
+    use serde_json::Value;
+
+    #[test]
+    fn for_version() {
+        let version: Version = "6.4.0.19400".parse().unwrap();
+        assert_eq!(
+            Version::new(6, 4),
+            Version {
+                build: 0,
+                ..version
+            }
+        );
+        assert_eq!("6.4.0.19400", version.to_string());
+        assert!(version > Version::new(6, 1));
+        assert!("6.x".parse::<Version>().is_err());
+        assert_eq!(Version::new(6, 5), "6.5-beta".parse().unwrap());
+        assert_eq!(Version::SDK, crate::SDK_VERSION.parse().unwrap());
+
+        let message: MessageOut<Value, Value, Value> =
+            MessageOut::switch_to_profile("abc", "device", "Profile")
+                .page(2)
+                .build();
+        match message.for_version(version).unwrap() {
+            MessageOut::SwitchToProfile { payload, .. } => assert_eq!(None, payload.page),
+            _ => unreachable!(),
+        }
+
+        let message: MessageOut<Value, Value, Value> = MessageOut::set_trigger_description("abc")
+            .push("Mute")
+            .build();
+        assert!(message.clone().for_version(version).is_ok());
+        assert!(message.for_version(Version::new(6, 0)).is_err());
+
+        let message: MessageOut<Value, Value, Value> =
+            MessageOut::set_title("abc").text("Title").state(1).build();
+        match message.for_version(Version::new(4, 0)).unwrap() {
+            MessageOut::SetTitle { payload, .. } => assert_eq!(Some(1), payload.state),
+            _ => unreachable!(),
+        }
+    }
+}